@@ -0,0 +1,362 @@
+//! algorithms.rs — Singine alternative shortest-path searches
+//!
+//!   astar         — A* with an ALT landmark heuristic (no coordinates needed)
+//!   bidirectional — Dijkstra from both ends, stopping once the frontiers
+//!                   can no longer improve the best meeting point
+//!   widest        — maximum-bottleneck path (`--algorithm widest`): the route
+//!                   whose lightest edge is heaviest, e.g. the strongest
+//!                   chain of similarity
+//!
+//! Each search reports its node-expansion count. `compare` runs them next to
+//! plain Dijkstra on the same adjacency and checks they agree on the total
+//! weight — a built-in cross-check for `--mode compare`. One-way arcs are
+//! respected: the backward search and the landmark's distances *to* nodes
+//! run over the reversed graph.
+
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::Instant;
+
+use crate::shortest_path::{dijkstra_counted, distances_from, reversed, Adjacency, PathResult, EPSILON};
+
+// Open-list entry — min-heap by priority
+struct Open<'a> {
+    priority: f64,
+    node:     &'a str,
+}
+
+impl PartialEq for Open<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+impl Eq for Open<'_> {}
+impl Ord for Open<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.partial_cmp(&self.priority).unwrap_or(Ordering::Equal)
+    }
+}
+impl PartialOrd for Open<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Walk `parent` links back from `node` to the search root; root first.
+fn trace<'a>(parent: &HashMap<&'a str, &'a str>, mut node: &'a str) -> Vec<String> {
+    let mut path = vec![node.to_string()];
+    while let Some(&p) = parent.get(node) {
+        path.push(p.to_string());
+        node = p;
+    }
+    path.reverse();
+    path
+}
+
+fn result(src: &str, dst: &str, path: Vec<String>, total_weight: f64, algorithm: &str) -> PathResult {
+    PathResult {
+        src_id:         src.to_string(),
+        dst_id:         dst.to_string(),
+        path,
+        total_weight,
+        algorithm:      algorithm.to_string(),
+        weight_by_type: HashMap::new(),
+    }
+}
+
+// ── A* ────────────────────────────────────────────────────────────────────────
+
+/// ALT lower bound on the distance to `dst`: with landmark L, the triangle
+/// inequality gives d(n,dst) ≥ d(L,dst) − d(L,n) and d(n,dst) ≥ d(n,L) −
+/// d(dst,L); on an undirected graph the two together are |d(L,dst) − d(L,n)|.
+/// The landmark is the node farthest from `src`, which tends to give the
+/// tightest bounds along the src → dst direction. A bound whose distances
+/// are unknown is dropped; with neither, the estimate is 0.
+pub fn landmark_heuristic(adj: &Adjacency, src: &str, dst: &str) -> impl Fn(&str) -> f64 {
+    let from_src = distances_from(adj, src);
+    let landmark = from_src
+        .iter()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(Ordering::Equal).then_with(|| b.0.cmp(a.0)))
+        .map(|(n, _)| n.clone())
+        .unwrap_or_else(|| src.to_string());
+    let from_landmark = distances_from(adj, &landmark);
+    let to_landmark = distances_from(&reversed(adj), &landmark);
+    let (landmark_to_dst, dst_to_landmark) = (from_landmark.get(dst).copied(), to_landmark.get(dst).copied());
+    move |node: &str| {
+        let ahead = landmark_to_dst.zip(from_landmark.get(node)).map(|(d, n)| d - n);
+        let behind = dst_to_landmark.zip(to_landmark.get(node)).map(|(d, n)| n - d);
+        ahead.into_iter().chain(behind).fold(0.0, f64::max)
+    }
+}
+
+/// A* guided by the admissible heuristic `h`; returns the path and expansions.
+pub fn astar(adj: &Adjacency, src: &str, dst: &str, h: impl Fn(&str) -> f64) -> (Option<PathResult>, usize) {
+    let mut g: HashMap<&str, f64> = HashMap::from([(src, 0.0)]);
+    let mut parent: HashMap<&str, &str> = HashMap::new();
+    let mut heap = BinaryHeap::from([Open { priority: h(src), node: src }]);
+    let mut expanded = 0;
+
+    while let Some(Open { priority, node }) = heap.pop() {
+        let cost = g[node];
+        if priority > cost + h(node) + EPSILON {
+            continue; // stale entry
+        }
+        if node == dst {
+            return (Some(result(src, dst, trace(&parent, node), cost, "astar")), expanded);
+        }
+        expanded += 1;
+        for (next, w) in adj.get(node).into_iter().flatten() {
+            let next_cost = cost + w;
+            if next_cost < g.get(next.as_str()).copied().unwrap_or(f64::INFINITY) {
+                g.insert(next, next_cost);
+                parent.insert(next, node);
+                heap.push(Open { priority: next_cost + h(next), node: next });
+            }
+        }
+    }
+    (None, expanded)
+}
+
+// ── Bidirectional Dijkstra ────────────────────────────────────────────────────
+
+struct Frontier<'a> {
+    dist:   HashMap<&'a str, f64>,
+    parent: HashMap<&'a str, &'a str>,
+    heap:   BinaryHeap<Open<'a>>,
+}
+
+impl<'a> Frontier<'a> {
+    fn new(root: &'a str) -> Self {
+        Frontier {
+            dist:   HashMap::from([(root, 0.0)]),
+            parent: HashMap::new(),
+            heap:   BinaryHeap::from([Open { priority: 0.0, node: root }]),
+        }
+    }
+
+    fn top(&self) -> f64 {
+        self.heap.peek().map(|o| o.priority).unwrap_or(f64::INFINITY)
+    }
+}
+
+/// Bidirectional Dijkstra, the backward half over the reversed arcs; returns
+/// the path and expansions.
+pub fn bidirectional(adj: &Adjacency, src: &str, dst: &str) -> (Option<PathResult>, usize) {
+    let rev = reversed(adj);
+    let (mut fwd, mut bwd) = (Frontier::new(src), Frontier::new(dst));
+    let mut best = if src == dst { 0.0 } else { f64::INFINITY };
+    let mut meet: Option<&str> = (src == dst).then_some(src);
+    let mut expanded = 0;
+
+    while !fwd.heap.is_empty() && !bwd.heap.is_empty() && fwd.top() + bwd.top() < best {
+        let (this, other, arcs) =
+            if fwd.top() <= bwd.top() { (&mut fwd, &bwd, adj) } else { (&mut bwd, &fwd, &rev) };
+        let Some(Open { priority: cost, node }) = this.heap.pop() else { break };
+        if cost > this.dist[node] + EPSILON {
+            continue;
+        }
+        expanded += 1;
+        for (next, w) in arcs.get(node).into_iter().flatten() {
+            let next_cost = cost + w;
+            if next_cost < this.dist.get(next.as_str()).copied().unwrap_or(f64::INFINITY) {
+                this.dist.insert(next, next_cost);
+                this.parent.insert(next, node);
+                this.heap.push(Open { priority: next_cost, node: next });
+                if let Some(rest) = other.dist.get(next.as_str()) {
+                    if next_cost + rest < best {
+                        best = next_cost + rest;
+                        meet = Some(next);
+                    }
+                }
+            }
+        }
+    }
+
+    let Some(meet) = meet else { return (None, expanded) };
+    let mut path = trace(&fwd.parent, meet);
+    let mut tail = trace(&bwd.parent, meet);
+    tail.reverse();
+    path.extend(tail.into_iter().skip(1));
+    (Some(result(src, dst, path, best, "bidirectional")), expanded)
+}
+
+// ── Widest path ───────────────────────────────────────────────────────────────
+
+/// Search behind `--algorithm`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Algorithm {
+    /// minimise the path score
+    #[default]
+    Dijkstra,
+    /// maximise the lightest edge on the path
+    Widest,
+}
+
+/// Maximum-bottleneck path: Dijkstra with a max-heap on the best bottleneck
+/// reaching each node. `total_weight` is that bottleneck; a zero-hop path
+/// (src = dst) has no edges and reports 0.
+pub fn widest_path(adj: &Adjacency, src: &str, dst: &str) -> Option<PathResult> {
+    let mut best: HashMap<&str, f64> = HashMap::from([(src, f64::INFINITY)]);
+    let mut parent: HashMap<&str, &str> = HashMap::new();
+    // the heap is a min-heap, so it is keyed on the negated bottleneck
+    let mut heap = BinaryHeap::from([Open { priority: f64::NEG_INFINITY, node: src }]);
+
+    while let Some(Open { priority, node }) = heap.pop() {
+        let width = -priority;
+        if width < best[node] {
+            continue;
+        }
+        if node == dst {
+            let total = if src == dst { 0.0 } else { width };
+            return Some(result(src, dst, trace(&parent, node), total, "widest"));
+        }
+        for (next, w) in adj.get(node).into_iter().flatten() {
+            let through = width.min(*w);
+            if through > best.get(next.as_str()).copied().unwrap_or(f64::NEG_INFINITY) {
+                best.insert(next, through);
+                parent.insert(next, node);
+                heap.push(Open { priority: -through, node: next });
+            }
+        }
+    }
+    None
+}
+
+// ── Cross-check ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlgorithmRun {
+    pub algorithm:    String,
+    pub total_weight: Option<f64>,
+    pub path:         Option<Vec<String>>,
+    pub expanded:     usize,
+    pub micros:       u128,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub agree:       bool,
+    /// largest total-weight difference between runs; `"inf"` in JSON when
+    /// only some of them found a path
+    #[serde(serialize_with = "finite_or_inf")]
+    pub discrepancy: f64,
+    pub runs:        Vec<AlgorithmRun>,
+}
+
+fn finite_or_inf<S: Serializer>(value: &f64, s: S) -> Result<S::Ok, S::Error> {
+    if value.is_finite() {
+        s.serialize_f64(*value)
+    } else {
+        s.serialize_str("inf")
+    }
+}
+
+fn timed(algorithm: &str, run: impl FnOnce() -> (Option<PathResult>, usize)) -> AlgorithmRun {
+    let start = Instant::now();
+    let (found, expanded) = run();
+    AlgorithmRun {
+        algorithm: algorithm.to_string(),
+        total_weight: found.as_ref().map(|r| r.total_weight),
+        path: found.map(|r| r.path),
+        expanded,
+        micros: start.elapsed().as_micros(),
+    }
+}
+
+pub fn compare(adj: &Adjacency, src: &str, dst: &str) -> Comparison {
+    let runs = vec![
+        timed("dijkstra", || dijkstra_counted(adj, src, dst)),
+        timed("astar", || astar(adj, src, dst, landmark_heuristic(adj, src, dst))),
+        timed("bidirectional", || bidirectional(adj, src, dst)),
+    ];
+    let weights: Vec<Option<f64>> = runs.iter().map(|r| r.total_weight).collect();
+    let discrepancy = if weights.iter().all(Option::is_none) {
+        0.0
+    } else if weights.iter().any(Option::is_none) {
+        f64::INFINITY
+    } else {
+        let w: Vec<f64> = weights.into_iter().flatten().collect();
+        let max = w.iter().cloned().fold(f64::MIN, f64::max);
+        let min = w.iter().cloned().fold(f64::MAX, f64::min);
+        max - min
+    };
+    let agree = discrepancy <= EPSILON;
+    if !agree {
+        tracing::error!(src = %src, dst = %dst, discrepancy, "Algorithms disagree on total weight");
+    }
+    Comparison { agree, discrepancy, runs }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, edge};
+
+    #[test]
+    fn test_all_agree_on_triangle() {
+        let adj = build_adjacency(&[edge("A", "B", 1.0), edge("B", "C", 2.0), edge("A", "C", 10.0)]);
+        let c = compare(&adj, "A", "C");
+        assert!(c.agree, "{:?}", c);
+        for run in &c.runs {
+            assert_eq!(run.total_weight, Some(3.0), "{}", run.algorithm);
+            assert_eq!(run.path.as_deref().unwrap(), ["A", "B", "C"], "{}", run.algorithm);
+        }
+    }
+
+    #[test]
+    fn test_widest_picks_strongest_weakest_link() {
+        // A-B-D sums to 2 but its weakest link is 1; A-C-D sums to 8 with links of 4
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0), edge("B", "D", 1.0),
+            edge("A", "C", 4.0), edge("C", "D", 4.0),
+            edge("A", "E", 9.0), edge("E", "D", 2.0),
+        ]);
+        assert_eq!(dijkstra_counted(&adj, "A", "D").0.unwrap().path, ["A", "B", "D"]);
+        let widest = widest_path(&adj, "A", "D").unwrap();
+        assert_eq!(widest.path, ["A", "C", "D"]);
+        assert_eq!(widest.total_weight, 4.0);
+        assert_eq!(widest.algorithm, "widest");
+        assert!(widest_path(&adj, "A", "Z").is_none());
+        assert_eq!(widest_path(&adj, "A", "A").unwrap().total_weight, 0.0);
+    }
+
+    #[test]
+    fn test_agree_on_longer_chain_and_no_path() {
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0), edge("B", "C", 1.0), edge("C", "D", 1.0), edge("D", "E", 1.0),
+            edge("A", "X", 0.5), edge("X", "E", 5.0), edge("P", "Q", 1.0),
+        ]);
+        let c = compare(&adj, "A", "E");
+        assert!(c.agree);
+        assert_eq!(c.runs[2].path.as_deref().unwrap(), ["A", "B", "C", "D", "E"]);
+        let none = compare(&adj, "A", "Q");
+        assert!(none.agree);
+        assert!(none.runs.iter().all(|r| r.total_weight.is_none()));
+    }
+
+    #[test]
+    fn test_agree_on_one_way_arcs() {
+        use crate::shortest_path::{build_adjacency_with, GraphOptions};
+        let directed = GraphOptions { directed: true, ..Default::default() };
+        // B is a sink: nothing leads from A to C
+        let adj = build_adjacency_with(&[edge("A", "B", 1.0), edge("C", "B", 1.0)], &directed);
+        let c = compare(&adj, "A", "C");
+        assert!(c.agree, "{:?}", c);
+        assert!(c.runs.iter().all(|r| r.total_weight.is_none()));
+
+        // the cheap way back D → A must not leak into A → D bounds or paths
+        let adj = build_adjacency_with(&[
+            edge("A", "B", 1.0), edge("B", "C", 1.0), edge("C", "D", 1.0),
+            edge("A", "D", 5.0), edge("D", "A", 0.1), edge("C", "A", 0.1),
+        ], &directed);
+        let c = compare(&adj, "A", "D");
+        assert!(c.agree, "{:?}", c);
+        for run in &c.runs {
+            assert_eq!(run.path.as_deref().unwrap(), ["A", "B", "C", "D"], "{}", run.algorithm);
+        }
+        let partial = Comparison { agree: false, discrepancy: f64::INFINITY, runs: Vec::new() };
+        assert_eq!(serde_json::to_value(&partial).unwrap()["discrepancy"], "inf");
+    }
+}
@@ -0,0 +1,87 @@
+//! analysis_runs.rs — Singine invocation audit trail
+//!
+//! Every invocation records itself in `analysis_runs` unless run with
+//! `--no-audit`: a `running` row when the mode starts, closed as `success` or
+//! `failed` with its finish time when the process ends — early exits (no
+//! path, a failed check) included. A database that cannot take the row (read
+//! only, say) logs a warning and the mode runs unrecorded. `params_json`
+//! holds the command-line arguments. The run_id is `--run-id` or else
+//! `run-<uuid>`, made without the id registry so that read-only modes leave
+//! it untouched; a generated id is not written to `path_results`, whose
+//! run_id must name a `pipeline_runs` row. A reused run_id adds a row rather
+//! than overwriting the earlier one. `status` does not count this table.
+
+use rusqlite::{Connection, Result as SqlResult};
+use std::path::{Path, PathBuf};
+
+pub fn ensure_table(conn: &Connection) -> SqlResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS analysis_runs (
+           run_id      TEXT NOT NULL,
+           mode        TEXT NOT NULL,
+           params_json TEXT NOT NULL,
+           started_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+           finished_at TEXT,
+           status      TEXT NOT NULL DEFAULT 'running'
+                            CHECK(status IN ('running','success','failed'))
+         );
+         CREATE INDEX IF NOT EXISTS idx_analysis_runs_run ON analysis_runs(run_id);",
+    )
+}
+
+/// A `running` row, closed from a fresh connection so that it can be
+/// closed on any exit path.
+#[derive(Debug, Clone)]
+pub struct OpenRun {
+    pub db:     PathBuf,
+    pub run_id: String,
+    row:        i64,
+}
+
+/// Record the start of a run in the database at `db`.
+pub fn open(conn: &Connection, db: &Path, run_id: &str, mode: &str, params_json: &str) -> SqlResult<OpenRun> {
+    ensure_table(conn)?;
+    conn.execute(
+        "INSERT INTO analysis_runs (run_id, mode, params_json) VALUES (?1,?2,?3)",
+        rusqlite::params![run_id, mode, params_json],
+    )?;
+    Ok(OpenRun { db: db.to_path_buf(), run_id: run_id.to_string(), row: conn.last_insert_rowid() })
+}
+
+impl OpenRun {
+    pub fn close(&self, success: bool) -> SqlResult<()> {
+        let conn = Connection::open(&self.db)?;
+        conn.execute(
+            "UPDATE analysis_runs SET status = ?1, finished_at = strftime('%Y-%m-%dT%H:%M:%SZ','now')
+             WHERE rowid = ?2",
+            rusqlite::params![if success { "success" } else { "failed" }, self.row],
+        )?;
+        tracing::info!(run_id = %self.run_id, success, "Analysis run recorded");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reused_run_id_adds_a_row() {
+        let db = std::env::temp_dir().join(format!("analysis-{}.db", std::process::id()));
+        let conn = Connection::open(&db).unwrap();
+        let first = open(&conn, &db, "r1", "batch", "[]").unwrap();
+        first.close(false).unwrap();
+        let second = open(&conn, &db, "r1", "batch", "[]").unwrap();
+        second.close(true).unwrap();
+        let statuses: Vec<String> = conn
+            .prepare("SELECT status FROM analysis_runs WHERE run_id = 'r1' ORDER BY rowid")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<SqlResult<_>>()
+            .unwrap();
+        drop(conn);
+        std::fs::remove_file(&db).ok();
+        assert_eq!(statuses, vec!["failed", "success"]);
+    }
+}
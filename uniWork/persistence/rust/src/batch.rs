@@ -0,0 +1,486 @@
+//! batch.rs — Singine batch shortest-path runner
+//!
+//! Reads a pairs file (one `src dst` pair per line, whitespace or comma
+//! separated, `#` comments allowed), loads the graph once — from the database
+//! or `--edges-json`/`--edges-json-adjacency` — and runs Dijkstra for every
+//! pair over the shared adjacency map, on `--jobs N` threads when asked.
+//!
+//! With `--dedup-paths` identical node sequences are collapsed into one
+//! entry carrying a reference count, which highlights common routes.
+//!
+//! With `--checkpoint-every N` the results so far are written to
+//! `<output>.partial` every N completed pairs; `--resume` reads that file and
+//! skips the pairs it already holds. The partial file is removed once the
+//! batch finishes.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::shortest_path::{
+    self, Graph, GraphOptions, PathResult, QueryParams, SearchOptions, SearchTooLarge,
+};
+
+// ── Data types ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairResult {
+    pub src_id: String,
+    pub dst_id: String,
+    pub result: Option<PathResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupedPath {
+    pub path:         Vec<String>,
+    pub total_weight: f64,
+    pub count:        usize,
+    pub pairs:        Vec<(String, String)>,
+}
+
+// ── Pairs file ────────────────────────────────────────────────────────────────
+
+/// Parse `src dst` pairs; blank lines, `#` comments and malformed lines are skipped.
+pub fn parse_pairs(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut parts = l
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|p| !p.is_empty());
+            match (parts.next(), parts.next()) {
+                (Some(s), Some(d)) => Some((s.to_string(), d.to_string())),
+                _ => {
+                    tracing::warn!(line = %l, "Skipping malformed pair line");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+pub fn read_pairs(path: &Path) -> std::io::Result<Vec<(String, String)>> {
+    Ok(parse_pairs(&std::fs::read_to_string(path)?))
+}
+
+// ── Checkpoints ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Default)]
+pub struct Checkpoint {
+    /// partial results file
+    pub path:   PathBuf,
+    /// write the partial file after every N computed pairs (0 = never)
+    pub every:  usize,
+    /// results of an earlier, interrupted run; their pairs are not recomputed
+    pub resume: Vec<PairResult>,
+}
+
+/// `<output>.partial`, next to the final output.
+pub fn partial_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// Results saved by an earlier run; empty when no partial file exists.
+pub fn read_partial(path: &Path) -> anyhow::Result<Vec<PairResult>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(serde_json::from_str(&text)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            tracing::warn!(path = %path.display(), "No partial file to resume from");
+            Ok(Vec::new())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_partial(path: &Path, results: &[PairResult]) -> anyhow::Result<()> {
+    std::fs::write(path, serde_json::to_string(results)?)?;
+    tracing::info!(path = %path.display(), completed = results.len(), "Wrote batch checkpoint");
+    Ok(())
+}
+
+// ── Batch run ─────────────────────────────────────────────────────────────────
+
+/// Search every pair over the shared graph, split across `jobs` threads;
+/// results come back in input order, split by edge type.
+fn search_all(
+    graph: &Graph,
+    pairs: &[&(String, String)],
+    search_opts: &SearchOptions,
+    jobs: usize,
+) -> Result<Vec<Option<PathResult>>, SearchTooLarge> {
+    let one = |(s, d): &&(String, String)| -> Result<Option<PathResult>, SearchTooLarge> {
+        let mut found = graph.search(s, d, search_opts)?;
+        if let Some(r) = found.as_mut() {
+            shortest_path::split_by_type(graph, search_opts, r);
+        }
+        Ok(found)
+    };
+    let run = |chunk: &[&(String, String)]| -> Result<Vec<Option<PathResult>>, SearchTooLarge> {
+        chunk.iter().map(one).collect()
+    };
+    if jobs <= 1 || pairs.len() < 2 {
+        return run(pairs);
+    }
+    let chunk_size = pairs.len().div_ceil(jobs);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = pairs.chunks(chunk_size).map(|c| scope.spawn(move || run(c))).collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("batch search thread panicked"))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|chunks| chunks.into_iter().flatten().collect())
+    })
+}
+
+/// Run every pair over the shared `graph`. Searches run on `jobs` threads;
+/// persistence stays on the calling thread, in pair order, so SQLite sees a
+/// single writer. With a checkpoint, pairs are processed in waves of
+/// `every` and the partial file is rewritten after each wave; under
+/// `--fail-fast` a wave is at most one pair per thread, so nothing past the
+/// first missing pair's wave is searched.
+#[allow(clippy::too_many_arguments)]
+pub fn run_batch(
+    conn: &Connection,
+    graph: &Graph,
+    pairs: &[(String, String)],
+    opts: &GraphOptions,
+    search_opts: &SearchOptions,
+    run_id: Option<&str>,
+    checkpoint: Option<&Checkpoint>,
+    jobs: usize,
+) -> anyhow::Result<Vec<PairResult>> {
+    tracing::info!(pairs = pairs.len(), jobs, "Running batch");
+    let adj = &graph.adj;
+    for (src, dst) in pairs {
+        shortest_path::check_endpoints(adj, opts, &shortest_path::endpoints(src, dst, search_opts))?;
+    }
+    shortest_path::check_weights(adj, search_opts.strict)?;
+    crate::path_score::check(adj, search_opts.path_score)?;
+    crate::integer_weights::check(adj, search_opts.integer_weights)?;
+
+    let mut done: HashMap<(&str, &str), &PairResult> = HashMap::new();
+    if let Some(cp) = checkpoint {
+        for pr in &cp.resume {
+            done.insert((pr.src_id.as_str(), pr.dst_id.as_str()), pr);
+        }
+        if !done.is_empty() {
+            tracing::info!(resumed = done.len(), "Resuming batch from checkpoint");
+        }
+    }
+
+    let mut slots: Vec<Option<PairResult>> = pairs
+        .iter()
+        .map(|(s, d)| done.get(&(s.as_str(), d.as_str())).map(|pr| (*pr).clone()))
+        .collect();
+    let pending: Vec<usize> = (0..pairs.len()).filter(|&i| slots[i].is_none()).collect();
+    let wave = match checkpoint {
+        Some(cp) if cp.every > 0 => cp.every,
+        _ => pending.len().max(1),
+    };
+    let wave = if search_opts.fail_fast { wave.min(jobs.max(1)) } else { wave };
+
+    let params = QueryParams::new(opts, search_opts);
+    // only --replace-run holds the whole batch in one transaction; otherwise
+    // each wave's rows are committed as they go, matching the checkpoints
+    let tx = search_opts.replace_run.then(|| shortest_path::run_transaction(conn, run_id, search_opts)).transpose()?;
+    let db = tx.as_deref().unwrap_or(conn);
+    // resumed paths were stored by the interrupted run, up to the same cap
+    let resumed = slots.iter().flatten().filter(|pr| pr.result.is_some()).count();
+    let mut persisted = search_opts.max_persist.map_or(resumed, |cap| resumed.min(cap));
+    let mut unpersisted = 0;
+    for indices in pending.chunks(wave) {
+        let wave_pairs: Vec<&(String, String)> = indices.iter().map(|&i| &pairs[i]).collect();
+        let found = search_all(graph, &wave_pairs, search_opts, jobs)?;
+        for (&i, result) in indices.iter().zip(found) {
+            let (src, dst) = &pairs[i];
+            match &result {
+                Some(_) if search_opts.max_persist.is_some_and(|cap| persisted >= cap) => unpersisted += 1,
+                Some(r) => {
+                    shortest_path::persist_path(db, r, run_id, &params)?;
+                    persisted += 1;
+                }
+                None if search_opts.fail_fast => {
+                    anyhow::bail!(
+                        "no path from {} to {} (pair {} of {}); --fail-fast aborted the batch",
+                        src,
+                        dst,
+                        i + 1,
+                        pairs.len()
+                    );
+                }
+                None => tracing::warn!(src = %src, dst = %dst, "No path found"),
+            }
+            slots[i] = Some(PairResult { src_id: src.clone(), dst_id: dst.clone(), result });
+        }
+        if let Some(cp) = checkpoint.filter(|cp| cp.every > 0) {
+            let so_far: Vec<PairResult> = slots.iter().flatten().cloned().collect();
+            write_partial(&cp.path, &so_far)?;
+        }
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    if unpersisted > 0 {
+        tracing::warn!(persisted, unpersisted, "--max-persist reached; later paths were reported but not stored");
+    }
+    if let Some(cp) = checkpoint {
+        if cp.path.exists() {
+            std::fs::remove_file(&cp.path)?;
+        }
+    }
+    Ok(slots.into_iter().flatten().collect())
+}
+
+// ── De-duplication ────────────────────────────────────────────────────────────
+
+/// Collapse identical node sequences, preserving first-seen order.
+/// Pairs without a path are not included.
+pub fn dedup_paths(results: &[PairResult]) -> Vec<DedupedPath> {
+    let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut deduped: Vec<DedupedPath> = Vec::new();
+    for pr in results {
+        let Some(r) = &pr.result else { continue };
+        let pair = (pr.src_id.clone(), pr.dst_id.clone());
+        match index.get(&r.path) {
+            Some(&i) => {
+                deduped[i].count += 1;
+                deduped[i].pairs.push(pair);
+            }
+            None => {
+                index.insert(r.path.clone(), deduped.len());
+                deduped.push(DedupedPath {
+                    path:         r.path.clone(),
+                    total_weight: r.total_weight,
+                    count:        1,
+                    pairs:        vec![pair],
+                });
+            }
+        }
+    }
+    deduped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, dijkstra, edge};
+
+    fn pair(adj: &shortest_path::Adjacency, s: &str, d: &str) -> PairResult {
+        PairResult { src_id: s.into(), dst_id: d.into(), result: dijkstra(adj, s, d) }
+    }
+
+    fn graph(conn: &Connection, opts: &GraphOptions) -> Graph {
+        shortest_path::load_typed_graph(conn, opts).unwrap()
+    }
+
+    /// In-memory database with the edge and path tables, holding `edges`
+    /// (the VALUES list of a `similarity_edges` insert).
+    fn db(edges: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT);
+             CREATE TABLE path_results (gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT,
+                                        path_json TEXT, total_weight REAL, algorithm TEXT,
+                                        computed_at TEXT, run_id TEXT);
+             INSERT INTO similarity_edges VALUES {};",
+            edges
+        ))
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_parse_pairs_skips_comments() {
+        let pairs = parse_pairs("# header\nA B\n\nB,C\nlonely\n");
+        assert_eq!(pairs, vec![("A".into(), "B".into()), ("B".into(), "C".into())]);
+    }
+
+    #[test]
+    fn test_resume_skips_completed_pairs() {
+        let conn = db("('e1','A','B',1.0,'sim'), ('e2','B','C',1.0,'sim'), ('e3','C','D',1.0,'sim')");
+        let pairs = parse_pairs("A B\nA C\nA D\n");
+        let (g, s) = (GraphOptions::default(), SearchOptions::default());
+        let path = std::env::temp_dir().join(format!("batch-{}.json.partial", std::process::id()));
+
+        // an interrupted run that got through the first pair
+        let first = run_batch(&conn, &graph(&conn, &g), &pairs[..1], &g, &s, None, None, 1).unwrap();
+        write_partial(&path, &first).unwrap();
+
+        let cp = Checkpoint { path: path.clone(), every: 1, resume: read_partial(&path).unwrap() };
+        let results = run_batch(&conn, &graph(&conn, &g), &pairs, &g, &s, None, Some(&cp), 1).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].result.as_ref().unwrap().path, vec!["A", "B", "C", "D"]);
+        let persisted: i64 =
+            conn.query_row("SELECT COUNT(*) FROM path_results", [], |r| r.get(0)).unwrap();
+        assert_eq!(persisted, 3, "resumed pair must not be recomputed");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_strict_batch_refuses_negative_weights() {
+        let conn = db("('e1','A','B',-1.0,'sim'), ('e2','B','C',1.0,'sim')");
+        let pairs = parse_pairs("A C\n");
+        let (g, strict) = (GraphOptions::default(), SearchOptions { strict: true, ..Default::default() });
+        assert!(run_batch(&conn, &graph(&conn, &g), &pairs, &g, &strict, None, None, 1).is_err());
+        let persisted: i64 =
+            conn.query_row("SELECT COUNT(*) FROM path_results", [], |r| r.get(0)).unwrap();
+        assert_eq!(persisted, 0);
+    }
+
+    #[test]
+    fn test_batch_refuses_pruned_endpoints() {
+        let conn = db("('e1','A','H',1.0,'sim'), ('e2','B','H',1.0,'sim'), ('e3','C','H',1.0,'sim'), \
+                       ('e4','A','B',5.0,'sim')");
+        let pairs = parse_pairs("A B\nA H\n");
+        let s = SearchOptions::default();
+        let hubless = GraphOptions { max_degree: Some(2), ..Default::default() };
+        let err = run_batch(&conn, &graph(&conn, &hubless), &pairs, &hubless, &s, None, None, 1).unwrap_err().to_string();
+        assert!(err.contains("H has more than --max-degree 2"), "{}", err);
+        let via_hub = SearchOptions { via: Some("H".into()), ..Default::default() };
+        let err = run_batch(&conn, &graph(&conn, &hubless), &pairs[..1], &hubless, &via_hub, None, None, 1).unwrap_err().to_string();
+        assert!(err.contains("H has more than --max-degree 2"), "{}", err);
+        let prefixed = GraphOptions { node_prefix: Some("A".into()), ..Default::default() };
+        assert!(run_batch(&conn, &graph(&conn, &prefixed), &pairs, &prefixed, &s, None, None, 1).is_err());
+        let persisted: i64 =
+            conn.query_row("SELECT COUNT(*) FROM path_results", [], |r| r.get(0)).unwrap();
+        assert_eq!(persisted, 0, "no pair is searched once one endpoint is refused");
+    }
+
+    #[test]
+    fn test_parallel_batch_matches_sequential() {
+        let conn = db("('e1','A','B',1.0,'sim'), ('e2','B','C',2.0,'sim'), ('e3','C','D',1.0,'sim'), \
+                       ('e4','A','D',5.0,'sim'), ('e5','X','Y',1.0,'sim')");
+        let pairs = parse_pairs("A B\nA C\nA D\nB D\nC A\nA X\nX Y\nD B\nB C\n");
+        let (g, s) = (GraphOptions::default(), SearchOptions::default());
+        let adj = graph(&conn, &g);
+        let one = run_batch(&conn, &adj, &pairs, &g, &s, None, None, 1).unwrap();
+        let four = run_batch(&conn, &adj, &pairs, &g, &s, None, None, 4).unwrap();
+        assert_eq!(one.len(), 9);
+        assert_eq!(serde_json::to_value(&one).unwrap(), serde_json::to_value(&four).unwrap());
+        let persisted: i64 =
+            conn.query_row("SELECT COUNT(*) FROM path_results", [], |r| r.get(0)).unwrap();
+        assert_eq!(persisted, 16);
+    }
+
+    #[test]
+    fn test_batch_results_split_by_type() {
+        let conn = db("('e1','A','B',0.5,'similarity'), ('e2','B','C',2.0,'lineage'), ('e3','B','C',3.0,'category')");
+        let pairs = parse_pairs("A C\nC A\n");
+        let (g, s) = (GraphOptions::default(), SearchOptions::default());
+        for jobs in [1, 2] {
+            let results = run_batch(&conn, &graph(&conn, &g), &pairs, &g, &s, None, None, jobs).unwrap();
+            for pr in &results {
+                let by_type = &pr.result.as_ref().unwrap().weight_by_type;
+                assert_eq!(by_type.len(), 2, "{:?}", by_type);
+                assert_eq!((by_type["similarity"], by_type["lineage"]), (0.5, 2.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_dedup_paths_counts_identical() {
+        let edges = vec![
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("B", "C", 1.0).with_id("e2"),
+        ];
+        let adj = build_adjacency(&edges);
+        let results = vec![pair(&adj, "A", "C"), pair(&adj, "A", "C")];
+        let deduped = dedup_paths(&results);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].count, 2);
+        assert_eq!(deduped[0].path, vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_max_persist_caps_stored_rows_not_results() {
+        let conn = db("('e1','A','B',1.0,'sim'), ('e2','B','C',1.0,'sim'), ('e3','C','D',1.0,'sim')");
+        let pairs = parse_pairs("A B\nA C\nA D\nB D\nC D\n");
+        let (g, s) = (GraphOptions::default(), SearchOptions { max_persist: Some(2), ..Default::default() });
+        let results = run_batch(&conn, &graph(&conn, &g), &pairs, &g, &s, None, None, 1).unwrap();
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.result.is_some()));
+        let persisted: i64 =
+            conn.query_row("SELECT COUNT(*) FROM path_results", [], |r| r.get(0)).unwrap();
+        assert_eq!(persisted, 2);
+    }
+
+    #[test]
+    fn test_max_persist_holds_across_resume() {
+        let conn = db("('e1','A','B',1.0,'sim'), ('e2','B','C',1.0,'sim'), ('e3','C','D',1.0,'sim')");
+        let pairs = parse_pairs("A B\nA C\nA D\nB D\n");
+        let (g, s) = (GraphOptions::default(), SearchOptions { max_persist: Some(2), ..Default::default() });
+        let path = std::env::temp_dir().join(format!("batch-cap-{}.json.partial", std::process::id()));
+        let first = run_batch(&conn, &graph(&conn, &g), &pairs[..2], &g, &s, None, None, 1).unwrap();
+        write_partial(&path, &first).unwrap();
+
+        let cp = Checkpoint { path, every: 1, resume: first };
+        let results = run_batch(&conn, &graph(&conn, &g), &pairs, &g, &s, None, Some(&cp), 1).unwrap();
+        assert_eq!(results.len(), 4);
+        let persisted: i64 =
+            conn.query_row("SELECT COUNT(*) FROM path_results", [], |r| r.get(0)).unwrap();
+        assert_eq!(persisted, 2, "the resumed run already used up --max-persist");
+    }
+
+    #[test]
+    fn test_replace_run_leaves_one_set_of_rows() {
+        let conn = db("('e1','A','B',1.0,'sim'), ('e2','B','C',1.0,'sim')");
+        let rows = |run: &str| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM path_results WHERE run_id = ?1", [run], |r| r.get(0)).unwrap()
+        };
+        let pairs = parse_pairs("A B\nA C\n");
+        let g = GraphOptions::default();
+        let adj = graph(&conn, &g);
+        run_batch(&conn, &adj, &pairs, &g, &SearchOptions::default(), Some("r1"), None, 1).unwrap();
+        run_batch(&conn, &adj, &pairs, &g, &SearchOptions::default(), Some("r2"), None, 1).unwrap();
+        run_batch(&conn, &adj, &pairs, &g, &SearchOptions::default(), Some("r1"), None, 1).unwrap();
+        assert_eq!(rows("r1"), 4, "a plain rerun accumulates");
+
+        let replace = SearchOptions { replace_run: true, ..Default::default() };
+        run_batch(&conn, &adj, &pairs, &g, &replace, Some("r1"), None, 1).unwrap();
+        assert_eq!(rows("r1"), 2);
+        let distinct: i64 = conn
+            .query_row("SELECT COUNT(DISTINCT src_id || '>' || dst_id) FROM path_results WHERE run_id='r1'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(distinct, 2, "no duplicate pairs");
+        assert_eq!(rows("r2"), 2, "other runs are untouched");
+
+        // a single query under the flag replaces the run's rows as well
+        shortest_path::search_and_persist(&conn, &adj, "A", "C", &g, &replace, Some("r1")).unwrap();
+        assert_eq!(rows("r1"), 1);
+    }
+
+    #[test]
+    fn test_fail_fast_aborts_on_first_missing_pair() {
+        let conn = db("('e1','A','B',1.0,'sim'), ('e2','X','Y',1.0,'sim')");
+        let pairs = parse_pairs("A B\nA X\nB Y\nX Y\n");
+        let g = GraphOptions::default();
+        let (adj, s) = (graph(&conn, &g), SearchOptions { fail_fast: true, ..Default::default() });
+        let err = run_batch(&conn, &adj, &pairs, &g, &s, None, None, 1).unwrap_err().to_string();
+        assert!(err.contains("from A to X (pair 2 of 4)"), "{}", err);
+        let persisted: i64 =
+            conn.query_row("SELECT COUNT(*) FROM path_results", [], |r| r.get(0)).unwrap();
+        assert_eq!(persisted, 1, "pairs after the failure are not persisted");
+
+        let results = run_batch(&conn, &adj, &pairs, &g, &SearchOptions::default(), None, None, 1).unwrap();
+        assert_eq!(results.iter().filter(|r| r.result.is_none()).count(), 2);
+    }
+
+    #[test]
+    fn test_fail_fast_does_not_search_later_pairs() {
+        // S X would overflow --max-open-states, so searching it fails differently
+        let conn = db("('e1','A','B',1.0,'sim'), ('e2','X','Y',1.0,'sim'), ('e3','S','L1',1.0,'sim'), \
+                       ('e4','S','L2',1.0,'sim'), ('e5','S','L3',1.0,'sim'), ('e6','S','L4',1.0,'sim')");
+        let pairs = parse_pairs("A B\nA X\nS X\n");
+        let g = GraphOptions::default();
+        let s = SearchOptions { fail_fast: true, max_open_states: Some(2), ..Default::default() };
+        for jobs in [1, 2] {
+            let err = run_batch(&conn, &graph(&conn, &g), &pairs, &g, &s, None, None, jobs).unwrap_err().to_string();
+            assert!(err.contains("from A to X (pair 2 of 3)"), "jobs {}: {}", jobs, err);
+        }
+    }
+}
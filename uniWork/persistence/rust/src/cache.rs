@@ -0,0 +1,271 @@
+//! cache.rs — Singine persistent query cache
+//!
+//! With `--cache`, shortest-path results are stored in `query_cache` keyed by
+//! a hash of the endpoints, the result-shaping options (`QueryParams`) and the
+//! graph hash. Repeating an identical query against an unchanged graph returns
+//! the stored result instead of recomputing; any edge change alters the graph
+//! hash, and the option set's stale entries are purged on its next lookup,
+//! leaving other option sets' entries alone. The graph hash also covers what
+//! the active options read beyond the base columns: the `--weight-column`
+//! column, `valid_from`/`valid_to` under `--as-of`, and the `node_weights`
+//! table under `--use-node-weights`.
+//!
+//! Hashes are FNV-1a 64 — stable across toolchains, unlike `DefaultHasher`.
+
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult};
+
+use crate::shortest_path::{self, GraphOptions, PathResult, QueryParams, SearchOptions};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    fnv1a64_update(FNV_OFFSET, bytes)
+}
+
+fn fnv1a64_update(mut h: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(FNV_PRIME);
+    }
+    h
+}
+
+/// Hash of the full edge table, in gen_id order. The optional per-row
+/// `directed` and `reverse_weight` columns are included when present, and
+/// whatever else `opts` makes the graph depend on.
+pub fn graph_hash(conn: &Connection, opts: &GraphOptions) -> SqlResult<String> {
+    let cols = shortest_path::table_columns(conn, "similarity_edges")?;
+    let (has_directed, has_reverse) = (cols.contains("directed"), cols.contains("reverse_weight"));
+    let weight_column = opts.weight_column.as_deref().filter(|c| *c != "weight" && cols.contains(*c));
+    let has_validity = opts.as_of.is_some() && cols.contains("valid_from") && cols.contains("valid_to");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT gen_id,src_id,dst_id,weight,edge_type,{},{},{},{} FROM similarity_edges ORDER BY gen_id",
+        if has_directed { "directed" } else { "NULL" },
+        if has_reverse { "reverse_weight" } else { "NULL" },
+        weight_column.map_or("NULL".to_string(), |c| format!("\"{}\"", c)),
+        if has_validity { "valid_from,valid_to" } else { "NULL,NULL" }
+    ))?;
+    let mut rows = stmt.query([])?;
+    let mut h = FNV_OFFSET;
+    while let Some(r) = rows.next()? {
+        for i in [0, 1, 2] {
+            h = fnv1a64_update(h, r.get::<_, String>(i)?.as_bytes());
+            h = fnv1a64_update(h, &[0x1f]);
+        }
+        // 0xff never occurs in UTF-8, so NULL and '' hash differently
+        let edge_type = r.get::<_, Option<String>>(4)?;
+        h = fnv1a64_update(h, edge_type.as_deref().map_or(&[0xff][..], str::as_bytes));
+        h = fnv1a64_update(h, &[0x1f]);
+        h = fnv1a64_update(h, &r.get::<_, f64>(3)?.to_bits().to_le_bytes());
+        if has_directed {
+            h = fnv1a64_update(h, &[r.get::<_, Option<bool>>(5)?.map_or(2, u8::from)]);
+        }
+        if has_reverse {
+            let reverse = r.get::<_, Option<f64>>(6)?;
+            h = fnv1a64_update(h, &reverse.map_or(u64::MAX, f64::to_bits).to_le_bytes());
+        }
+        if weight_column.is_some() {
+            let weight = r.get::<_, Option<f64>>(7)?;
+            h = fnv1a64_update(h, &weight.map_or(u64::MAX, f64::to_bits).to_le_bytes());
+        }
+        if has_validity {
+            for i in [8, 9] {
+                let bound = r.get::<_, Option<String>>(i)?;
+                h = fnv1a64_update(h, bound.as_deref().map_or(&[0xff][..], str::as_bytes));
+                h = fnv1a64_update(h, &[0x1f]);
+            }
+        }
+    }
+    if opts.use_node_weights && !shortest_path::table_columns(conn, "node_weights")?.is_empty() {
+        let mut stmt = conn.prepare("SELECT node_id, weight FROM node_weights ORDER BY node_id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(r) = rows.next()? {
+            h = fnv1a64_update(h, r.get::<_, String>(0)?.as_bytes());
+            h = fnv1a64_update(h, &r.get::<_, f64>(1)?.to_bits().to_le_bytes());
+        }
+    }
+    Ok(format!("{:016x}", h))
+}
+
+/// Hash of the options that shape a result, with the crate version; run
+/// bookkeeping such as `--fail-fast` or `--max-persist` is not part of it.
+pub fn option_key(opts: &GraphOptions, search_opts: &SearchOptions) -> String {
+    let params = serde_json::to_string(&QueryParams::new(opts, search_opts)).unwrap_or_default();
+    format!("{:016x}", fnv1a64(format!("{}\x1f{}", params, env!("CARGO_PKG_VERSION")).as_bytes()))
+}
+
+pub fn input_hash(src: &str, dst: &str, option_key: &str, graph_hash: &str) -> String {
+    let key = format!("{}\x1f{}\x1f{}\x1f{}", src, dst, option_key, graph_hash);
+    format!("{:016x}", fnv1a64(key.as_bytes()))
+}
+
+pub fn ensure_table(conn: &Connection) -> SqlResult<()> {
+    let cols = shortest_path::table_columns(conn, "query_cache")?;
+    if !cols.is_empty() && !cols.contains("option_key") {
+        // a cache from before option keys; nothing in it is worth migrating
+        conn.execute_batch("DROP TABLE query_cache")?;
+    }
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS query_cache (
+           input_hash  TEXT NOT NULL PRIMARY KEY,
+           result_json TEXT NOT NULL,
+           option_key  TEXT NOT NULL,
+           graph_hash  TEXT NOT NULL,
+           created_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now'))
+         );
+         CREATE INDEX IF NOT EXISTS idx_query_cache_option ON query_cache(option_key);",
+    )
+}
+
+/// Cached shortest path; returns the result and whether it came from the cache.
+/// Only found paths are cached. A hit is persisted to path_results again only
+/// under a `run_id`, so that every run holds its own rows.
+pub fn cached_shortest_path(
+    conn: &Connection,
+    src: &str,
+    dst: &str,
+    opts: &GraphOptions,
+    search_opts: &SearchOptions,
+    run_id: Option<&str>,
+) -> anyhow::Result<(Option<PathResult>, bool)> {
+    ensure_table(conn)?;
+    let (gh, key) = (graph_hash(conn, opts)?, option_key(opts, search_opts));
+    let purged =
+        conn.execute("DELETE FROM query_cache WHERE option_key = ?1 AND graph_hash != ?2", [&key, &gh])?;
+    if purged > 0 {
+        tracing::info!(purged, graph_hash = %gh, "Graph changed, purged stale cache entries");
+    }
+    let ih = input_hash(src, dst, &key, &gh);
+
+    let hit: Option<String> = conn
+        .query_row("SELECT result_json FROM query_cache WHERE input_hash = ?1", [&ih], |r| r.get(0))
+        .optional()?;
+    if let Some(json) = hit {
+        if let Ok(result) = serde_json::from_str::<PathResult>(&json) {
+            tracing::info!(input_hash = %ih, "Query cache hit");
+            if let Some(run_id) = run_id {
+                let tx = shortest_path::run_transaction(conn, Some(run_id), search_opts)?;
+                shortest_path::persist_path(&tx, &result, Some(run_id), &QueryParams::new(opts, search_opts))?;
+                tx.commit()?;
+            }
+            return Ok((Some(result), true));
+        }
+    }
+
+    let result = shortest_path::compute_and_persist(conn, src, dst, opts, search_opts, run_id)?;
+    if let Some(r) = &result {
+        conn.execute(
+            "INSERT OR REPLACE INTO query_cache (input_hash, result_json, option_key, graph_hash)
+             VALUES (?1,?2,?3,?4)",
+            rusqlite::params![ih, serde_json::to_string(r).unwrap_or_default(), key, gh],
+        )?;
+    }
+    Ok((result, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT,
+                                            weight REAL, edge_type TEXT);
+             CREATE TABLE path_results (gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT,
+                                        path_json TEXT, total_weight REAL, algorithm TEXT,
+                                        computed_at TEXT, run_id TEXT);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','B',1.0,'similarity'), ('e2','B','C',2.0,'similarity');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_second_query_hits_cache() {
+        let conn = db();
+        let (g, s) = (GraphOptions::default(), SearchOptions::default());
+        let (first, hit) = cached_shortest_path(&conn, "A", "C", &g, &s, None).unwrap();
+        assert!(!hit);
+        let (second, hit) = cached_shortest_path(&conn, "A", "C", &g, &s, None).unwrap();
+        assert!(hit);
+        assert_eq!(first.unwrap().path, second.unwrap().path);
+        let persisted: i64 =
+            conn.query_row("SELECT COUNT(*) FROM path_results", [], |r| r.get(0)).unwrap();
+        assert_eq!(persisted, 1);
+
+        // run bookkeeping is not part of the key, and a new run gets its own row
+        let fail_fast = SearchOptions { fail_fast: true, ..Default::default() };
+        let (_, hit) = cached_shortest_path(&conn, "A", "C", &g, &fail_fast, Some("r2")).unwrap();
+        assert!(hit);
+        let in_run: i64 = conn
+            .query_row("SELECT COUNT(*) FROM path_results WHERE run_id = 'r2'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(in_run, 1);
+    }
+
+    #[test]
+    fn test_option_sets_keep_their_own_entries() {
+        let conn = db();
+        conn.execute_batch("ALTER TABLE similarity_edges ADD COLUMN confidence REAL DEFAULT 1.0;").unwrap();
+        let s = SearchOptions::default();
+        let (plain, by_confidence) =
+            (GraphOptions::default(), GraphOptions { weight_column: Some("confidence".into()), ..Default::default() });
+        cached_shortest_path(&conn, "A", "C", &plain, &s, None).unwrap();
+        cached_shortest_path(&conn, "A", "C", &by_confidence, &s, None).unwrap();
+        assert!(cached_shortest_path(&conn, "A", "C", &plain, &s, None).unwrap().1);
+        assert!(cached_shortest_path(&conn, "A", "C", &by_confidence, &s, None).unwrap().1);
+    }
+
+    #[test]
+    fn test_untyped_rows_hash_apart_from_empty_type() {
+        let conn = db();
+        conn.execute("INSERT INTO similarity_edges VALUES ('e3','C','D',1.0,NULL)", []).unwrap();
+        let untyped = graph_hash(&conn, &GraphOptions::default()).unwrap();
+        conn.execute("UPDATE similarity_edges SET edge_type = '' WHERE gen_id = 'e3'", []).unwrap();
+        assert_ne!(graph_hash(&conn, &GraphOptions::default()).unwrap(), untyped);
+    }
+
+    #[test]
+    fn test_hash_covers_option_inputs() {
+        let conn = db();
+        conn.execute_batch(
+            "ALTER TABLE similarity_edges ADD COLUMN confidence REAL;
+             ALTER TABLE similarity_edges ADD COLUMN valid_from TEXT;
+             ALTER TABLE similarity_edges ADD COLUMN valid_to TEXT;
+             CREATE TABLE node_weights (node_id TEXT PRIMARY KEY, weight REAL);",
+        )
+        .unwrap();
+        let options = [
+            GraphOptions { weight_column: Some("confidence".into()), ..Default::default() },
+            GraphOptions { as_of: Some("2026-01-01T00:00:00Z".into()), ..Default::default() },
+            GraphOptions { use_node_weights: true, ..Default::default() },
+        ];
+        let changes = [
+            "UPDATE similarity_edges SET confidence = 0.5 WHERE gen_id = 'e1'",
+            "UPDATE similarity_edges SET valid_to = '2025-01-01T00:00:00Z' WHERE gen_id = 'e1'",
+            "INSERT INTO node_weights VALUES ('B', 3.0)",
+        ];
+        for (opts, change) in options.iter().zip(changes) {
+            let before = graph_hash(&conn, opts).unwrap();
+            let plain = graph_hash(&conn, &GraphOptions::default()).unwrap();
+            conn.execute(change, []).unwrap();
+            assert_ne!(graph_hash(&conn, opts).unwrap(), before, "{}", change);
+            assert_eq!(graph_hash(&conn, &GraphOptions::default()).unwrap(), plain, "{}", change);
+        }
+    }
+
+    #[test]
+    fn test_graph_change_invalidates() {
+        let conn = db();
+        let (g, s) = (GraphOptions::default(), SearchOptions::default());
+        cached_shortest_path(&conn, "A", "C", &g, &s, None).unwrap();
+        conn.execute("INSERT INTO similarity_edges VALUES ('e3','A','C',0.5,'similarity')", [])
+            .unwrap();
+        let (result, hit) = cached_shortest_path(&conn, "A", "C", &g, &s, None).unwrap();
+        assert!(!hit);
+        assert_eq!(result.unwrap().total_weight, 0.5);
+    }
+}
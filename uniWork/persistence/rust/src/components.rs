@@ -0,0 +1,213 @@
+//! components.rs — Singine connected components
+//!
+//! Undirected reachability over the adjacency map. Used by `--diagnose` to
+//! explain a missing path: when src and dst fall in different components the
+//! no-path output reports each side's size and a few representative nodes.
+//!
+//! `--mode components` lists every component, largest first. With
+//! `--warn-components`, building a graph of more than one component logs a
+//! warning pointing there; the count comes from a union-find pass over the
+//! adjacency, linear in its size.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::shortest_path::Adjacency;
+
+/// Representative nodes reported per component.
+pub const SAMPLE_NODES: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentSummary {
+    pub node:   String,
+    /// 0 when the node is not in the graph at all
+    pub size:   usize,
+    /// first nodes of the component in sorted order
+    pub sample: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoPathDiagnosis {
+    pub same_component: bool,
+    pub src:            ComponentSummary,
+    pub dst:            ComponentSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Component {
+    pub size:   usize,
+    /// first nodes of the component in sorted order
+    pub sample: Vec<String>,
+}
+
+/// Union-find over node indices: path halving, union by size.
+struct DisjointSets {
+    parent: Vec<usize>,
+    size:   Vec<usize>,
+}
+
+impl DisjointSets {
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merge the sets of `a` and `b`; false if they were already one.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        true
+    }
+}
+
+/// Every node of `adj` (sources and neighbours) with the union-find of its
+/// arcs taken as undirected, and the resulting component count.
+fn disjoint_sets(adj: &Adjacency) -> (Vec<&str>, DisjointSets, usize) {
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    let mut names: Vec<&str> = Vec::new();
+    let mut arcs: Vec<(usize, usize)> = Vec::new();
+    for (node, nbrs) in adj {
+        for name in std::iter::once(node).chain(nbrs.iter().map(|(next, _)| next)) {
+            index.entry(name.as_str()).or_insert_with(|| {
+                names.push(name.as_str());
+                names.len() - 1
+            });
+        }
+        arcs.extend(nbrs.iter().map(|(next, _)| (index[node.as_str()], index[next.as_str()])));
+    }
+    let mut sets = DisjointSets { parent: (0..names.len()).collect(), size: vec![1; names.len()] };
+    let mut count = names.len();
+    for (a, b) in arcs {
+        if sets.union(a, b) {
+            count -= 1;
+        }
+    }
+    (names, sets, count)
+}
+
+/// Number of connected components, direction ignored.
+pub fn count_components(adj: &Adjacency) -> usize {
+    disjoint_sets(adj).2
+}
+
+/// Every component, largest first (ties by smallest node).
+pub fn components(adj: &Adjacency) -> Vec<Component> {
+    let (names, mut sets, _) = disjoint_sets(adj);
+    let mut members: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for (i, name) in names.iter().enumerate() {
+        members.entry(sets.find(i)).or_default().push(name);
+    }
+    let mut groups: Vec<Vec<&str>> = members.into_values().collect();
+    for group in &mut groups {
+        group.sort_unstable();
+    }
+    groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(b[0])));
+    groups
+        .into_iter()
+        .map(|g| Component { size: g.len(), sample: g.iter().take(SAMPLE_NODES).map(|n| n.to_string()).collect() })
+        .collect()
+}
+
+/// `--warn-components`: log a warning when `adj` is split, returning the
+/// component count if it did.
+pub fn warn_if_disconnected(adj: &Adjacency) -> Option<usize> {
+    let count = count_components(adj);
+    if count <= 1 {
+        return None;
+    }
+    tracing::warn!(
+        components = count,
+        "Graph has {} connected components; paths between them will fail (see --mode components)",
+        count
+    );
+    Some(count)
+}
+
+/// The component of `start` (including itself), direction ignored, sorted;
+/// empty for a node not in the graph.
+pub fn component_of(adj: &Adjacency, start: &str) -> Vec<String> {
+    let (names, mut sets, _) = disjoint_sets(adj);
+    let Some(at) = names.iter().position(|&n| n == start) else { return Vec::new() };
+    let root = sets.find(at);
+    let mut nodes: Vec<String> =
+        (0..names.len()).filter(|&i| sets.find(i) == root).map(|i| names[i].to_string()).collect();
+    nodes.sort_unstable();
+    nodes
+}
+
+fn summarise(node: &str, members: &[String]) -> ComponentSummary {
+    ComponentSummary {
+        node:   node.to_string(),
+        size:   members.len(),
+        sample: members.iter().take(SAMPLE_NODES).cloned().collect(),
+    }
+}
+
+pub fn diagnose_no_path(adj: &Adjacency, src: &str, dst: &str) -> NoPathDiagnosis {
+    let src_members = component_of(adj, src);
+    let same_component = src_members.binary_search_by(|n| n.as_str().cmp(dst)).is_ok();
+    let dst_members = if same_component { src_members.clone() } else { component_of(adj, dst) };
+    NoPathDiagnosis {
+        same_component,
+        src: summarise(src, &src_members),
+        dst: summarise(dst, &dst_members),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, edge, Edge};
+
+    #[test]
+    fn test_two_clusters_report_sizes() {
+        let adj = build_adjacency(&[edge("A", "B", 1.0), edge("B", "C", 1.0), edge("X", "Y", 1.0)]);
+        let d = diagnose_no_path(&adj, "A", "Y");
+        assert!(!d.same_component);
+        let out = serde_json::to_value(&d).unwrap();
+        assert_eq!(out["src"]["size"], 3);
+        assert_eq!(out["dst"]["size"], 2);
+        assert_eq!(out["src"]["sample"], serde_json::json!(["A", "B", "C"]));
+        assert_eq!(out["dst"]["sample"], serde_json::json!(["X", "Y"]));
+    }
+
+    #[test]
+    fn test_two_components_warn_and_list() {
+        let split = build_adjacency(&[edge("A", "B", 1.0), edge("B", "C", 1.0), edge("X", "Y", 1.0)]);
+        assert_eq!(warn_if_disconnected(&split), Some(2));
+        let listed = components(&split);
+        assert_eq!(listed.iter().map(|c| c.size).collect::<Vec<_>>(), vec![3, 2]);
+        assert_eq!(listed[1].sample, vec!["X", "Y"]);
+
+        let joined = build_adjacency(&[
+            edge("A", "B", 1.0), edge("B", "C", 1.0), edge("C", "X", 1.0), edge("X", "Y", 1.0),
+        ]);
+        assert_eq!(count_components(&joined), 1);
+        assert_eq!(warn_if_disconnected(&joined), None);
+    }
+
+    #[test]
+    fn test_unknown_node_has_empty_component() {
+        let adj = build_adjacency(&[edge("A", "B", 1.0)]);
+        let d = diagnose_no_path(&adj, "A", "Z");
+        assert_eq!(d.dst.size, 0);
+        assert!(d.dst.sample.is_empty());
+
+        // a sink of a one-way arc has no key but is in the graph
+        let adj = build_adjacency(&[Edge { directed: Some(true), ..edge("A", "B", 1.0) }, edge("X", "Y", 1.0)]);
+        let d = diagnose_no_path(&adj, "Y", "B");
+        assert!(!d.same_component);
+        assert_eq!((d.src.size, d.dst.size), (2, 2));
+        assert_eq!(d.dst.sample, vec!["A", "B"]);
+    }
+}
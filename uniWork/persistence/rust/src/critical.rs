@@ -0,0 +1,191 @@
+//! critical.rs — Singine single points of failure
+//!
+//!   bridges      — edges whose removal disconnects their component
+//!   articulation — nodes whose removal disconnects their component
+//!
+//! Runs Tarjan's lowlink DFS over the undirected multigraph of the loaded
+//! edges (direction ignored). Parallel edges between the same pair protect
+//! each other, so neither is a bridge; self-loops never are. The DFS is
+//! iterative so long lineage chains cannot overflow the stack.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::shortest_path::Edge;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Bridge {
+    pub gen_id: String,
+    pub src_id: String,
+    pub dst_id: String,
+    pub weight: f64,
+}
+
+/// Discovery order and lowlink of every node, plus each tree edge's child.
+struct Lowlink {
+    disc: Vec<usize>,
+    low:  Vec<usize>,
+    /// (parent, child, edge index) for every DFS tree edge, in finishing order
+    tree: Vec<(usize, usize, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CutVertex {
+    pub node:       String,
+    /// components left behind when the node is removed
+    pub components: usize,
+}
+
+/// Undirected multigraph: node index → [(neighbour index, edge index)].
+struct Multigraph<'a> {
+    names:    Vec<&'a str>,
+    incident: Vec<Vec<(usize, usize)>>,
+}
+
+fn incidence(edges: &[Edge]) -> Multigraph<'_> {
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    let mut names: Vec<&str> = Vec::new();
+    let mut incident: Vec<Vec<(usize, usize)>> = Vec::new();
+    for (i, e) in edges.iter().enumerate() {
+        if e.src_id == e.dst_id {
+            continue;
+        }
+        let [a, b] = [&e.src_id, &e.dst_id].map(|id| {
+            let next = index.len();
+            let n = *index.entry(id.as_str()).or_insert(next);
+            if n == incident.len() {
+                names.push(id.as_str());
+                incident.push(Vec::new());
+            }
+            n
+        });
+        incident[a].push((b, i));
+        incident[b].push((a, i));
+    }
+    Multigraph { names, incident }
+}
+
+fn lowlink(incident: &[Vec<(usize, usize)>]) -> Lowlink {
+    let n = incident.len();
+    let (mut disc, mut low) = (vec![usize::MAX; n], vec![0; n]);
+    let mut tree = Vec::new();
+    let mut timer = 0;
+    for root in 0..n {
+        if disc[root] != usize::MAX {
+            continue;
+        }
+        disc[root] = timer;
+        low[root] = timer;
+        timer += 1;
+        // (node, edge it was entered by, next incident entry to visit)
+        let mut stack: Vec<(usize, Option<usize>, usize)> = vec![(root, None, 0)];
+        while let Some(top) = stack.last_mut() {
+            let (u, entered_by) = (top.0, top.1);
+            if let Some(&(v, e)) = incident[u].get(top.2) {
+                top.2 += 1;
+                if Some(e) == entered_by {
+                    continue; // the tree edge back to the parent, not a parallel copy
+                }
+                if disc[v] == usize::MAX {
+                    disc[v] = timer;
+                    low[v] = timer;
+                    timer += 1;
+                    stack.push((v, Some(e), 0));
+                } else {
+                    low[u] = low[u].min(disc[v]);
+                }
+                continue;
+            }
+            stack.pop();
+            if let (Some(&(p, ..)), Some(e)) = (stack.last(), entered_by) {
+                low[p] = low[p].min(low[u]);
+                tree.push((p, u, e));
+            }
+        }
+    }
+    Lowlink { disc, low, tree }
+}
+
+/// Every bridge among `edges`, in gen_id order.
+pub fn bridges(edges: &[Edge]) -> Vec<Bridge> {
+    let ll = lowlink(&incidence(edges).incident);
+    let mut found: Vec<Bridge> = ll
+        .tree
+        .iter()
+        .filter(|&&(p, child, _)| ll.low[child] > ll.disc[p])
+        .map(|&(_, _, e)| {
+            let e = &edges[e];
+            Bridge { gen_id: e.gen_id.clone(), src_id: e.src_id.clone(), dst_id: e.dst_id.clone(), weight: e.weight }
+        })
+        .collect();
+    found.sort_by(|a, b| a.gen_id.cmp(&b.gen_id));
+    found
+}
+
+/// Every articulation point among `edges`, in node order. A DFS root cuts
+/// when it has two or more tree children; any other node cuts once per child
+/// subtree that cannot climb above it (`low[child] >= disc[node]`).
+pub fn articulation_points(edges: &[Edge]) -> Vec<CutVertex> {
+    let graph = incidence(edges);
+    let ll = lowlink(&graph.incident);
+    let n = graph.names.len();
+    let mut is_child = vec![false; n];
+    let mut children = vec![0usize; n];
+    let mut separated = vec![0usize; n];
+    for &(p, child, _) in &ll.tree {
+        is_child[child] = true;
+        children[p] += 1;
+        if ll.low[child] >= ll.disc[p] {
+            separated[p] += 1;
+        }
+    }
+    let mut found: Vec<CutVertex> = (0..n)
+        .filter_map(|u| {
+            // a root has nothing above it, so each child subtree is its own component
+            let components = if is_child[u] { separated[u] + 1 } else { children[u] };
+            (components > 1).then(|| CutVertex { node: graph.names[u].to_string(), components })
+        })
+        .collect();
+    found.sort_by(|a, b| a.node.cmp(&b.node));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::edge;
+
+    #[test]
+    fn test_bridge_between_two_triangles() {
+        // triangle A-B-C, bridge C-D, triangle D-E-F, parallel pair F=G, dangling G-H
+        let e = |id: &str, s: &str, d: &str| edge(s, d, 1.0).with_id(id);
+        let edges = vec![
+            e("e1", "A", "B"), e("e2", "B", "C"), e("e3", "C", "A"),
+            e("e4", "C", "D"),
+            e("e5", "D", "E"), e("e6", "E", "F"), e("e7", "F", "D"),
+            e("e8", "F", "G"), e("e9", "G", "F"),
+            e("e10", "G", "H"), e("e11", "H", "H"),
+        ];
+        let ids: Vec<String> = bridges(&edges).into_iter().map(|b| b.gen_id).collect();
+        assert_eq!(ids, vec!["e10", "e4"]);
+    }
+
+    #[test]
+    fn test_cut_vertex_joining_two_triangles() {
+        // triangles A-B-C and C-D-E share only C; E-F hangs off E
+        let e = |id: &str, s: &str, d: &str| edge(s, d, 1.0).with_id(id);
+        let edges = vec![
+            e("e1", "A", "B"), e("e2", "B", "C"), e("e3", "C", "A"),
+            e("e4", "C", "D"), e("e5", "D", "E"), e("e6", "E", "C"),
+            e("e7", "E", "F"), e("e8", "X", "Y"),
+        ];
+        let cuts: Vec<(String, usize)> =
+            articulation_points(&edges).into_iter().map(|c| (c.node, c.components)).collect();
+        assert_eq!(cuts, vec![("C".to_string(), 2), ("E".to_string(), 2)]);
+        // starting the DFS at the cut vertex itself exercises the root rule
+        let mut rooted = edges.clone();
+        rooted.rotate_left(3);
+        let nodes: Vec<String> = articulation_points(&rooted).into_iter().map(|c| c.node).collect();
+        assert_eq!(nodes, vec!["C", "E"]);
+    }
+}
@@ -0,0 +1,77 @@
+//! cycles.rs — Singine directed cycle detection
+//!
+//! Lineage must be acyclic. `find_cycle` runs an iterative three-colour DFS
+//! over the directed src → dst graph and returns the first cycle found as a
+//! closed node list (`[a, b, c, a]`). Nodes and neighbours are visited in
+//! sorted order so the reported cycle is deterministic.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::shortest_path::Edge;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Colour {
+    White,
+    Grey,
+    Black,
+}
+
+pub fn find_cycle(edges: &[Edge]) -> Option<Vec<String>> {
+    let mut succ: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for e in edges {
+        succ.entry(e.src_id.as_str()).or_default().insert(e.dst_id.as_str());
+        succ.entry(e.dst_id.as_str()).or_default();
+    }
+    let mut colour: HashMap<&str, Colour> = succ.keys().map(|&n| (n, Colour::White)).collect();
+
+    for &root in succ.keys() {
+        if colour[root] != Colour::White {
+            continue;
+        }
+        // stack of (node, remaining successors)
+        let mut stack: Vec<(&str, Vec<&str>)> = vec![(root, succ[root].iter().rev().copied().collect())];
+        colour.insert(root, Colour::Grey);
+        while let Some(top) = stack.last_mut() {
+            let node = top.0;
+            match top.1.pop() {
+                Some(next) => match colour[next] {
+                    Colour::White => {
+                        colour.insert(next, Colour::Grey);
+                        stack.push((next, succ[next].iter().rev().copied().collect()));
+                    }
+                    Colour::Grey => {
+                        let start = stack.iter().position(|(n, _)| *n == next).expect("grey on stack");
+                        let mut cycle: Vec<String> =
+                            stack[start..].iter().map(|(n, _)| n.to_string()).collect();
+                        cycle.push(next.to_string());
+                        return Some(cycle);
+                    }
+                    Colour::Black => {}
+                },
+                None => {
+                    colour.insert(node, Colour::Black);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::edge;
+
+    #[test]
+    fn test_finds_cycle() {
+        let edges = vec![edge("A", "B", 1.0), edge("B", "C", 1.0), edge("C", "A", 1.0), edge("C", "D", 1.0)];
+        assert_eq!(find_cycle(&edges).unwrap(), vec!["A", "B", "C", "A"]);
+    }
+
+    #[test]
+    fn test_dag_has_no_cycle() {
+        let edges = vec![edge("A", "B", 1.0), edge("A", "C", 1.0), edge("B", "D", 1.0), edge("C", "D", 1.0)];
+        assert!(find_cycle(&edges).is_none());
+    }
+}
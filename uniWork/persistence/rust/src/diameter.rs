@@ -0,0 +1,81 @@
+//! diameter.rs — Singine eccentricity, radius and diameter
+//!
+//! `--mode diameter` runs one single-source Dijkstra per node (V searches,
+//! so only for small graphs — guarded by `--max-nodes`). A node's
+//! eccentricity is its largest distance to any node it can reach; the radius
+//! and diameter are the smallest and largest eccentricities. On a
+//! disconnected graph these are per-component figures and `connected` is
+//! false; like `--mode components`, connectivity ignores direction, so a
+//! sink of a one-way arc has eccentricity 0 without disconnecting the graph.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::components::count_components;
+use crate::shortest_path::{all_nodes, distances_from, Adjacency};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diameter {
+    pub nodes:        usize,
+    pub connected:    bool,
+    pub radius:       Option<f64>,
+    pub diameter:     Option<f64>,
+    /// nodes whose eccentricity equals the radius
+    pub center:       Vec<String>,
+    pub eccentricity: BTreeMap<String, f64>,
+}
+
+pub fn diameter(adj: &Adjacency, max_nodes: usize) -> anyhow::Result<Diameter> {
+    let nodes = all_nodes(adj);
+    if nodes.len() > max_nodes {
+        anyhow::bail!(
+            "graph has {} nodes, over --max-nodes {}; diameter runs one search per node",
+            nodes.len(),
+            max_nodes
+        );
+    }
+    let eccentricity: BTreeMap<String, f64> = nodes
+        .iter()
+        .map(|&node| {
+            let dist = distances_from(adj, node);
+            (node.to_string(), dist.values().cloned().fold(0.0, f64::max))
+        })
+        .collect();
+    let radius = eccentricity.values().cloned().reduce(f64::min);
+    let diameter = eccentricity.values().cloned().reduce(f64::max);
+    let center = eccentricity
+        .iter()
+        .filter(|(_, &e)| Some(e) == radius)
+        .map(|(n, _)| n.clone())
+        .collect();
+    let connected = count_components(adj) <= 1;
+    Ok(Diameter { nodes: nodes.len(), connected, radius, diameter, center, eccentricity })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, edge, Edge};
+
+    #[test]
+    fn test_line_graph_diameter_is_end_to_end_weight() {
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0), edge("B", "C", 2.0), edge("C", "D", 3.0), edge("D", "E", 0.5),
+        ]);
+        let d = diameter(&adj, 100).unwrap();
+        assert!(d.connected);
+        assert_eq!(d.diameter, Some(6.5));
+        assert_eq!(d.eccentricity["A"], 6.5);
+        assert_eq!(d.radius, Some(3.5));
+        assert_eq!(d.center, vec!["C"]);
+        assert!(diameter(&adj, 4).unwrap_err().to_string().contains("--max-nodes"));
+
+        // C is only the sink of a one-way arc: listed, and the graph stays connected
+        let adj = build_adjacency(&[edge("A", "B", 1.0), Edge { directed: Some(true), ..edge("B", "C", 2.0) }]);
+        let d = diameter(&adj, 100).unwrap();
+        assert_eq!(d.nodes, 3);
+        assert!(d.connected);
+        assert_eq!(d.eccentricity["C"], 0.0);
+        assert_eq!(d.diameter, Some(3.0));
+    }
+}
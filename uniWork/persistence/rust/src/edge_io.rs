@@ -0,0 +1,621 @@
+//! edge_io.rs — Singine edge import
+//!
+//! `--mode import-edges --input edges.csv` bulk-loads `similarity_edges` from
+//! CSV inside a single transaction. The header names the columns:
+//!
+//!   src_id,dst_id,weight[,edge_type][,gen_id][,directed][,reverse_weight]
+//!
+//! Missing gen_ids are allocated from the `edge` namespace; edge_type
+//! defaults to `similarity`, and `\N` stands for a NULL (untyped) edge_type.
+//! `directed` is `true`/`false` (or `1`/`0`); it and `reverse_weight` may be
+//! left empty for NULL, and need the matching optional columns on
+//! `similarity_edges` once a row sets them. Fields may be double-quoted
+//! (RFC 4180).
+//!
+//! `--mode export-edges --output edges.csv` writes the edge table back out in
+//! that format, every column included, so export → import into a fresh
+//! database reproduces the same edges. Ids with leading or trailing spaces
+//! come back trimmed, as on any import.
+//!
+//! With `--fail-on-cycle` the directed graph of the imported edge types is
+//! checked after insertion; a cycle rolls the whole import back.
+//!
+//! `--edges-json-adjacency file` bypasses the database and builds the graph
+//! from a nested `{node: {neighbour: weight}}` export. A pair listed in both
+//! directions is one undirected edge.
+//!
+//! `--mode snapshot --output snap.json` writes the full edge table with the
+//! schema version and graph hash; `--edges-json snap.json` later rebuilds the
+//! exact same graph without the database.
+//!
+//! `--mode build-adjacency` materializes the built graph into
+//! `adjacency(node_id, neighbor_id, weight)`, one row per directed arc (an
+//! undirected edge gives two), replacing the previous contents in one
+//! transaction.
+//!
+//! `--mode shard-edges --out-dir shards/` splits `similarity_edges` into one
+//! SQLite file per edge_type (`shards/lineage.db`, ...). Each shard gets the
+//! full migrated schema, plus any extra columns the source edge table has,
+//! so it works as a standalone database. Existing shard files are refused
+//! rather than overwritten, and rows without an edge_type are skipped. Types
+//! whose file names would collide get a numeric suffix; the report lists the
+//! path of each type's shard.
+//!
+//! Both JSON inputs accept comments and trailing commas with `--json5`
+//! (feature `json5`).
+
+use rusqlite::{Connection, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::cache;
+use crate::cycles;
+use crate::id_gen;
+use crate::seed;
+use crate::shortest_path::{self, Adjacency, Edge, GraphOptions};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub inserted:    usize,
+    pub edge_types:  Vec<String>,
+    /// offending cycle when --fail-on-cycle rejected the import
+    pub cycle:       Option<Vec<String>>,
+    pub rolled_back: bool,
+}
+
+/// Split one CSV record, honouring double quotes and `""` escapes.
+pub fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// CSV stand-in for a NULL edge_type.
+const NULL_FIELD: &str = "\\N";
+
+/// Edges as `import-edges` CSV, in gen_id order. Weights use the shortest
+/// representation that parses back to the same f64; an empty edge_type (a
+/// NULL in the table) is written as `\N`.
+pub fn export_edges_csv(edges: &[Edge]) -> String {
+    let mut sorted: Vec<&Edge> = edges.iter().collect();
+    sorted.sort_by(|a, b| a.gen_id.cmp(&b.gen_id));
+    let mut csv = String::from("src_id,dst_id,weight,edge_type,gen_id,directed,reverse_weight\n");
+    for e in sorted {
+        let edge_type = if e.edge_type.is_empty() { NULL_FIELD.to_string() } else { csv_field(&e.edge_type) };
+        let row = [csv_field(&e.src_id), csv_field(&e.dst_id), e.weight.to_string(), edge_type,
+                   csv_field(&e.gen_id), e.directed.map_or(String::new(), |d| d.to_string()),
+                   e.reverse_weight.map_or(String::new(), |w| w.to_string())];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn import_edges_csv(conn: &Connection, text: &str, fail_on_cycle: bool) -> anyhow::Result<ImportReport> {
+    let mut lines = text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    let (_, header) = lines.next().ok_or_else(|| anyhow::anyhow!("empty CSV: header row required"))?;
+    let header: Vec<String> = split_csv_line(header).iter().map(|h| h.trim().to_lowercase()).collect();
+    let col = |name: &str| header.iter().position(|h| h == name);
+    let (Some(src_col), Some(dst_col), Some(weight_col)) = (col("src_id"), col("dst_id"), col("weight")) else {
+        anyhow::bail!("CSV header must include src_id, dst_id and weight (got: {})", header.join(","));
+    };
+    let type_col = col("edge_type");
+    let id_col = col("gen_id");
+    let (directed_col, reverse_col) = (col("directed"), col("reverse_weight"));
+
+    let tx = conn.unchecked_transaction()?;
+    let table = shortest_path::table_columns(&tx, "similarity_edges")?;
+    let (has_directed, has_reverse) = (table.contains("directed"), table.contains("reverse_weight"));
+    let mut columns = vec!["gen_id", "src_id", "dst_id", "weight", "edge_type"];
+    columns.extend(has_directed.then_some("directed"));
+    columns.extend(has_reverse.then_some("reverse_weight"));
+    let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+    let sql = format!("INSERT INTO similarity_edges ({}) VALUES ({})", columns.join(", "), placeholders.join(","));
+    let mut inserted = 0;
+    let mut types: BTreeSet<String> = BTreeSet::new();
+    for (lineno, line) in lines {
+        let fields = split_csv_line(line);
+        let field = |i: usize| fields.get(i).map(|f| f.trim()).filter(|f| !f.is_empty());
+        let (Some(src), Some(dst), Some(weight)) = (field(src_col), field(dst_col), field(weight_col)) else {
+            anyhow::bail!("line {}: src_id, dst_id and weight are required", lineno + 1);
+        };
+        let weight: f64 = weight
+            .parse()
+            .map_err(|_| anyhow::anyhow!("line {}: invalid weight '{}'", lineno + 1, weight))?;
+        let edge_type = match type_col.and_then(field) {
+            Some(NULL_FIELD) => None,
+            t => Some(t.unwrap_or("similarity")),
+        };
+        let gen_id = match id_col.and_then(field) {
+            Some(id) => id.to_string(),
+            None => id_gen::generate(&tx, "edge", None)?.gen_id,
+        };
+        let directed = match directed_col.and_then(field) {
+            None => None,
+            Some("true" | "1") => Some(true),
+            Some("false" | "0") => Some(false),
+            Some(other) => anyhow::bail!("line {}: invalid directed '{}' (true or false)", lineno + 1, other),
+        };
+        let reverse_weight = match reverse_col.and_then(field) {
+            None => None,
+            Some(w) => Some(
+                w.parse::<f64>()
+                    .map_err(|_| anyhow::anyhow!("line {}: invalid reverse_weight '{}'", lineno + 1, w))?,
+            ),
+        };
+        if (directed.is_some() && !has_directed) || (reverse_weight.is_some() && !has_reverse) {
+            anyhow::bail!(
+                "line {}: similarity_edges has no {} column",
+                lineno + 1,
+                if directed.is_some() && !has_directed { "directed" } else { "reverse_weight" }
+            );
+        }
+        let mut values: Vec<&dyn rusqlite::ToSql> = vec![&gen_id, &src, &dst, &weight, &edge_type];
+        if has_directed {
+            values.push(&directed);
+        }
+        if has_reverse {
+            values.push(&reverse_weight);
+        }
+        tx.execute(&sql, values.as_slice())?;
+        types.extend(edge_type.map(String::from));
+        inserted += 1;
+    }
+
+    let mut report = ImportReport {
+        inserted,
+        edge_types: types.iter().cloned().collect(),
+        cycle: None,
+        rolled_back: false,
+    };
+    if fail_on_cycle {
+        let mut edges = shortest_path::load_edges(&tx, None)?;
+        edges.retain(|e| types.contains(&e.edge_type));
+        if let Some(cycle) = cycles::find_cycle(&edges) {
+            tracing::error!(cycle = ?cycle, "Import introduces a cycle, rolling back");
+            tx.rollback()?;
+            report.cycle = Some(cycle);
+            report.rolled_back = true;
+            return Ok(report);
+        }
+    }
+    tx.commit()?;
+    tracing::info!(inserted, "Imported edges");
+    Ok(report)
+}
+
+/// Parse a nested JSON adjacency into edges, one per unordered pair.
+pub fn parse_json_adjacency(text: &str) -> anyhow::Result<Vec<Edge>> {
+    let nested: BTreeMap<String, BTreeMap<String, f64>> = serde_json::from_str(text)
+        .map_err(|e| anyhow::anyhow!("invalid JSON adjacency (expected {{node: {{neighbour: weight}}}}): {}", e))?;
+    let mut seen: BTreeSet<(&str, &str)> = BTreeSet::new();
+    let mut edges = Vec::new();
+    for (src, neighbours) in &nested {
+        for (dst, &weight) in neighbours {
+            let key = if src <= dst { (src.as_str(), dst.as_str()) } else { (dst.as_str(), src.as_str()) };
+            // the reverse listing of an undirected edge
+            let mirrored = src != dst && nested.get(dst).and_then(|n| n.get(src)) == Some(&weight);
+            if mirrored && !seen.insert(key) {
+                continue;
+            }
+            edges.push(Edge {
+                gen_id: format!("json:{}:{}", src, dst),
+                src_id: src.clone(),
+                dst_id: dst.clone(),
+                weight,
+                edge_type: "json".to_string(),
+                directed: None,
+                reverse_weight: None,
+            });
+        }
+    }
+    Ok(edges)
+}
+
+/// Contents of a JSON input file (gunzipped if compressed), made strict first
+/// under `opts.lenient_json`.
+fn read_json(path: &Path, opts: &GraphOptions) -> anyhow::Result<String> {
+    let text = crate::output::read_file(path)?;
+    if opts.lenient_json {
+        return lenient(&text);
+    }
+    Ok(text)
+}
+
+#[cfg(feature = "json5")]
+fn lenient(text: &str) -> anyhow::Result<String> {
+    crate::json5::to_strict(text)
+}
+
+#[cfg(not(feature = "json5"))]
+fn lenient(_text: &str) -> anyhow::Result<String> {
+    anyhow::bail!("--json5 requires building with --features json5")
+}
+
+/// Edge rows of a JSON adjacency file, before the in-memory filters.
+pub fn json_adjacency_edges(path: &Path, opts: &GraphOptions) -> anyhow::Result<Vec<Edge>> {
+    let edges = parse_json_adjacency(&read_json(path, opts)?)?;
+    if opts.edge_type.is_some() || opts.as_of.is_some() || opts.weight_column.is_some() {
+        tracing::warn!("--edge-type, --as-of and --weight-column do not apply to a JSON adjacency");
+    }
+    Ok(edges)
+}
+
+/// Graph from a JSON adjacency file, shaped by the in-memory graph options.
+pub fn load_json_adjacency(path: &Path, opts: &GraphOptions) -> anyhow::Result<Adjacency> {
+    let mut edges = json_adjacency_edges(path, opts)?;
+    opts.apply(&mut edges);
+    tracing::info!(edge_count = edges.len(), path = %path.display(), "Loaded JSON adjacency");
+    shortest_path::quicksort_edges(&mut edges);
+    Ok(shortest_path::build_adjacency_with(&edges, opts))
+}
+
+/// Self-contained copy of the edge table for reproducing an analysis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub schema_version: String,
+    pub graph_hash:     String,
+    pub created_at:     String,
+    pub edges:          Vec<Edge>,
+}
+
+/// Every edge in `similarity_edges`, unfiltered, in gen_id order.
+pub fn take_snapshot(conn: &Connection, schema_version: String) -> SqlResult<Snapshot> {
+    let mut edges = shortest_path::load_edges_with(conn, &GraphOptions::default())?;
+    edges.sort_by(|a, b| a.gen_id.cmp(&b.gen_id));
+    Ok(Snapshot {
+        schema_version,
+        graph_hash: cache::graph_hash(conn, &GraphOptions::default())?,
+        created_at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        edges,
+    })
+}
+
+/// Edge rows of a snapshot file, before the in-memory filters. `--edge-type`
+/// still filters; options that need columns a snapshot does not carry are
+/// ignored with a warning.
+pub fn snapshot_edges(path: &Path, opts: &GraphOptions) -> anyhow::Result<Vec<Edge>> {
+    let snap: Snapshot = serde_json::from_str(&read_json(path, opts)?)
+        .map_err(|e| anyhow::anyhow!("invalid snapshot {}: {}", path.display(), e))?;
+    let mut edges = snap.edges;
+    if let Some(t) = &opts.edge_type {
+        edges.retain(|e| &e.edge_type == t || (opts.include_untyped && e.edge_type.is_empty()));
+    }
+    if opts.as_of.is_some() || opts.weight_column.is_some() || opts.use_node_weights {
+        tracing::warn!("--as-of, --weight-column and --use-node-weights do not apply to a snapshot");
+    }
+    tracing::info!(
+        edge_count = edges.len(),
+        graph_hash = %snap.graph_hash,
+        schema_version = %snap.schema_version,
+        "Loaded graph snapshot"
+    );
+    Ok(edges)
+}
+
+/// Graph from a snapshot file, shaped by the in-memory graph options.
+pub fn load_snapshot(path: &Path, opts: &GraphOptions) -> anyhow::Result<Adjacency> {
+    let mut edges = snapshot_edges(path, opts)?;
+    opts.apply(&mut edges);
+    shortest_path::quicksort_edges(&mut edges);
+    Ok(shortest_path::build_adjacency_with(&edges, opts))
+}
+
+/// Replace the `adjacency` table with every arc of `adj`; returns the row count.
+pub fn write_adjacency(conn: &Connection, adj: &Adjacency) -> SqlResult<usize> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS adjacency (
+           node_id     TEXT NOT NULL,
+           neighbor_id TEXT NOT NULL,
+           weight      REAL NOT NULL
+         );
+         DELETE FROM adjacency;",
+    )?;
+    let mut nodes: Vec<&String> = adj.keys().collect();
+    nodes.sort();
+    let mut rows = 0;
+    {
+        let mut stmt = tx.prepare("INSERT INTO adjacency (node_id, neighbor_id, weight) VALUES (?1,?2,?3)")?;
+        for node in nodes {
+            for (next, w) in &adj[node] {
+                stmt.execute(rusqlite::params![node, next, w])?;
+                rows += 1;
+            }
+        }
+    }
+    tx.commit()?;
+    tracing::info!(rows, nodes = adj.len(), "Materialized adjacency table");
+    Ok(rows)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Shard {
+    pub edge_type: String,
+    pub path:      PathBuf,
+    pub rows:      usize,
+}
+
+/// `(name, declared type)` of every column of `table` in schema `db`.
+fn columns(conn: &Connection, db: &str, table: &str) -> SqlResult<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info({})", db, table))?;
+    let cols = stmt.query_map([], |r| Ok((r.get(1)?, r.get(2)?)))?.collect();
+    cols
+}
+
+/// File names for the shards of `types`, in order; anything but
+/// `[A-Za-z0-9_-]` becomes `_`. Types that would share a file (`a b` and
+/// `a_b`, or names differing only in case on a case-insensitive filesystem)
+/// get `-2`, `-3`, ... suffixes in order.
+fn shard_names(types: &[String]) -> Vec<String> {
+    let mut taken: HashSet<String> = HashSet::new();
+    types
+        .iter()
+        .map(|edge_type| {
+            let stem: String = edge_type
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+                .collect();
+            let mut name = stem.clone();
+            let mut n = 2;
+            while !taken.insert(name.to_lowercase()) {
+                name = format!("{}-{}", stem, n);
+                n += 1;
+            }
+            if name != stem {
+                tracing::warn!(edge_type = %edge_type, file = %name, "Shard file name collides, suffixed");
+            }
+            format!("{}.db", name)
+        })
+        .collect()
+}
+
+/// Write one migrated database per edge type under `out_dir`.
+pub fn shard_edges(conn: &Connection, out_dir: &Path) -> anyhow::Result<Vec<Shard>> {
+    let types: Vec<String> = conn
+        .prepare("SELECT DISTINCT edge_type FROM similarity_edges WHERE edge_type IS NOT NULL ORDER BY edge_type")?
+        .query_map([], |r| r.get(0))?
+        .collect::<SqlResult<_>>()?;
+    let untyped: i64 = conn.query_row("SELECT COUNT(*) FROM similarity_edges WHERE edge_type IS NULL", [], |r| r.get(0))?;
+    if untyped > 0 {
+        tracing::warn!(rows = untyped, "Skipping edges without an edge_type");
+    }
+    let paths: Vec<PathBuf> = shard_names(&types).iter().map(|name| out_dir.join(name)).collect();
+    if let Some(existing) = paths.iter().find(|p| p.exists()) {
+        anyhow::bail!("{} already exists; remove it or choose another --out-dir", existing.display());
+    }
+    std::fs::create_dir_all(out_dir)?;
+
+    let source = columns(conn, "main", "similarity_edges")?;
+    let mut shards = Vec::with_capacity(types.len());
+    for (edge_type, path) in types.into_iter().zip(paths) {
+        let shard = Connection::open(&path)?;
+        seed::apply_migrations(&shard)?;
+        let present: BTreeSet<String> = columns(&shard, "main", "similarity_edges")?.into_iter().map(|c| c.0).collect();
+        for (name, decl) in source.iter().filter(|c| !present.contains(&c.0)) {
+            shard.execute_batch(&format!("ALTER TABLE similarity_edges ADD COLUMN \"{}\" {}", name, decl))?;
+        }
+        drop(shard);
+
+        let cols = source.iter().map(|c| format!("\"{}\"", c.0)).collect::<Vec<_>>().join(", ");
+        conn.execute("ATTACH DATABASE ?1 AS shard", [path.to_string_lossy()])?;
+        let copied = conn.execute(
+            &format!(
+                "INSERT INTO shard.similarity_edges ({0}) SELECT {0} FROM main.similarity_edges WHERE edge_type = ?1",
+                cols
+            ),
+            [&edge_type],
+        );
+        conn.execute_batch("DETACH DATABASE shard")?;
+        let rows = copied?;
+        tracing::info!(edge_type = %edge_type, rows, path = %path.display(), "Wrote edge shard");
+        shards.push(Shard { edge_type, path, rows });
+    }
+    Ok(shards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT,
+                                            weight REAL, edge_type TEXT);
+             INSERT INTO similarity_edges VALUES ('e0','A','B',1.0,'lineage');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM similarity_edges", [], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_import_with_quotes_and_generated_ids() {
+        let conn = db();
+        let csv = "src_id,dst_id,weight,edge_type\nB,\"C, Inc\",2.5,lineage\nC,D,1,\n";
+        let report = import_edges_csv(&conn, csv, true).unwrap();
+        assert_eq!(report.inserted, 2);
+        assert!(!report.rolled_back);
+        assert_eq!(count(&conn), 3);
+        let dst: String = conn
+            .query_row("SELECT dst_id FROM similarity_edges WHERE src_id='B'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(dst, "C, Inc");
+        let err = import_edges_csv(&conn, "src_id,dst_id,weight,directed\nA,B,1,true\n", false).unwrap_err();
+        assert!(err.to_string().contains("no directed column"), "{}", err);
+        assert_eq!(count(&conn), 3, "nothing committed");
+    }
+
+    #[test]
+    fn test_export_then_import_round_trips() {
+        let with_direction = || {
+            let conn = db();
+            conn.execute_batch(
+                "ALTER TABLE similarity_edges ADD COLUMN directed INTEGER;
+                 ALTER TABLE similarity_edges ADD COLUMN reverse_weight REAL;",
+            )
+            .unwrap();
+            conn
+        };
+        let conn = with_direction();
+        let csv = "src_id,dst_id,weight,edge_type,gen_id,directed,reverse_weight\n\
+                   B,\"C, \"\"Inc\"\"\",0.1,lineage,,true,\n C ,D,1e-7,,,,3\nD,E,2,\\N,e9,0,\n";
+        import_edges_csv(&conn, csv, false).unwrap();
+        let untyped = |c: &Connection| -> i64 {
+            c.query_row("SELECT COUNT(*) FROM similarity_edges WHERE edge_type IS NULL", [], |r| r.get(0)).unwrap()
+        };
+        assert_eq!(untyped(&conn), 1);
+        let edges = shortest_path::load_edges(&conn, None).unwrap();
+        let exported = export_edges_csv(&edges);
+
+        let fresh = with_direction();
+        fresh.execute("DELETE FROM similarity_edges", []).unwrap();
+        assert_eq!(import_edges_csv(&fresh, &exported, false).unwrap().inserted, 4);
+        assert_eq!(untyped(&fresh), 1, "a NULL type stays NULL");
+        let key = |mut es: Vec<Edge>| {
+            es.sort_by(|a, b| a.gen_id.cmp(&b.gen_id));
+            es.into_iter()
+                .map(|e| (e.gen_id, e.src_id, e.dst_id, e.weight.to_bits(), e.edge_type, e.directed,
+                          e.reverse_weight.map(f64::to_bits)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(key(shortest_path::load_edges(&fresh, None).unwrap()), key(edges));
+        let hash = |c: &Connection| cache::graph_hash(c, &GraphOptions::default()).unwrap();
+        assert_eq!(hash(&fresh), hash(&conn));
+    }
+
+    #[test]
+    fn test_json_adjacency_shortest_path() {
+        let json = r#"{"A": {"B": 1.0, "C": 10.0}, "B": {"A": 1.0, "C": 2.0}, "C": {}}"#;
+        let edges = parse_json_adjacency(json).unwrap();
+        assert_eq!(edges.len(), 3, "mirrored A-B listed once");
+        let path = std::env::temp_dir().join(format!("adj-{}.json", std::process::id()));
+        std::fs::write(&path, json).unwrap();
+        let adj = load_json_adjacency(&path, &GraphOptions::default()).unwrap();
+        std::fs::remove_file(&path).ok();
+        let r = shortest_path::dijkstra(&adj, "C", "A").unwrap();
+        assert_eq!(r.path, vec!["C", "B", "A"]);
+        assert_eq!(r.total_weight, 3.0);
+    }
+
+    #[test]
+    fn test_snapshot_reproduces_path() {
+        let conn = db();
+        import_edges_csv(&conn, "src_id,dst_id,weight,edge_type\nB,C,2,lineage\nA,C,5,lineage\n", false).unwrap();
+        let live_adj = shortest_path::load_graph(&conn, &GraphOptions::default()).unwrap();
+        let live = shortest_path::dijkstra(&live_adj, "A", "C").unwrap();
+
+        let snap = take_snapshot(&conn, "V003".into()).unwrap();
+        assert_eq!(snap.edges.len(), 3);
+        assert_eq!(snap.graph_hash, cache::graph_hash(&conn, &GraphOptions::default()).unwrap());
+        let path = std::env::temp_dir().join(format!("snap-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&snap).unwrap()).unwrap();
+        conn.execute("DELETE FROM similarity_edges", []).unwrap();
+
+        let adj = load_snapshot(&path, &GraphOptions::default()).unwrap();
+        std::fs::remove_file(&path).ok();
+        let replayed = shortest_path::dijkstra(&adj, "A", "C").unwrap();
+        assert_eq!(replayed.path, live.path);
+        assert_eq!(replayed.total_weight, live.total_weight);
+    }
+
+    #[test]
+    fn test_fail_on_cycle_rolls_back() {
+        let conn = db();
+        let csv = "src_id,dst_id,weight,edge_type,gen_id\nB,C,1,lineage,e1\nC,A,1,lineage,e2\n";
+        let report = import_edges_csv(&conn, csv, true).unwrap();
+        assert!(report.rolled_back);
+        assert_eq!(report.cycle.unwrap(), vec!["A", "B", "C", "A"]);
+        assert_eq!(count(&conn), 1);
+    }
+
+    #[test]
+    fn test_write_adjacency_triangle_with_reverse_arcs() {
+        let conn = db();
+        conn.execute_batch(
+            "INSERT INTO similarity_edges VALUES ('e1','B','C',2.0,'lineage'), ('e2','A','C',4.0,'lineage');
+             CREATE TABLE adjacency (node_id TEXT, neighbor_id TEXT, weight REAL);
+             INSERT INTO adjacency VALUES ('stale','row',0);",
+        )
+        .unwrap();
+        let adj = shortest_path::load_graph(&conn, &GraphOptions::default()).unwrap();
+        assert_eq!(write_adjacency(&conn, &adj).unwrap(), 6);
+        let mut stmt = conn.prepare("SELECT node_id, neighbor_id, weight FROM adjacency ORDER BY 1, 2").unwrap();
+        let rows: Vec<(String, String, f64)> = stmt
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .collect::<SqlResult<_>>()
+            .unwrap();
+        let expected = [
+            ("A", "B", 1.0), ("A", "C", 4.0),
+            ("B", "A", 1.0), ("B", "C", 2.0),
+            ("C", "A", 4.0), ("C", "B", 2.0),
+        ];
+        let expected: Vec<_> = expected.iter().map(|&(a, b, w)| (a.to_string(), b.to_string(), w)).collect();
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn test_shard_three_types_counts_per_file() {
+        let conn = Connection::open_in_memory().unwrap();
+        seed::apply_migrations(&conn).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE similarity_edges ADD COLUMN directed INTEGER;
+             INSERT INTO similarity_edges (gen_id, src_id, dst_id, weight, edge_type, directed) VALUES
+               ('e1','A','B',1.0,'lineage',1), ('e2','B','C',1.0,'lineage',NULL),
+               ('e3','A','C',2.0,'category',NULL),
+               ('e4','C','D',0.5,'similarity',NULL), ('e5','D','E',0.5,'similarity',NULL),
+               ('e6','E','F',0.5,'similarity',NULL);",
+        )
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("shards-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let shards = shard_edges(&conn, &dir).unwrap();
+        let counts: Vec<(&str, usize)> = shards.iter().map(|s| (s.edge_type.as_str(), s.rows)).collect();
+        assert_eq!(counts, vec![("category", 1), ("lineage", 2), ("similarity", 3)]);
+
+        let lineage = Connection::open(dir.join("lineage.db")).unwrap();
+        let (rows, directed): (i64, i64) = lineage
+            .query_row("SELECT COUNT(*), SUM(directed) FROM similarity_edges", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!((rows, directed), (2, 1));
+        let migrated: i64 = lineage.query_row("SELECT COUNT(*) FROM schema_migrations", [], |r| r.get(0)).unwrap();
+        assert_eq!(migrated, 4);
+        drop(lineage);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+        assert!(shard_edges(&conn, &dir).unwrap_err().to_string().contains("already exists"));
+        std::fs::remove_dir_all(&dir).ok();
+
+        let types: Vec<String> = ["a b", "a_b", "a_b-2", "A_B", "lineage"].iter().map(|t| t.to_string()).collect();
+        assert_eq!(shard_names(&types), vec!["a_b.db", "a_b-2.db", "a_b-2-2.db", "A_B-3.db", "lineage.db"]);
+    }
+}
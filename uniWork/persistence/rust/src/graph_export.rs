@@ -0,0 +1,177 @@
+//! graph_export.rs — Singine graph exports for other tools
+//!
+//! `--mode export-networkx --output graph.json` writes the built graph (all
+//! load filters applied) in NetworkX's node-link shape, so that
+//! `networkx.readwrite.json_graph.node_link_graph(json.load(f))` rebuilds it:
+//!
+//!   {"directed": false, "multigraph": false, "graph": {},
+//!    "nodes": [{"id": "A"}, ...],
+//!    "links": [{"source": "A", "target": "B", "weight": 1.0}, ...]}
+//!
+//! `directed` follows `--directed`, and is also set when the graph has
+//! one-way arcs (per-row `directed`, or reverse weights that differ), since an
+//! undirected NetworkX graph would walk those backwards. An undirected export
+//! lists each pair once; parallel edges are already combined by the
+//! parallel-edge policy.
+//!
+//! `--mode export-cypher --output graph.cypher` writes the edge rows as Neo4j
+//! statements: one `MERGE (:Node {id: ...})` per node, then one `:EDGE`
+//! relationship per row carrying `gen_id`, `edge_type`, `weight` and
+//! `directed` as properties, src → dst. `--cypher-verb create` emits `CREATE`
+//! instead, for loading into an empty database quickly; `merge` (the default)
+//! can be re-run without duplicating anything.
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::shortest_path::{all_nodes, is_undirected, Adjacency, Edge};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeLink {
+    pub directed:   bool,
+    pub multigraph: bool,
+    pub graph:      serde_json::Map<String, serde_json::Value>,
+    pub nodes:      Vec<Node>,
+    pub links:      Vec<Link>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Node {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Link {
+    pub source: String,
+    pub target: String,
+    pub weight: f64,
+}
+
+/// Node-link data for `adj`, nodes and links sorted by id.
+pub fn node_link(adj: &Adjacency, directed: bool) -> NodeLink {
+    let directed = directed || !is_undirected(adj);
+    let nodes = all_nodes(adj);
+    let mut links = Vec::new();
+    for &node in &nodes {
+        let mut arcs: Vec<&(String, f64)> =
+            adj.get(node).into_iter().flatten().filter(|(next, _)| directed || node <= next.as_str()).collect();
+        arcs.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        links.extend(arcs.into_iter().map(|(next, w)| Link {
+            source: node.to_string(),
+            target: next.clone(),
+            weight: *w,
+        }));
+    }
+    NodeLink {
+        directed,
+        multigraph: false,
+        graph: serde_json::Map::new(),
+        nodes: nodes.into_iter().map(|id| Node { id: id.to_string() }).collect(),
+        links,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CypherVerb {
+    #[default]
+    Merge,
+    Create,
+}
+
+/// `s` as a single-quoted Cypher string literal.
+fn cypher_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('\'');
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Cypher statements for `edges`, nodes first, one statement per line.
+/// `directed` is the direction of rows without their own `directed` value.
+pub fn cypher(edges: &[Edge], directed: bool, verb: CypherVerb) -> String {
+    let verb = match verb {
+        CypherVerb::Merge => "MERGE",
+        CypherVerb::Create => "CREATE",
+    };
+    let nodes: BTreeSet<&str> = edges.iter().flat_map(|e| [e.src_id.as_str(), e.dst_id.as_str()]).collect();
+    let mut out = String::new();
+    for node in nodes {
+        out.push_str(&format!("{} (:Node {{id: {}}});\n", verb, cypher_string(node)));
+    }
+    for e in edges {
+        out.push_str(&format!(
+            "MATCH (a:Node {{id: {}}}), (b:Node {{id: {}}}) \
+             {} (a)-[:EDGE {{gen_id: {}, edge_type: {}, weight: {:?}, directed: {}}}]->(b);\n",
+            cypher_string(&e.src_id),
+            cypher_string(&e.dst_id),
+            verb,
+            cypher_string(&e.gen_id),
+            cypher_string(&e.edge_type),
+            e.weight,
+            e.directed.unwrap_or(directed),
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency_with, edge, GraphOptions};
+    use serde_json::json;
+
+    #[test]
+    fn test_node_link_schema_and_edge_count() {
+        let edges = vec![edge("A", "B", 1.0), edge("B", "C", 2.0), edge("A", "C", 10.0)];
+        let adj = build_adjacency_with(&edges, &GraphOptions::default());
+        let undirected = serde_json::to_value(node_link(&adj, false)).unwrap();
+        assert_eq!(undirected["directed"], json!(false));
+        assert_eq!(undirected["multigraph"], json!(false));
+        assert_eq!(undirected["graph"], json!({}));
+        assert_eq!(undirected["nodes"], json!([{"id": "A"}, {"id": "B"}, {"id": "C"}]));
+        assert_eq!(undirected["links"].as_array().unwrap().len(), 3);
+        assert_eq!(undirected["links"][0], json!({"source": "A", "target": "B", "weight": 1.0}));
+
+        let opts = GraphOptions { directed: true, ..Default::default() };
+        let directed = serde_json::to_value(node_link(&build_adjacency_with(&edges, &opts), true)).unwrap();
+        assert_eq!(directed["directed"], json!(true));
+        assert_eq!(directed["links"].as_array().unwrap().len(), 3);
+        assert_eq!(directed["nodes"].as_array().unwrap().len(), 3, "sink C is listed");
+        // forcing directed on an undirected graph lists both arcs of each pair
+        assert_eq!(node_link(&adj, true).links.len(), 6);
+
+        // a one-way B→A row makes the export directed rather than dropping it
+        let mixed = build_adjacency_with(&[Edge { directed: Some(true), ..edge("B", "A", 1.0) }, edge("B", "C", 2.0)],
+                                         &GraphOptions::default());
+        let data = node_link(&mixed, false);
+        assert!(data.directed);
+        let links: Vec<(&str, &str)> = data.links.iter().map(|l| (l.source.as_str(), l.target.as_str())).collect();
+        assert_eq!(links, vec![("B", "A"), ("B", "C"), ("C", "B")]);
+    }
+
+    #[test]
+    fn test_cypher_merges_nodes_and_edges() {
+        let edges = vec![edge("A", "B", 1.0), edge("B", "it's", 2.5)];
+        let text = cypher(&edges, false, CypherVerb::Merge);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.iter().filter(|l| l.starts_with("MERGE (:Node")).count(), 3);
+        assert!(lines.contains(&"MERGE (:Node {id: 'it\\'s'});"), "{}", text);
+        let rels: Vec<&&str> = lines.iter().filter(|l| l.contains("-[:EDGE")).collect();
+        assert_eq!(rels.len(), 2);
+        assert!(rels[0].contains("weight: 1.0"), "{}", rels[0]);
+        assert!(rels[1].contains("{id: 'it\\'s'}) MERGE (a)-[:EDGE {gen_id: 'B-it\\'s', edge_type: 'sim', weight: 2.5"));
+        assert_eq!(cypher_string("a\\b\nc"), "'a\\\\b\\nc'");
+        assert!(cypher(&edges, false, CypherVerb::Create).starts_with("CREATE (:Node {id: 'A'});"));
+    }
+}
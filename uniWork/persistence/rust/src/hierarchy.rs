@@ -0,0 +1,175 @@
+//! hierarchy.rs — Singine parent-chain walks
+//!
+//!   ancestors   — the chain from a node up to the root of its hierarchy
+//!   descendants — every node below a node, with its depth under it
+//!
+//! Hierarchy edges (`ldap_parent` by default, or whatever `--edge-type`
+//! selects) point child → parent: `src_id` is the child, `dst_id` its parent.
+//! Each node is assumed to have at most one parent; a second parent or a
+//! cycle on the way up is reported as an error rather than guessed around.
+//! Walking down, a cycle is reported alongside the subtree instead, and a
+//! node reachable through two parents is listed once, at its shallowest depth.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::shortest_path::Edge;
+
+/// Edge type walked when `--edge-type` is not given.
+pub const DEFAULT_EDGE_TYPE: &str = "ldap_parent";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Descendant {
+    pub node:   String,
+    /// hops below the subtree root
+    pub depth:  usize,
+    pub parent: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Subtree {
+    pub root:        String,
+    /// breadth-first: by depth, then by name
+    pub descendants: Vec<Descendant>,
+    /// parent loops found below the root, each as a node list ending where it started
+    pub cycles:      Vec<Vec<String>>,
+}
+
+/// `key` → its distinct linked nodes, sorted; child → parents when
+/// `upward`, parent → children otherwise.
+fn links(edges: &[Edge], upward: bool) -> BTreeMap<&str, Vec<&str>> {
+    let mut links: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for e in edges {
+        let (from, to) = if upward { (&e.src_id, &e.dst_id) } else { (&e.dst_id, &e.src_id) };
+        let of = links.entry(from.as_str()).or_default();
+        if !of.contains(&to.as_str()) {
+            of.push(to.as_str());
+        }
+    }
+    for of in links.values_mut() {
+        of.sort_unstable();
+    }
+    links
+}
+
+/// `node` followed by its parent, grandparent, … up to the root.
+pub fn ancestors(edges: &[Edge], node: &str) -> anyhow::Result<Vec<String>> {
+    let parents = links(edges, true);
+    let mut chain = vec![node.to_string()];
+    let mut seen: HashSet<&str> = HashSet::from([node]);
+    let mut at = node;
+    while let Some(of) = parents.get(at) {
+        if of.len() > 1 {
+            anyhow::bail!(
+                "node '{}' has {} parents ({}); ancestors assumes one parent per node",
+                at,
+                of.len(),
+                of.join(", ")
+            );
+        }
+        at = of[0];
+        if !seen.insert(at) {
+            anyhow::bail!("parent cycle through '{}' above '{}'", at, node);
+        }
+        chain.push(at.to_string());
+    }
+    Ok(chain)
+}
+
+/// Every node below `root`, breadth-first.
+pub fn descendants(edges: &[Edge], root: &str) -> Subtree {
+    let children = links(edges, false);
+    // node → (depth, BFS-tree parent); the root has no parent
+    let mut placed: HashMap<&str, (usize, Option<&str>)> = HashMap::from([(root, (0, None))]);
+    let mut queue: VecDeque<&str> = VecDeque::from([root]);
+    let mut found = Vec::new();
+    let mut cycles = Vec::new();
+
+    while let Some(node) = queue.pop_front() {
+        let depth = placed[node].0;
+        for &child in children.get(node).into_iter().flatten() {
+            if placed.contains_key(child) {
+                if let Some(cycle) = closing_cycle(&placed, node, child) {
+                    cycles.push(cycle);
+                }
+                continue;
+            }
+            placed.insert(child, (depth + 1, Some(node)));
+            found.push(Descendant { node: child.to_string(), depth: depth + 1, parent: node.to_string() });
+            queue.push_back(child);
+        }
+    }
+    if !cycles.is_empty() {
+        tracing::warn!(root = %root, cycles = cycles.len(), "Hierarchy below root contains parent cycles");
+    }
+    Subtree { root: root.to_string(), descendants: found, cycles }
+}
+
+/// If `child` is on the tree path from the root to `node`, the loop
+/// child → … → node → child.
+fn closing_cycle(placed: &HashMap<&str, (usize, Option<&str>)>, node: &str, child: &str) -> Option<Vec<String>> {
+    let mut up = vec![node.to_string()];
+    let mut at = node;
+    while at != child {
+        at = placed[at].1?;
+        up.push(at.to_string());
+    }
+    up.reverse();
+    up.push(child.to_string());
+    Some(up)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::edge;
+
+    fn parent(child: &str, of: &str) -> Edge {
+        edge(child, of, 1.0).with_type(DEFAULT_EDGE_TYPE)
+    }
+
+    #[test]
+    fn test_ancestor_chain_to_root() {
+        // ou=eng under ou=rd under o=corp; alice in ou=eng, bob in ou=rd
+        let edges = vec![
+            parent("alice", "eng"),
+            parent("eng", "rd"),
+            parent("rd", "corp"),
+            parent("bob", "rd"),
+        ];
+        assert_eq!(ancestors(&edges, "alice").unwrap(), vec!["alice", "eng", "rd", "corp"]);
+        assert_eq!(ancestors(&edges, "corp").unwrap(), vec!["corp"]);
+    }
+
+    #[test]
+    fn test_multiple_parents_and_cycles_error() {
+        let edges = vec![parent("alice", "eng"), parent("alice", "ops")];
+        let err = ancestors(&edges, "alice").unwrap_err().to_string();
+        assert!(err.contains("eng, ops"), "{}", err);
+
+        let edges = vec![parent("a", "b"), parent("b", "c"), parent("c", "b")];
+        assert!(ancestors(&edges, "a").unwrap_err().to_string().contains("cycle"));
+    }
+
+    #[test]
+    fn test_descendants_with_depths_and_cycle() {
+        let edges = vec![
+            parent("eng", "corp"),
+            parent("ops", "corp"),
+            parent("alice", "eng"),
+            parent("bob", "eng"),
+            parent("carol", "ops"),
+        ];
+        let sub = descendants(&edges, "corp");
+        let got: Vec<_> = sub.descendants.iter().map(|d| (d.node.as_str(), d.depth)).collect();
+        assert_eq!(got, vec![("eng", 1), ("ops", 1), ("alice", 2), ("bob", 2), ("carol", 2)]);
+        assert_eq!(sub.descendants[4].parent, "ops");
+        assert!(sub.cycles.is_empty());
+        assert!(descendants(&edges, "alice").descendants.is_empty());
+
+        // eng claims corp as its child as well: corp → eng → corp
+        let mut looped = edges.clone();
+        looped.push(parent("corp", "eng"));
+        assert_eq!(descendants(&looped, "corp").cycles, vec![vec!["corp", "eng", "corp"]]);
+    }
+}
@@ -4,11 +4,22 @@
 //! Each URN is:     urn:singine:<namespace>:<gen_id>
 //! Each inode is:   a monotonically increasing u64 persisted in SQLite
 //!
+//! Inodes come from an `InodeStore`. The default `SqliteInodeStore` keeps the
+//! counter next to the data and records every generated ID in `id_registry`,
+//! so the inodes issued per namespace can be audited as a gapless 1..n
+//! sequence (`verify_inodes`) and checked for gen_ids or urns recorded twice
+//! (`check_registry`). Deployments with a central allocator plug in
+//! their own store via `generate_with`.
+//!
 //! The "code gen key method" resolves the namespace from the URN map
-//! (schema/urn_map.json) so every generated ID is URN-addressable.
+//! (schema/urn_map.json) so every generated ID is URN-addressable: an
+//! `aliases` entry, or a `prefixes` entry of the form `urn:singine:<ns>:`,
+//! maps a logical name to its canonical namespace.
 
-use rusqlite::{Connection, Result as SqlResult};
+use rusqlite::{Connection, ErrorCode, OptionalExtension, Result as SqlResult};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,59 +29,571 @@ pub struct GenId {
     pub inode:  u64,
 }
 
-/// Generate a new inode-style ID, persist the inode counter in SQLite.
-pub fn generate(conn: &Connection, namespace: &str, hint: Option<&str>) -> SqlResult<GenId> {
-    // Ensure inode counter table exists
+/// A generated ID as reported by `--mode gen-id`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Generation {
+    #[serde(flatten)]
+    pub id:         GenId,
+    /// gen_ids rejected because the registry already held them
+    pub collisions: u32,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackfillReport {
+    /// tables with a gen_id column that were scanned
+    pub tables:     Vec<String>,
+    pub scanned:    u64,
+    pub backfilled: u64,
+    /// already present in id_registry
+    pub registered: u64,
+    pub unparsed:   u64,
+    /// first few gen_ids whose namespace could not be parsed
+    pub unparsed_samples: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactReport {
+    pub namespace:  String,
+    pub rows:       u64,
+    /// rows whose inode changed
+    pub renumbered: u64,
+    pub next_inode: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InodeReport {
+    pub namespace:  String,
+    pub count:      u64,
+    pub max_inode:  u64,
+    /// missing inode ranges, inclusive
+    pub gaps:       Vec<(u64, u64)>,
+    pub duplicates: Vec<u64>,
+    pub ok:         bool,
+}
+
+/// Registry rows sharing a gen_id or urn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// `gen_id` or `urn`
+    pub column: String,
+    pub value:  String,
+    /// (gen_id, inode) of each row, lowest inode first; the first is the one kept
+    pub rows:   Vec<(String, u64)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryCheck {
+    pub duplicates: Vec<DuplicateGroup>,
+    /// rows deleted by `fix`
+    pub removed:    u64,
+    pub ok:         bool,
+}
+
+/// Logical name → canonical namespace, from a URN map file. The default map
+/// resolves every name to itself.
+#[derive(Debug, Clone, Default)]
+pub struct UrnMap {
+    aliases: HashMap<String, String>,
+}
+
+impl UrnMap {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct File {
+            #[serde(default)]
+            prefixes: HashMap<String, String>,
+            #[serde(default)]
+            aliases:  HashMap<String, String>,
+        }
+        let file: File = serde_json::from_str(text)?;
+        let mut aliases: HashMap<String, String> = file
+            .prefixes
+            .into_iter()
+            .filter_map(|(name, prefix)| {
+                let ns = prefix.strip_prefix("urn:singine:")?.strip_suffix(':')?;
+                (!ns.is_empty()).then(|| (name, ns.to_string()))
+            })
+            .collect();
+        aliases.extend(file.aliases);
+        Ok(UrnMap { aliases })
+    }
+
+    /// Load `path`. A missing file yields the identity map unless `required`.
+    pub fn load(path: &Path, required: bool) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map_err(|e| anyhow::anyhow!("invalid URN map {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => {
+                tracing::debug!(path = %path.display(), "no URN map, namespaces used as given");
+                Ok(UrnMap::default())
+            }
+            Err(e) => Err(anyhow::anyhow!("cannot read URN map {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map(String::as_str).unwrap_or(name)
+    }
+}
+
+/// Ensure the inode counter and ID registry tables exist.
+pub fn ensure_tables(conn: &Connection) -> SqlResult<()> {
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS inode_counter (
            namespace TEXT NOT NULL PRIMARY KEY,
            next_inode INTEGER NOT NULL DEFAULT 1
-         );",
+         );
+         CREATE TABLE IF NOT EXISTS id_registry (
+           gen_id     TEXT    NOT NULL PRIMARY KEY,
+           urn        TEXT    NOT NULL,
+           namespace  TEXT    NOT NULL,
+           inode      INTEGER NOT NULL,
+           hint       TEXT,
+           created_at TEXT    NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now'))
+         );
+         CREATE INDEX IF NOT EXISTS idx_id_registry_ns ON id_registry(namespace, inode);",
+    )
+}
+
+/// Source of per-namespace inodes.
+pub trait InodeStore {
+    /// Allocate the next inode for `namespace` (first call returns 1).
+    fn next(&self, namespace: &str) -> SqlResult<u64>;
+
+    /// Record a freshly generated ID. Stores without a registry ignore it.
+    fn record(&self, _id: &GenId, _namespace: &str, _hint: Option<&str>) -> SqlResult<()> {
+        Ok(())
+    }
+
+    /// Registry timestamp of a recorded ID, if the store keeps one.
+    fn created_at(&self, _gen_id: &str) -> SqlResult<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Inode counter and ID registry in the same SQLite database as the data.
+pub struct SqliteInodeStore<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SqliteInodeStore<'a> {
+    pub fn new(conn: &'a Connection) -> SqlResult<Self> {
+        ensure_tables(conn)?;
+        Ok(SqliteInodeStore { conn })
+    }
+}
+
+impl InodeStore for SqliteInodeStore<'_> {
+    fn next(&self, namespace: &str) -> SqlResult<u64> {
+        // Atomically increment inode for this namespace
+        self.conn.execute(
+            "INSERT INTO inode_counter (namespace, next_inode) VALUES (?1, 2)
+             ON CONFLICT(namespace) DO UPDATE SET next_inode = next_inode + 1",
+            [namespace],
+        )?;
+        let inode = self.conn.query_row(
+            "SELECT next_inode - 1 FROM inode_counter WHERE namespace = ?1",
+            [namespace],
+            |r| r.get::<_, i64>(0),
+        )?;
+        Ok(inode as u64)
+    }
+
+    fn record(&self, id: &GenId, namespace: &str, hint: Option<&str>) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT INTO id_registry (gen_id, urn, namespace, inode, hint) VALUES (?1,?2,?3,?4,?5)",
+            rusqlite::params![id.gen_id, id.urn, namespace, id.inode as i64, hint],
+        )?;
+        Ok(())
+    }
+
+    fn created_at(&self, gen_id: &str) -> SqlResult<Option<String>> {
+        self.conn
+            .query_row("SELECT created_at FROM id_registry WHERE gen_id = ?1", [gen_id], |r| r.get(0))
+            .optional()
+    }
+}
+
+/// Attempts at a fresh uuid part before a registry collision is returned as an error.
+const MAX_ATTEMPTS: u32 = 8;
+
+/// Generate a new inode-style ID, persist the inode counter in SQLite.
+pub fn generate(conn: &Connection, namespace: &str, hint: Option<&str>) -> SqlResult<GenId> {
+    generate_with(&SqliteInodeStore::new(conn)?, namespace, hint)
+}
+
+/// `generate` with the namespace resolved through `urn_map`, also reporting
+/// collisions and the registry timestamp. With `max_inode`, refuses to
+/// allocate an inode above it (the counter is left untouched).
+pub fn generate_tracked(
+    conn: &Connection,
+    urn_map: &UrnMap,
+    namespace: &str,
+    hint: Option<&str>,
+    max_inode: Option<u64>,
+) -> anyhow::Result<Generation> {
+    let canonical = urn_map.resolve(namespace);
+    if canonical != namespace {
+        tracing::info!(alias = %namespace, namespace = %canonical, "Resolved namespace alias");
+    }
+    let store = SqliteInodeStore::new(conn)?;
+    if let Some(max) = max_inode {
+        let next = peek_next_inode(conn, canonical)?;
+        if next > max {
+            anyhow::bail!(
+                "namespace '{}' would allocate inode {}, over --max-inode {}; refusing to generate",
+                canonical,
+                next,
+                max
+            );
+        }
+    }
+    Ok(generate_from(&store, canonical, hint, short_uuid)?)
+}
+
+/// Inode the next generation in `namespace` will be given (1 for a new namespace).
+pub fn peek_next_inode(conn: &Connection, namespace: &str) -> SqlResult<u64> {
+    let next = conn
+        .query_row("SELECT next_inode FROM inode_counter WHERE namespace = ?1", [namespace], |r| {
+            r.get::<_, i64>(0)
+        })
+        .optional()?;
+    Ok(next.map_or(1, |n| n as u64))
+}
+
+/// Generate a new inode-style ID, allocating the inode from `store`.
+pub fn generate_with(store: &impl InodeStore, namespace: &str, hint: Option<&str>) -> SqlResult<GenId> {
+    Ok(generate_from(store, namespace, hint, short_uuid)?.id)
+}
+
+fn short_uuid() -> String {
+    Uuid::new_v4().to_string()[..8].to_string()
+}
+
+/// Generate with uuid parts drawn from `next_uuid`. A gen_id the registry
+/// already holds is retried with a fresh uuid part under the same inode, so a
+/// collision leaves no gap in the namespace's inode sequence.
+fn generate_from(
+    store: &impl InodeStore,
+    namespace: &str,
+    hint: Option<&str>,
+    mut next_uuid: impl FnMut() -> String,
+) -> SqlResult<Generation> {
+    let inode = store.next(namespace)?;
+    let mut collisions = 0;
+    loop {
+        // Build gen_id: <namespace>-<uuid_short>[_hint]
+        let short_uuid = next_uuid();
+        let gen_id = match hint {
+            Some(h) if !h.is_empty() => format!("{}-{}-{}", namespace, short_uuid, sanitize_hint(h)),
+            _ => format!("{}-{}", namespace, short_uuid),
+        };
+
+        let urn = format!("urn:singine:{}:{}", namespace, gen_id);
+
+        let id = GenId { gen_id, urn, inode };
+        match store.record(&id, namespace, hint) {
+            Err(rusqlite::Error::SqliteFailure(e, _))
+                if e.code == ErrorCode::ConstraintViolation && collisions + 1 < MAX_ATTEMPTS =>
+            {
+                collisions += 1;
+                tracing::warn!(gen_id = %id.gen_id, collisions, "gen_id already registered, retrying");
+                continue;
+            }
+            result => result?,
+        }
+
+        tracing::debug!(gen_id = %id.gen_id, urn = %id.urn, inode = inode, "generated ID");
+
+        let created_at = store.created_at(&id.gen_id)?;
+        return Ok(Generation { id, collisions, created_at });
+    }
+}
+
+/// Hint as embedded in a gen_id: alphanumerics and `-` kept, others → `_`, max 16 chars.
+pub fn sanitize_hint(hint: &str) -> String {
+    hint.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .take(16)
+        .collect()
+}
+
+/// Registered IDs whose gen_id ends in `-<sanitized hint>`, in inode order.
+pub fn find_by_hint(conn: &Connection, hint: &str) -> SqlResult<Vec<GenId>> {
+    ensure_tables(conn)?;
+    let suffix = format!("-{}", sanitize_hint(hint));
+    let mut stmt = conn.prepare(
+        "SELECT gen_id, urn, inode FROM id_registry
+         WHERE substr(gen_id, -length(?1)) = ?1
+         ORDER BY namespace, inode",
     )?;
+    let ids = stmt
+        .query_map([&suffix], |r| {
+            Ok(GenId { gen_id: r.get(0)?, urn: r.get(1)?, inode: r.get::<_, i64>(2)? as u64 })
+        })?
+        .collect::<SqlResult<Vec<_>>>()?;
+    Ok(ids)
+}
+
+/// Canonical namespace key: trimmed, lowercased, inner whitespace runs → `_`.
+pub fn normalize_namespace(namespace: &str) -> String {
+    namespace
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Best-effort `(namespace, hint)` of an existing gen_id: the segments before
+/// the 8-hex uuid part of `<namespace>-<uuid_short>[-hint]`, or for legacy
+/// hand-written ids (`cat-main`) the prefix before the first `-`.
+pub fn parse_gen_id(gen_id: &str) -> Option<(String, Option<String>)> {
+    let parts: Vec<&str> = gen_id.split('-').collect();
+    let is_uuid = |p: &&str| p.len() == 8 && p.chars().all(|c| c.is_ascii_hexdigit());
+    let namespace_ok = |ns: &str| !ns.is_empty() && ns.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if let Some(i) = parts.iter().skip(1).position(is_uuid).map(|i| i + 1) {
+        let namespace = parts[..i].join("-");
+        let hint = (i + 1 < parts.len()).then(|| parts[i + 1..].join("-"));
+        return Some((namespace, hint));
+    }
+    match gen_id.split_once('-') {
+        Some((ns, _)) if namespace_ok(ns) => Some((ns.to_string(), None)),
+        _ => None,
+    }
+}
+
+const UNPARSED_SAMPLES: usize = 10;
 
-    // Atomically increment inode for this namespace
-    conn.execute(
-        "INSERT INTO inode_counter (namespace, next_inode) VALUES (?1, 2)
-         ON CONFLICT(namespace) DO UPDATE SET next_inode = next_inode + 1",
-        [namespace],
+/// Register gen_ids that predate the registry, from every table with a
+/// `gen_id` column. Inodes are allocated fresh per namespace in scan order.
+pub fn backfill_registry(conn: &Connection) -> SqlResult<BackfillReport> {
+    let store = SqliteInodeStore::new(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT m.name FROM sqlite_master m, pragma_table_info(m.name) c
+         WHERE m.type = 'table' AND c.name = 'gen_id' AND m.name != 'id_registry'
+         ORDER BY m.name",
     )?;
+    let tables = stmt.query_map([], |r| r.get::<_, String>(0))?.collect::<SqlResult<Vec<_>>>()?;
 
-    let inode: u64 = conn.query_row(
-        "SELECT next_inode - 1 FROM inode_counter WHERE namespace = ?1",
-        [namespace],
-        |r| r.get::<_, i64>(0),
-    )? as u64;
-
-    // Build gen_id: <namespace>-<uuid_short>[_hint]
-    let short_uuid = &Uuid::new_v4().to_string()[..8];
-    let gen_id = match hint {
-        Some(h) if !h.is_empty() => {
-            let safe: String = h
-                .chars()
-                .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
-                .take(16)
-                .collect();
-            format!("{}-{}-{}", namespace, short_uuid, safe)
-        }
-        _ => format!("{}-{}", namespace, short_uuid),
+    let mut report = BackfillReport { tables: tables.clone(), ..Default::default() };
+    let tx = conn.unchecked_transaction()?;
+    for table in &tables {
+        // table names come from sqlite_master, not user input
+        let sql = format!(
+            "SELECT gen_id, EXISTS (SELECT 1 FROM id_registry r WHERE r.gen_id = t.gen_id)
+             FROM \"{}\" t WHERE gen_id IS NOT NULL ORDER BY rowid",
+            table
+        );
+        let rows = tx
+            .prepare(&sql)?
+            .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, bool>(1)?)))?
+            .collect::<SqlResult<Vec<_>>>()?;
+        for (gen_id, known) in rows {
+            report.scanned += 1;
+            if known {
+                report.registered += 1;
+                continue;
+            }
+            let Some((namespace, hint)) = parse_gen_id(&gen_id) else {
+                report.unparsed += 1;
+                if report.unparsed_samples.len() < UNPARSED_SAMPLES {
+                    report.unparsed_samples.push(gen_id);
+                }
+                continue;
+            };
+            let id = GenId {
+                urn:   format!("urn:singine:{}:{}", namespace, gen_id),
+                inode: store.next(&namespace)?,
+                gen_id,
+            };
+            store.record(&id, &namespace, hint.as_deref())?;
+            report.backfilled += 1;
+        }
+    }
+    tx.commit()?;
+    tracing::info!(backfilled = report.backfilled, unparsed = report.unparsed, "Backfilled id registry");
+    Ok(report)
+}
+
+/// How path nodes are written in shortest-path output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PathFormat {
+    /// bare gen_ids, as stored
+    #[default]
+    Ids,
+    /// `urn:singine:<namespace>:<gen_id>`
+    Urns,
+    /// `labels.label`, falling back to the gen_id
+    Labels,
+}
+
+/// URN of `gen_id`: the registry's when recorded, otherwise rebuilt from the
+/// namespace prefix. `None` when neither is available.
+pub fn urn_for(conn: &Connection, gen_id: &str) -> SqlResult<Option<String>> {
+    let registered = conn
+        .query_row("SELECT urn FROM id_registry WHERE gen_id = ?1", [gen_id], |r| r.get(0))
+        .optional()?;
+    Ok(registered.or_else(|| {
+        parse_gen_id(gen_id).map(|(namespace, _)| format!("urn:singine:{}:{}", namespace, gen_id))
+    }))
+}
+
+/// Rewrite `path` per `format`. Nodes that cannot be resolved keep their gen_id.
+pub fn format_path(conn: &Connection, path: &[String], format: PathFormat) -> SqlResult<Vec<String>> {
+    let mut unresolved = 0;
+    let formatted = match format {
+        PathFormat::Ids => return Ok(path.to_vec()),
+        PathFormat::Urns => {
+            ensure_tables(conn)?;
+            path.iter()
+                .map(|id| {
+                    let urn = urn_for(conn, id)?;
+                    unresolved += urn.is_none() as usize;
+                    Ok(urn.unwrap_or_else(|| id.clone()))
+                })
+                .collect::<SqlResult<Vec<_>>>()?
+        }
+        PathFormat::Labels => {
+            let has_labels: bool = conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'labels')",
+                [],
+                |r| r.get(0),
+            )?;
+            if !has_labels {
+                tracing::warn!("--path-format labels: no labels table, keeping gen_ids");
+                return Ok(path.to_vec());
+            }
+            let mut stmt = conn.prepare("SELECT label FROM labels WHERE gen_id = ?1")?;
+            path.iter()
+                .map(|id| {
+                    let label = stmt.query_row([id], |r| r.get::<_, String>(0)).optional()?;
+                    unresolved += label.is_none() as usize;
+                    Ok(label.unwrap_or_else(|| id.clone()))
+                })
+                .collect::<SqlResult<Vec<_>>>()?
+        }
     };
+    if unresolved > 0 {
+        tracing::warn!(unresolved, format = ?format, "some path nodes could not be resolved, kept as gen_ids");
+    }
+    Ok(formatted)
+}
 
-    let urn = format!("urn:singine:{}:{}", namespace, gen_id);
+/// Renumber the registry rows of `namespace` to a gapless 1..n in inode
+/// order and reset its counter to n + 1, all in one transaction. Destructive:
+/// anything holding old inodes outside the registry goes stale.
+pub fn compact_inodes(conn: &Connection, namespace: &str) -> SqlResult<CompactReport> {
+    ensure_tables(conn)?;
+    let tx = conn.unchecked_transaction()?;
+    let rows = tx
+        .prepare("SELECT gen_id, inode FROM id_registry WHERE namespace = ?1 ORDER BY inode, gen_id")?
+        .query_map([namespace], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)? as u64)))?
+        .collect::<SqlResult<Vec<_>>>()?;
+    let mut renumbered = 0;
+    for (new_inode, (gen_id, inode)) in (1..).zip(&rows) {
+        if new_inode != *inode {
+            tx.execute(
+                "UPDATE id_registry SET inode = ?1 WHERE gen_id = ?2",
+                rusqlite::params![new_inode as i64, gen_id],
+            )?;
+            renumbered += 1;
+        }
+    }
+    let next_inode = rows.len() as u64 + 1;
+    tx.execute(
+        "INSERT INTO inode_counter (namespace, next_inode) VALUES (?1, ?2)
+         ON CONFLICT(namespace) DO UPDATE SET next_inode = excluded.next_inode",
+        rusqlite::params![namespace, next_inode as i64],
+    )?;
+    tx.commit()?;
+    tracing::info!(namespace = %namespace, rows = rows.len(), renumbered, "Compacted inodes");
+    Ok(CompactReport { namespace: namespace.to_string(), rows: rows.len() as u64, renumbered, next_inode })
+}
 
-    tracing::debug!(gen_id = %gen_id, urn = %urn, inode = inode, "generated ID");
+/// Check that the inodes recorded per namespace form a gapless sequence from 1.
+pub fn verify_inodes(conn: &Connection) -> SqlResult<Vec<InodeReport>> {
+    ensure_tables(conn)?;
+    let mut stmt = conn.prepare("SELECT namespace, inode FROM id_registry ORDER BY namespace, inode")?;
+    let rows = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)? as u64)))?
+        .collect::<SqlResult<Vec<_>>>()?;
 
-    Ok(GenId { gen_id, urn, inode })
+    let mut reports: Vec<InodeReport> = Vec::new();
+    for (namespace, inode) in rows {
+        if reports.last().map(|r| r.namespace != namespace).unwrap_or(true) {
+            reports.push(InodeReport {
+                namespace,
+                count: 0,
+                max_inode: 0,
+                gaps: Vec::new(),
+                duplicates: Vec::new(),
+                ok: true,
+            });
+        }
+        let rep = reports.last_mut().expect("pushed above");
+        if rep.count > 0 && inode == rep.max_inode {
+            if rep.duplicates.last() != Some(&inode) {
+                rep.duplicates.push(inode);
+            }
+        } else if inode > rep.max_inode + 1 {
+            rep.gaps.push((rep.max_inode + 1, inode - 1));
+        }
+        rep.count += 1;
+        rep.max_inode = inode;
+    }
+    for rep in &mut reports {
+        rep.ok = rep.gaps.is_empty() && rep.duplicates.is_empty();
+        if !rep.ok {
+            tracing::warn!(namespace = %rep.namespace, gaps = rep.gaps.len(),
+                           duplicates = rep.duplicates.len(), "inode sequence broken");
+        }
+    }
+    Ok(reports)
 }
 
-/// Resolve a URN back to its gen_id component.
-pub fn resolve_urn(urn: &str) -> Option<String> {
-    // urn:singine:<namespace>:<gen_id>
-    let parts: Vec<&str> = urn.splitn(4, ':').collect();
-    if parts.len() == 4 && parts[0] == "urn" && parts[1] == "singine" {
-        Some(parts[3].to_string())
-    } else {
-        None
+/// Find registry rows that share a gen_id or a urn. `id_registry` as created
+/// here keys on gen_id, but registries built by hand or by older tools may
+/// not, and nothing keeps urns unique. Under `fix`, each group keeps its
+/// lowest-inode row and the rest are deleted in one transaction; `ok`
+/// reports the registry as found.
+pub fn check_registry(conn: &Connection, fix: bool) -> SqlResult<RegistryCheck> {
+    ensure_tables(conn)?;
+    let rows = conn
+        .prepare("SELECT rowid, gen_id, urn, inode FROM id_registry ORDER BY inode, rowid")?
+        .query_map([], |r| {
+            Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?, r.get::<_, i64>(3)? as u64))
+        })?
+        .collect::<SqlResult<Vec<_>>>()?;
+
+    let mut duplicates = Vec::new();
+    let mut doomed: BTreeSet<i64> = BTreeSet::new();
+    for column in ["gen_id", "urn"] {
+        let mut by_value: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for (i, (_, gen_id, urn, _)) in rows.iter().enumerate() {
+            by_value.entry(if column == "gen_id" { gen_id } else { urn }).or_default().push(i);
+        }
+        for (value, group) in by_value.into_iter().filter(|(_, g)| g.len() > 1) {
+            doomed.extend(group[1..].iter().map(|&i| rows[i].0));
+            duplicates.push(DuplicateGroup {
+                column: column.to_string(),
+                value:  value.to_string(),
+                rows:   group.iter().map(|&i| (rows[i].1.clone(), rows[i].3)).collect(),
+            });
+        }
     }
+    let ok = duplicates.is_empty();
+    let mut removed = 0;
+    if fix && !ok {
+        let tx = conn.unchecked_transaction()?;
+        for rowid in &doomed {
+            removed += tx.execute("DELETE FROM id_registry WHERE rowid = ?1", [rowid])? as u64;
+        }
+        tx.commit()?;
+        tracing::info!(groups = duplicates.len(), removed, "Removed duplicate registry rows");
+    } else if !ok {
+        tracing::warn!(groups = duplicates.len(), "id_registry holds duplicate gen_ids or urns");
+    }
+    Ok(RegistryCheck { duplicates, removed, ok })
 }
 
 #[cfg(test)]
@@ -88,10 +611,220 @@ mod tests {
         assert!(a.urn.starts_with("urn:singine:lineage:"));
     }
 
+    struct MockStore {
+        next: std::cell::Cell<u64>,
+    }
+
+    impl InodeStore for MockStore {
+        fn next(&self, _namespace: &str) -> SqlResult<u64> {
+            let n = self.next.get();
+            self.next.set(n + 10);
+            Ok(n)
+        }
+    }
+
     #[test]
-    fn test_resolve_urn() {
-        let urn = "urn:singine:cat:cat-abc12345";
-        let id = resolve_urn(urn).unwrap();
-        assert_eq!(id, "cat-abc12345");
+    fn test_generate_with_custom_store() {
+        let store = MockStore { next: std::cell::Cell::new(500) };
+        let a = generate_with(&store, "entity", None).unwrap();
+        let b = generate_with(&store, "entity", Some("x")).unwrap();
+        assert_eq!(a.inode, 500);
+        assert_eq!(b.inode, 510);
+        assert!(b.gen_id.starts_with("entity-") && b.gen_id.ends_with("-x"));
+    }
+
+    #[test]
+    fn test_verify_inodes_reports_gap() {
+        let conn = Connection::open_in_memory().unwrap();
+        for _ in 0..4 {
+            generate(&conn, "lineage", None).unwrap();
+        }
+        generate(&conn, "cat", None).unwrap();
+        conn.execute("DELETE FROM id_registry WHERE namespace='lineage' AND inode=2", [])
+            .unwrap();
+        let reports = verify_inodes(&conn).unwrap();
+        let cat = reports.iter().find(|r| r.namespace == "cat").unwrap();
+        assert!(cat.ok);
+        let lin = reports.iter().find(|r| r.namespace == "lineage").unwrap();
+        assert!(!lin.ok);
+        assert_eq!(lin.gaps, vec![(2, 2)]);
+        assert_eq!(lin.count, 3);
+        assert_eq!(lin.max_inode, 4);
+    }
+
+    #[test]
+    fn test_normalized_namespaces_share_counter() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(normalize_namespace("  Data Product "), "data_product");
+        let a = generate(&conn, &normalize_namespace("Lineage"), None).unwrap();
+        let b = generate(&conn, &normalize_namespace(" lineage"), None).unwrap();
+        assert_eq!(a.inode + 1, b.inode);
+        assert!(b.urn.starts_with("urn:singine:lineage:lineage-"));
+    }
+
+    #[test]
+    fn test_find_by_hint_matches_sanitized_suffix() {
+        let conn = Connection::open_in_memory().unwrap();
+        let a = generate(&conn, "entity", Some("Customer Table")).unwrap();
+        generate(&conn, "entity", Some("other")).unwrap();
+        generate(&conn, "entity", None).unwrap();
+        let found = find_by_hint(&conn, "Customer Table").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].gen_id, a.gen_id);
+        assert!(a.gen_id.ends_with("-Customer_Table"));
+        assert!(find_by_hint(&conn, "Customer").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_backfill_registers_legacy_ids() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE path_results (gen_id TEXT PRIMARY KEY, src_id TEXT);
+             CREATE TABLE categories (gen_id TEXT PRIMARY KEY, name TEXT);
+             INSERT INTO path_results VALUES ('path-1a2b3c4d', 'A'), ('path-deadbeef-x', 'B');
+             INSERT INTO categories VALUES ('cat-main', 'Main'), ('legacy', 'No namespace');",
+        )
+        .unwrap();
+        let existing = generate(&conn, "cat", None).unwrap();
+        conn.execute("INSERT INTO categories VALUES (?1, 'New')", [&existing.gen_id]).unwrap();
+
+        let report = backfill_registry(&conn).unwrap();
+        assert_eq!(report.tables, vec!["categories", "path_results"]);
+        assert_eq!(report.scanned, 5);
+        assert_eq!(report.backfilled, 3);
+        assert_eq!(report.registered, 1);
+        assert_eq!(report.unparsed_samples, vec!["legacy"]);
+
+        let (ns, hint): (String, Option<String>) = conn
+            .query_row("SELECT namespace, hint FROM id_registry WHERE gen_id='path-deadbeef-x'", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((ns.as_str(), hint.as_deref()), ("path", Some("x")));
+        assert!(verify_inodes(&conn).unwrap().iter().all(|r| r.ok));
+        assert_eq!(backfill_registry(&conn).unwrap().backfilled, 0);
+    }
+
+    #[test]
+    fn test_collision_is_retried_and_reported() {
+        let conn = Connection::open_in_memory().unwrap();
+        ensure_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO id_registry (gen_id, urn, namespace, inode) VALUES ('cat-aaaaaaaa','u','cat',99)",
+            [],
+        )
+        .unwrap();
+        let mut uuids = ["aaaaaaaa", "bbbbbbbb"].into_iter().map(String::from);
+        let store = SqliteInodeStore::new(&conn).unwrap();
+        let g = generate_from(&store, "cat", None, || uuids.next().unwrap()).unwrap();
+        assert_eq!(g.id.gen_id, "cat-bbbbbbbb");
+        assert_eq!(g.id.inode, 1);
+
+        let out = serde_json::to_value(&g).unwrap();
+        assert!(out["collisions"].as_u64().unwrap() >= 1);
+        assert_eq!(out["gen_id"], "cat-bbbbbbbb");
+        assert!(out["created_at"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_urn_path_format_covers_every_hop() {
+        let conn = Connection::open_in_memory().unwrap();
+        let registered = generate(&conn, "entity", None).unwrap();
+        let path = vec![registered.gen_id.clone(), "lineage-0a1b2c3d".to_string(), "cat-main".to_string()];
+        let urns = format_path(&conn, &path, PathFormat::Urns).unwrap();
+        assert_eq!(urns[0], registered.urn);
+        assert_eq!(urns[1], "urn:singine:lineage:lineage-0a1b2c3d");
+        assert_eq!(urns[2], "urn:singine:cat:cat-main");
+        for (urn, id) in urns.iter().zip(&path) {
+            assert!(urn.starts_with("urn:singine:"), "{}", urn);
+            assert!(urn.ends_with(&format!(":{}", id)), "{}", urn);
+        }
+        assert_eq!(format_path(&conn, &path, PathFormat::Ids).unwrap(), path);
+    }
+
+    #[test]
+    fn test_check_registry_finds_and_fixes_duplicate_urn() {
+        let conn = Connection::open_in_memory().unwrap();
+        let a = generate(&conn, "lineage", None).unwrap();
+        generate(&conn, "lineage", None).unwrap();
+        assert!(check_registry(&conn, false).unwrap().ok);
+
+        // a hand-edited row reusing a's urn under a later inode
+        conn.execute(
+            "INSERT INTO id_registry (gen_id, urn, namespace, inode) VALUES ('lineage-copy', ?1, 'lineage', 9)",
+            [&a.urn],
+        )
+        .unwrap();
+        let found = check_registry(&conn, false).unwrap();
+        assert!(!found.ok);
+        assert_eq!(found.duplicates.len(), 1);
+        assert_eq!(found.duplicates[0].column, "urn");
+        assert_eq!(found.duplicates[0].rows, vec![(a.gen_id.clone(), a.inode), ("lineage-copy".to_string(), 9)]);
+        assert_eq!(found.removed, 0);
+
+        let fixed = check_registry(&conn, true).unwrap();
+        assert_eq!(fixed.removed, 1);
+        let owner: String =
+            conn.query_row("SELECT gen_id FROM id_registry WHERE urn = ?1", [&a.urn], |r| r.get(0)).unwrap();
+        assert_eq!(owner, a.gen_id, "lowest inode kept");
+        assert!(check_registry(&conn, false).unwrap().ok);
+    }
+
+    #[test]
+    fn test_compact_closes_deleted_inode_gap() {
+        let conn = Connection::open_in_memory().unwrap();
+        let ids: Vec<GenId> = (0..4).map(|_| generate(&conn, "lineage", None).unwrap()).collect();
+        generate(&conn, "cat", None).unwrap();
+        conn.execute("DELETE FROM id_registry WHERE gen_id = ?1", [&ids[1].gen_id]).unwrap();
+        assert!(!verify_inodes(&conn).unwrap()[1].ok);
+
+        let report = compact_inodes(&conn, "lineage").unwrap();
+        assert_eq!((report.rows, report.renumbered, report.next_inode), (3, 2, 4));
+        let reports = verify_inodes(&conn).unwrap();
+        assert!(reports.iter().all(|r| r.ok), "{:?}", reports);
+        let inode_of = |id: &GenId| -> i64 {
+            conn.query_row("SELECT inode FROM id_registry WHERE gen_id = ?1", [&id.gen_id], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!([inode_of(&ids[0]), inode_of(&ids[2]), inode_of(&ids[3])], [1, 2, 3]);
+        assert_eq!(generate(&conn, "lineage", None).unwrap().inode, 4);
+    }
+
+    #[test]
+    fn test_urn_map_alias_resolves_to_canonical_namespace() {
+        let map = UrnMap::parse(
+            r#"{"prefixes": {"lin": "urn:singine:lin:", "foaf": "http://xmlns.com/foaf/0.1/"},
+                "aliases": {"ent": "entity"}}"#,
+        )
+        .unwrap();
+        assert_eq!(map.resolve("lin"), "lin");
+        assert_eq!(map.resolve("foaf"), "foaf");
+        let conn = Connection::open_in_memory().unwrap();
+        let a = generate_tracked(&conn, &map, "ent", None, None).unwrap();
+        let b = generate_tracked(&conn, &map, "entity", None, None).unwrap();
+        assert!(a.id.gen_id.starts_with("entity-"), "{}", a.id.gen_id);
+        assert!(a.id.urn.starts_with("urn:singine:entity:"));
+        assert_eq!((a.id.inode, b.id.inode), (1, 2), "alias shares the canonical counter");
+
+        let missing = std::env::temp_dir().join("no-such-urn-map.json");
+        assert_eq!(UrnMap::load(&missing, false).unwrap().resolve("ent"), "ent");
+        assert!(UrnMap::load(&missing, true).is_err());
+    }
+
+    #[test]
+    fn test_max_inode_ceiling_stops_generation() {
+        let conn = Connection::open_in_memory().unwrap();
+        ensure_tables(&conn).unwrap();
+        conn.execute("INSERT INTO inode_counter (namespace, next_inode) VALUES ('edge', 99)", [])
+            .unwrap();
+        let map = UrnMap::default();
+        let last = generate_tracked(&conn, &map, "edge", None, Some(100)).unwrap();
+        assert_eq!(last.id.inode, 99);
+        assert_eq!(generate_tracked(&conn, &map, "edge", None, Some(100)).unwrap().id.inode, 100);
+
+        let err = generate_tracked(&conn, &map, "edge", None, Some(100)).unwrap_err().to_string();
+        assert!(err.contains("inode 101") && err.contains("--max-inode 100"), "{}", err);
+        assert_eq!(peek_next_inode(&conn, "edge").unwrap(), 101, "a refused generation allocates nothing");
+        assert_eq!(generate_tracked(&conn, &map, "other", None, Some(100)).unwrap().id.inode, 1);
     }
 }
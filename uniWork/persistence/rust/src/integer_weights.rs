@@ -0,0 +1,129 @@
+//! integer_weights.rs — Singine fixed-point Dijkstra
+//!
+//! `--integer-weights S` scales every hop weight by S, rounds it to an i64
+//! and runs the search in integer arithmetic, so path costs are sums of
+//! integers and identical on every platform and optimisation level. The
+//! reported total is the integer cost divided by S; it differs from the float
+//! search by at most half a unit of 1/S per hop.
+//!
+//! `check` refuses a scale at which the heaviest hop, taken once per node,
+//! would not fit in an i64, so no path cost can overflow during the search.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::shortest_path::{self, Adjacency, PathResult, SearchTooLarge};
+
+/// `w` in units of 1/`scale`, rounded half away from zero.
+pub fn scaled(w: f64, scale: u64) -> i64 {
+    (w * scale as f64).round() as i64
+}
+
+/// Reject `scale` when a path cost in units of 1/`scale` could overflow.
+pub fn check(adj: &Adjacency, scale: Option<u64>) -> anyhow::Result<()> {
+    let Some(scale) = scale else { return Ok(()) };
+    if scale == 0 {
+        anyhow::bail!("--integer-weights needs a scale of at least 1");
+    }
+    let heaviest = shortest_path::arcs_once(adj).into_iter().map(|(_, _, w)| w.abs()).fold(0.0, f64::max);
+    let hops = shortest_path::all_nodes(adj).len().saturating_sub(1).max(1);
+    if heaviest * scale as f64 * hops as f64 >= i64::MAX as f64 {
+        anyhow::bail!(
+            "--integer-weights {} overflows i64: the heaviest hop ({}) over {} hops exceeds {}; use a smaller scale",
+            scale,
+            heaviest,
+            hops,
+            i64::MAX
+        );
+    }
+    Ok(())
+}
+
+pub fn dijkstra(
+    adj: &Adjacency,
+    src: &str,
+    dst: &str,
+    scale: u64,
+    max_open: Option<usize>,
+) -> Result<Option<PathResult>, SearchTooLarge> {
+    let mut index: HashMap<&str, usize> = HashMap::from([(src, 0)]);
+    let mut names: Vec<&str> = vec![src];
+    let mut dist: Vec<Option<i64>> = vec![Some(0)];
+    let mut parent: Vec<Option<usize>> = vec![None];
+    // ties on cost break on discovery order, which keeps the result stable
+    let mut heap = BinaryHeap::from([Reverse((0i64, 0usize))]);
+
+    while let Some(Reverse((cost, u))) = heap.pop() {
+        if dist[u].is_some_and(|best| cost > best) {
+            continue;
+        }
+        if names[u] == dst {
+            let mut path = vec![dst.to_string()];
+            let mut at = u;
+            while let Some(p) = parent[at] {
+                path.push(names[p].to_string());
+                at = p;
+            }
+            path.reverse();
+            return Ok(Some(PathResult {
+                src_id:         src.to_string(),
+                dst_id:         dst.to_string(),
+                path,
+                total_weight:   cost as f64 / scale as f64,
+                algorithm:      "dijkstra+integer".to_string(),
+                weight_by_type: HashMap::new(),
+            }));
+        }
+        for (next, w) in adj.get(names[u]).into_iter().flatten() {
+            let v = *index.entry(next.as_str()).or_insert_with(|| {
+                names.push(next.as_str());
+                dist.push(None);
+                parent.push(None);
+                names.len() - 1
+            });
+            let next_cost = cost + scaled(*w, scale);
+            if dist[v].is_some_and(|best| next_cost >= best) {
+                continue;
+            }
+            dist[v] = Some(next_cost);
+            parent[v] = Some(u);
+            heap.push(Reverse((next_cost, v)));
+            if let Some(limit) = max_open.filter(|&limit| heap.len() > limit) {
+                return Err(SearchTooLarge { limit });
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{self, build_adjacency, edge};
+
+    #[test]
+    fn test_integer_path_matches_float_within_scale() {
+        // 0.1 + 0.2 ≠ 0.3 in floats; in thousandths it is exactly 300
+        let adj = build_adjacency(&[
+            edge("A", "B", 0.1),
+            edge("B", "C", 0.2),
+            edge("A", "C", 0.35),
+            edge("C", "D", 1.0 / 3.0),
+        ]);
+        let float = shortest_path::dijkstra(&adj, "A", "D").unwrap();
+        let int = dijkstra(&adj, "A", "D", 1000, None).unwrap().unwrap();
+        assert_eq!(int.path, float.path);
+        assert_eq!(int.path, vec!["A", "B", "C", "D"]);
+        let hops = (int.path.len() - 1) as f64;
+        assert!((int.total_weight - float.total_weight).abs() <= hops * 0.5 / 1000.0);
+        assert_eq!(int.total_weight, 0.633);
+        assert!(dijkstra(&adj, "A", "Z", 1000, None).unwrap().is_none());
+
+        assert!(check(&adj, Some(1000)).is_ok());
+        assert!(check(&adj, Some(0)).is_err());
+        let heavy = build_adjacency(&[edge("A", "B", 1e12), edge("B", "C", 1e12)]);
+        assert!(check(&heavy, Some(1_000_000)).is_ok());
+        let err = check(&heavy, Some(10_000_000)).unwrap_err().to_string();
+        assert!(err.contains("overflows i64"), "{}", err);
+    }
+}
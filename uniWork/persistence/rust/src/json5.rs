@@ -0,0 +1,88 @@
+//! json5.rs — Singine lenient JSON input (feature `json5`)
+//!
+//! Hand-written edge files use two JSON5 conveniences: `//` and `/* */`
+//! comments, and trailing commas before `}` / `]`. `to_strict` strips both
+//! outside string literals, leaving text `serde_json` accepts. Other JSON5
+//! extensions (unquoted keys, single quotes, hex numbers) are not supported.
+
+/// Strip comments and trailing commas from `text`.
+pub fn to_strict(text: &str) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(s) = chars.next() {
+                    out.push(s);
+                    match s {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&n| n != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let start = line;
+                let mut prev = '\0';
+                loop {
+                    match chars.next() {
+                        Some('/') if prev == '*' => break,
+                        Some(n) => {
+                            line += (n == '\n') as usize;
+                            prev = n;
+                        }
+                        None => anyhow::bail!("unterminated /* comment starting on line {}", start),
+                    }
+                }
+                out.push(' ');
+            }
+            '}' | ']' => {
+                let kept = out.trim_end().len();
+                if out[..kept].ends_with(',') {
+                    out.remove(kept - 1);
+                }
+                out.push(c);
+            }
+            _ => {
+                line += (c == '\n') as usize;
+                out.push(c);
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge_io::load_json_adjacency;
+    use crate::shortest_path::{dijkstra, GraphOptions};
+
+    #[test]
+    fn test_json5_edge_file_with_comments() {
+        let text = r#"{
+            // lineage hops
+            "A": {"B": 1.0, "C": 10.0,},   /* direct but expensive */
+            "B": {"C": 2.0,},
+        }"#;
+        let path = std::env::temp_dir().join(format!("adj-{}.json5", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let opts = GraphOptions { lenient_json: true, ..Default::default() };
+        let adj = load_json_adjacency(&path, &opts).unwrap();
+        assert!(load_json_adjacency(&path, &GraphOptions::default()).is_err(), "strict by default");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(adj["A"], vec![("B".to_string(), 1.0), ("C".to_string(), 10.0)]);
+        assert_eq!(dijkstra(&adj, "A", "C").unwrap().total_weight, 3.0);
+
+        assert_eq!(to_strict(r#"{"url": "http://x//y/*z*/",}"#).unwrap(), r#"{"url": "http://x//y/*z*/"}"#);
+        assert!(to_strict("{ /* open").unwrap_err().to_string().contains("line 1"));
+    }
+}
@@ -1,21 +1,90 @@
 //! main.rs — Singine persistence engine entry point
-//! Modes: shortest-path | gen-id | migrate-check | status
+//! Modes: shortest-path | count-paths | compare | batch | neighbors | bands | mst | bridges | articulation | components | ancestors | descendants | diameter | pairwise | centroid | connectivity-sample | sample-edges | random-walk | repl | validate-graph | edge-types | nodes | import-edges | export-edges | export-parquet | snapshot | seed | build-adjacency | list-paths | report-paths | gen-id | find-hint | verify-inodes | compact-inodes | backfill-registry | migrate-check | status
 //!
 //! Called by GitHub Actions Phase 4 and by the top-level Makefile.
 
+mod algorithms;
+mod analysis_runs;
+mod batch;
+mod cache;
+mod components;
+mod critical;
+mod cycles;
+mod diameter;
+mod edge_io;
+mod graph_export;
+mod hierarchy;
 mod id_gen;
+mod integer_weights;
+#[cfg(feature = "json5")]
+mod json5;
+mod mst;
+mod neighbors;
+mod output;
+#[cfg(feature = "pairing-heap")]
+mod pairing_heap;
+mod pairwise;
+mod path_score;
+#[cfg(feature = "export-parquet")]
+mod parquet_export;
+mod path_results;
+mod repl;
+mod rng;
+mod sampling;
+mod seed;
 mod shortest_path;
+mod validate;
+#[cfg(feature = "emit-http")]
+mod webhook;
 
 use clap::{Parser, ValueEnum};
 use rusqlite::Connection;
-use serde_json::json;
-use std::path::PathBuf;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use tracing_subscriber::{fmt, EnvFilter};
 
 #[derive(Debug, Clone, ValueEnum)]
 enum Mode {
     ShortestPath,
+    CountPaths,
+    Compare,
+    Batch,
+    Neighbors,
+    Bands,
+    Mst,
+    Bridges,
+    Articulation,
+    Components,
+    Ancestors,
+    Descendants,
+    Diameter,
+    Pairwise,
+    Centroid,
+    ConnectivitySample,
+    SampleEdges,
+    RandomWalk,
+    Repl,
+    ValidateGraph,
+    EdgeTypes,
+    Nodes,
+    ImportEdges,
+    ExportEdges,
+    ExportParquet,
+    ExportNetworkx,
+    ExportCypher,
+    ShardEdges,
+    Snapshot,
+    Seed,
+    BuildAdjacency,
+    ListPaths,
+    ReportPaths,
     GenId,
+    FindHint,
+    VerifyInodes,
+    CheckRegistry,
+    CompactInodes,
+    BackfillRegistry,
     MigrateCheck,
     Status,
 }
@@ -23,9 +92,18 @@ enum Mode {
 #[derive(Parser, Debug)]
 #[command(name = "persistence", about = "Singine persistence engine")]
 struct Args {
-    #[arg(long, default_value = "singine.db")]
+    /// SQLite database; falls back to $SINGINE_DB when the flag is omitted
+    #[arg(long, env = "SINGINE_DB", default_value = "singine.db")]
     db: PathBuf,
 
+    /// log every SQL statement run against the database, with its bound parameters
+    #[arg(long)]
+    explain_sql: bool,
+
+    /// SQLite pragma applied on open, before any query (repeatable; e.g. journal_mode=WAL, cache_size=-64000)
+    #[arg(long, value_name = "KEY=VALUE")]
+    pragma: Vec<String>,
+
     #[arg(long, value_enum, default_value = "status")]
     mode: Mode,
 
@@ -37,120 +115,1677 @@ struct Args {
     #[arg(long)]
     dst: Option<String>,
 
+    /// shortest-path: file of candidate sources, one id per line; the path
+    /// starts at whichever is cheapest (replaces --src)
+    #[arg(long)]
+    src_set: Option<PathBuf>,
+
+    /// shortest-path: file of candidate destinations (replaces --dst)
+    #[arg(long)]
+    dst_set: Option<PathBuf>,
+
     /// edge type filter (similarity | lineage | category | ldap_parent)
     #[arg(long)]
     edge_type: Option<String>,
 
-    /// namespace for gen-id mode
+    /// with --edge-type, also match edges whose edge_type is NULL (legacy rows)
+    #[arg(long)]
+    include_untyped: bool,
+
+    /// build the graph from a nested `{node: {neighbour: weight}}` JSON file instead of the database
+    #[arg(long)]
+    edges_json_adjacency: Option<PathBuf>,
+
+    /// build the graph from a `--mode snapshot` file instead of the database
+    #[arg(long, conflicts_with = "edges_json_adjacency")]
+    edges_json: Option<PathBuf>,
+
+    /// accept comments and trailing commas in --edges-json / --edges-json-adjacency (needs feature json5)
+    #[arg(long)]
+    json5: bool,
+
+    /// add each entered node's cost from the node_weights table to the hop weight
+    #[arg(long)]
+    use_node_weights: bool,
+
+    /// treat edges as src → dst only, unless a row's own `directed` column says otherwise
+    #[arg(long)]
+    directed: bool,
+
+    /// leave the edge with this gen_id out of the graph (repeatable), to see how routes change
+    #[arg(long, value_name = "GEN_ID")]
+    exclude_edge: Vec<String>,
+
+    /// drop repeated (src, dst, edge_type) rows on load, keeping the first or the lightest
+    #[arg(long, value_enum)]
+    dedup_edges_on_load: Option<shortest_path::EdgeDedup>,
+
+    /// abort unless the latest schema_migrations version is N (e.g. 3 or V003)
+    #[arg(long, value_name = "N")]
+    require_schema_version: Option<String>,
+
+    /// how path nodes are written: bare gen_ids, full URNs, or labels from the labels table
+    #[arg(long, value_enum, default_value_t = id_gen::PathFormat::Ids)]
+    path_format: id_gen::PathFormat,
+
+    /// shortest-path: round reported weights to D decimal places (the search is unaffected)
+    #[arg(long, value_name = "D")]
+    weight_precision: Option<u32>,
+
+    /// shard-edges: directory receiving one SQLite file per edge_type
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+
+    /// export-cypher: MERGE (idempotent) or CREATE (faster into an empty database)
+    #[arg(long, value_enum, default_value = "merge")]
+    cypher_verb: graph_export::CypherVerb,
+
+    /// shortest-path: read weights as seconds and also report total_duration (e.g. 1h23m)
+    #[arg(long, value_enum, default_value = "number")]
+    weight_as: output::WeightAs,
+
+    /// shortest-path: route through this node, joining shortest(src → N) and shortest(N → dst)
+    #[arg(long, value_name = "N")]
+    via: Option<String>,
+
+    /// shortest-path/batch: search algorithm; widest maximises the path's lightest edge
+    #[arg(long, value_enum, default_value = "dijkstra")]
+    algorithm: algorithms::Algorithm,
+
+    /// shortest-path/batch: how hop weights combine into the minimised score (max = bottleneck)
+    #[arg(long, value_enum, default_value = "sum")]
+    path_score: path_score::PathScore,
+
+    /// shortest-path/batch: cheapest path with at least N hops, which may not be the shortest overall
+    #[arg(long, value_name = "N")]
+    min_hops: Option<usize>,
+
+    /// shortest-path: also list each neighbour of --src with the best total cost routed through it
+    #[arg(long)]
+    with_alternatives: bool,
+
+    /// priority queue used by Dijkstra; `pairing` needs feature pairing-heap
+    #[arg(long, value_enum, default_value_t = shortest_path::HeapKind::Binary)]
+    heap: shortest_path::HeapKind,
+
+    /// search in integer units of 1/S (weights scaled and rounded) for bit-reproducible costs; overrides --heap
+    #[arg(long, value_name = "S", value_parser = clap::value_parser!(u64).range(1..))]
+    integer_weights: Option<u64>,
+
+    /// abort with SearchTooLarge once the Dijkstra heap holds more than N entries (default: unlimited)
+    #[arg(long, value_name = "N")]
+    max_open_states: Option<usize>,
+
+    /// fail instead of warning when Dijkstra would run over negative edge weights
+    #[arg(long)]
+    strict: bool,
+
+    /// report per-phase timings (load, sort, build, search, persist) under `timings`, in ms
+    #[arg(long)]
+    profile: bool,
+
+    /// ignore individual edges heavier than W
+    #[arg(long)]
+    weight_ceiling: Option<f64>,
+
+    /// never take a single hop costing more than W, even if it would shorten the total
+    #[arg(long, value_name = "W")]
+    max_hop_weight: Option<f64>,
+
+    /// expected distinct node count, used to pre-size the adjacency map
+    #[arg(long)]
+    node_hint: Option<usize>,
+
+    /// how parallel edges between the same node pair combine
+    #[arg(long, value_enum, default_value = "min")]
+    parallel_edge_policy: shortest_path::ParallelEdgePolicy,
+
+    /// report undirected paths with the lexicographically smaller endpoint first (not with --directed)
+    #[arg(long)]
+    canonical_path: bool,
+
+    /// batch: abort with a non-zero exit on the first pair that has no path
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// shortest-path/batch: delete the --run-id's earlier path_results before persisting
+    #[arg(long)]
+    replace_run: bool,
+
+    /// batch: store at most N paths in path_results per run; the rest are still computed and reported
+    #[arg(long, value_name = "N")]
+    max_persist: Option<usize>,
+
+    /// store paths longer than N nodes in path_results as first/last nodes plus an omitted count
+    #[arg(long)]
+    max_path_store: Option<usize>,
+
+    /// warn when the loaded graph has more than one connected component
+    #[arg(long)]
+    warn_components: bool,
+
+    /// shortest-path: on no path, report the component sizes of src and dst
+    #[arg(long)]
+    diagnose: bool,
+
+    /// shortest-path: reuse stored results for identical queries on an unchanged graph
+    #[arg(long)]
+    cache: bool,
+
+    /// exclude nodes with more than D distinct neighbours from traversal
+    #[arg(long)]
+    max_degree: Option<usize>,
+
+    /// traverse only nodes whose id starts with P (endpoints must match)
+    #[arg(long, value_name = "P")]
+    node_prefix: Option<String>,
+
+    /// keep only each node's K lightest neighbours (faster on dense graphs; results marked approximate)
+    #[arg(long, value_name = "K")]
+    limit_neighbors: Option<usize>,
+
+    /// column of similarity_edges to read as the edge weight
+    #[arg(long)]
+    weight_column: Option<String>,
+
+    /// only use edges valid at this RFC 3339 instant (needs valid_from/valid_to columns)
+    #[arg(long)]
+    as_of: Option<String>,
+
+    /// abort if the edge table holds more than N rows (default: unlimited)
+    #[arg(long)]
+    edge_limit: Option<u64>,
+
+    /// load edges even when --edge-limit is exceeded
+    #[arg(long)]
+    force: bool,
+
+    /// pairs file, one `src dst` per line (for batch mode)
+    #[arg(long)]
+    pairs: Option<PathBuf>,
+
+    /// batch: write partial results to `<output>.partial` every N computed pairs
+    #[arg(long)]
+    checkpoint_every: Option<usize>,
+
+    /// batch: skip pairs already saved in `<output>.partial` by an interrupted run
+    #[arg(long)]
+    resume: bool,
+
+    /// batch: search pairs on N threads over the shared graph (persistence stays serial)
+    #[arg(long, default_value_t = 1)]
+    jobs: usize,
+
+    /// collapse identical node sequences in batch output, with a count per path
+    #[arg(long)]
+    dedup_paths: bool,
+
+    /// expansion depth in hops (for neighbors mode)
+    #[arg(long, default_value_t = 1)]
+    depth: usize,
+
+    /// cap on nodes returned by graph-expansion modes (and on graph size for diameter mode)
+    #[arg(long, default_value_t = 1000)]
+    max_nodes: usize,
+
+    /// distance band width (for bands mode)
+    #[arg(long, default_value_t = 1.0)]
+    band_width: f64,
+
+    /// node list file, one id per line (for pairwise mode)
+    #[arg(long)]
+    nodes: Option<PathBuf>,
+
+    /// seed: number of nodes in the generated graph
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    node_count: usize,
+
+    /// seed: number of edges in the generated graph (at least --node-count - 1)
+    #[arg(long, value_name = "M", default_value_t = 40)]
+    edge_count: usize,
+
+    /// number of random samples (for sampling modes)
+    #[arg(long, default_value_t = 100)]
+    samples: usize,
+
+    /// hops per walk (for random-walk mode)
+    #[arg(long, default_value_t = 10)]
+    steps: usize,
+
+    /// number of walks (for random-walk mode)
+    #[arg(long, default_value_t = 10)]
+    walks: usize,
+
+    /// random-walk transition bias
+    #[arg(long, value_enum, default_value = "inverse")]
+    walk_bias: sampling::WalkBias,
+
+    /// confidence level of the intervals reported by sampling modes, in (0, 1)
+    #[arg(long, default_value_t = 0.95, value_parser = parse_confidence)]
+    confidence: f64,
+
+    /// RNG seed for sampling modes
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+
+    /// input file (CSV for import-edges)
+    #[arg(long)]
+    input: Option<PathBuf>,
+
+    /// import-edges: roll back if the imported edge types now contain a directed cycle
+    #[arg(long)]
+    fail_on_cycle: bool,
+
+    /// only stored paths computed at or after this RFC 3339 time (list-paths, report-paths)
+    #[arg(long)]
+    since: Option<String>,
+
+    /// namespace for gen-id and compact-inodes modes
     #[arg(long, default_value = "entity")]
     namespace: String,
 
-    /// optional hint for gen-id mode
+    /// compact-inodes: actually renumber (the operation is destructive)
+    #[arg(long)]
+    confirm: bool,
+
+    /// check-registry: delete duplicate rows, keeping the lowest inode of each group
+    #[arg(long)]
+    fix: bool,
+
+    /// gen-id: URN map resolving namespace aliases [default: schema/urn_map.json, skipped if absent]
+    #[arg(long)]
+    urn_map_file: Option<PathBuf>,
+
+    /// gen-id: lowercase/trim the namespace and replace spaces with `_`
+    #[arg(long)]
+    normalize_namespace: bool,
+
+    /// gen-id: fail instead of allocating an inode above N in the namespace (default: unlimited)
+    #[arg(long, value_name = "N")]
+    max_inode: Option<u64>,
+
+    /// optional hint for gen-id mode; the hint to look up in find-hint mode
     #[arg(long)]
     hint: Option<String>,
 
-    /// output JSON file path
+    /// migrate-check: create an empty schema_migrations table if it is missing
+    #[arg(long)]
+    init: bool,
+
+    /// output file path (JSON; CSV for export-edges)
     #[arg(long, default_value = "path-report.json")]
     output: PathBuf,
 
+    /// rename top-level result keys, e.g. src=source,dst=target (each key must exist)
+    #[arg(long, value_delimiter = ',', value_parser = output::parse_field_rename)]
+    field_map: Vec<(String, String)>,
+
+    /// make --output absolute, create its parent directories and report it as output_path
+    #[arg(long)]
+    normalize_output_paths: bool,
+
+    /// nodes: return at most N node ids
+    #[arg(long, value_name = "N")]
+    limit: Option<u64>,
+
+    /// nodes: skip the first N node ids (in sorted order)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    offset: u64,
+
+    /// gzip-compress the --output file (implied when it ends in .gz)
+    #[arg(long)]
+    gzip: bool,
+
+    /// shortest-path: shape the result with a JSON template using {{src}}, {{dst}},
+    /// {{total_weight}}, {{hops}} and {{path}} placeholders
+    #[arg(long)]
+    template: Option<PathBuf>,
+
+    /// print only the value at this RFC 6901 JSON pointer (e.g. /total_weight)
+    #[arg(long)]
+    extract: Option<String>,
+
+    /// POST the result JSON to this URL after persisting (needs feature emit-http)
+    #[arg(long)]
+    emit_http: Option<String>,
+
     /// run_id from pipeline_runs (for tracing)
     #[arg(long)]
     run_id: Option<String>,
+
+    /// do not record this invocation in analysis_runs
+    #[arg(long)]
+    no_audit: bool,
+}
+
+/// Edge-set shaping options shared by all graph modes.
+fn graph_options(args: &Args) -> anyhow::Result<shortest_path::GraphOptions> {
+    let as_of = args
+        .as_of
+        .as_deref()
+        .map(|ts| path_results::parse_timestamp("--as-of", ts))
+        .transpose()?;
+    Ok(shortest_path::GraphOptions {
+        edge_type:      args.edge_type.clone(),
+        weight_ceiling: args.weight_ceiling,
+        max_hop_weight: args.max_hop_weight,
+        node_hint:      args.node_hint,
+        parallel_policy: args.parallel_edge_policy,
+        as_of,
+        weight_column:  args.weight_column.clone(),
+        max_degree:     args.max_degree,
+        include_untyped: args.include_untyped,
+        use_node_weights: args.use_node_weights,
+        dedup_edges:    args.dedup_edges_on_load,
+        directed:       args.directed,
+        exclude_edges:  args.exclude_edge.clone(),
+        lenient_json:   args.json5,
+        limit_neighbors: args.limit_neighbors,
+        warn_components: args.warn_components,
+        node_prefix:    args.node_prefix.clone(),
+    })
+}
+
+/// Query-time options for path searches.
+fn search_options(args: &Args) -> shortest_path::SearchOptions {
+    shortest_path::SearchOptions {
+        canonical:      args.canonical_path,
+        max_path_store: args.max_path_store,
+        strict:         args.strict,
+        heap:           args.heap,
+        via:            args.via.clone(),
+        min_hops:       args.min_hops,
+        path_score:     args.path_score,
+        algorithm:      args.algorithm,
+        max_open_states: args.max_open_states,
+        integer_weights: args.integer_weights,
+        max_persist:    args.max_persist,
+        fail_fast:      args.fail_fast,
+        replace_run:    args.replace_run,
+    }
+}
+
+/// Enforce --edge-limit, then load the adjacency map per the graph options
+/// (or from --edges-json-adjacency when given).
+fn load_checked(conn: &Connection, args: &Args) -> anyhow::Result<shortest_path::Adjacency> {
+    let file = match (args.edges_json_adjacency.as_deref(), args.edges_json.as_deref()) {
+        (Some(path), _) => Some(edge_io::load_json_adjacency(path, &graph_options(args)?)?),
+        (None, Some(path)) => Some(edge_io::load_snapshot(path, &graph_options(args)?)?),
+        (None, None) => None,
+    };
+    if let Some(adj) = file {
+        if args.warn_components {
+            components::warn_if_disconnected(&adj);
+        }
+        return Ok(adj);
+    }
+    shortest_path::enforce_edge_limit(conn, args.edge_type.as_deref(), args.edge_limit, args.force)?;
+    Ok(shortest_path::load_graph(conn, &graph_options(args)?)?)
+}
+
+/// `load_checked`, keeping the edge type behind each hop so found paths can
+/// report `weight_by_type`.
+fn load_checked_graph(conn: &Connection, args: &Args) -> anyhow::Result<shortest_path::Graph> {
+    let opts = graph_options(args)?;
+    if !file_graph(args) {
+        shortest_path::enforce_edge_limit(conn, args.edge_type.as_deref(), args.edge_limit, args.force)?;
+        return Ok(shortest_path::load_typed_graph(conn, &opts)?);
+    }
+    let graph = shortest_path::graph_from_rows(load_rows_with(conn, args, &opts)?, &opts);
+    if args.warn_components {
+        components::warn_if_disconnected(&graph.adj);
+    }
+    Ok(graph)
+}
+
+/// Edge rows for the modes that work on rows rather than the built graph:
+/// the graph file's edges when one is given, else similarity_edges under
+/// the SQL-side filters. The in-memory filters are not applied.
+fn load_rows(conn: &Connection, args: &Args) -> anyhow::Result<Vec<shortest_path::Edge>> {
+    load_rows_with(conn, args, &graph_options(args)?)
+}
+
+/// `load_rows` under graph options adjusted by the mode.
+fn load_rows_with(
+    conn: &Connection,
+    args: &Args,
+    opts: &shortest_path::GraphOptions,
+) -> anyhow::Result<Vec<shortest_path::Edge>> {
+    match (args.edges_json_adjacency.as_deref(), args.edges_json.as_deref()) {
+        (Some(path), _) => edge_io::json_adjacency_edges(path, opts),
+        (None, Some(path)) => edge_io::snapshot_edges(path, opts),
+        (None, None) => Ok(shortest_path::load_edges_with(conn, opts)?),
+    }
+}
+
+/// True when the graph comes from a file rather than similarity_edges.
+fn file_graph(args: &Args) -> bool {
+    args.edges_json_adjacency.is_some() || args.edges_json.is_some()
+}
+
+const DEFAULT_URN_MAP: &str = "schema/urn_map.json";
+
+/// Output schema version stamped on every result object as `format_version`.
+/// Bump when a mode's field shapes change.
+const FORMAT_VERSION: u64 = 1;
+
+fn versioned(out: &Value) -> Value {
+    let mut out = out.clone();
+    if let Some(obj) = out.as_object_mut() {
+        obj.insert("format_version".to_string(), json!(FORMAT_VERSION));
+    }
+    out
+}
+
+/// `versioned`, plus the absolute `output_path` under --normalize-output-paths;
+/// only for results that are themselves written to --output.
+fn stamped(out: &Value, args: &Args) -> Value {
+    let mut out = versioned(out);
+    if let (true, Some(obj)) = (args.normalize_output_paths, out.as_object_mut()) {
+        obj.insert("output_path".to_string(), json!(args.output.to_string_lossy()));
+    }
+    out
+}
+
+/// Print the result on stdout — in full, or just the `--extract` pointer.
+fn print_out(out: &Value, args: &Args) -> anyhow::Result<()> {
+    let out = &output::rename_fields(versioned(out), &args.field_map)?;
+    match args.extract.as_deref() {
+        Some(pointer) => println!("{}", output::extract(out, pointer)?),
+        None => println!("{}", serde_json::to_string_pretty(out)?),
+    }
+    Ok(())
+}
+
+/// Write the result JSON to `--output`, echo it on stdout and forward it
+/// to `--emit-http` when set.
+fn emit(out: &Value, args: &Args) -> anyhow::Result<()> {
+    write_output(out, args)?;
+    let out = &stamped(out, args);
+    print_out(out, args)?;
+    if let Some(url) = args.emit_http.as_deref() {
+        forward(url, &output::rename_fields(out.clone(), &args.field_map)?);
+    }
+    Ok(())
+}
+
+/// Best-effort webhook delivery: failures warn, they never fail the run.
+#[cfg(feature = "emit-http")]
+fn forward(url: &str, out: &Value) {
+    match webhook::post_json(url, out) {
+        Ok(status) => tracing::info!(url = %url, status, "Result emitted"),
+        Err(e) => tracing::warn!(url = %url, error = %e, "Failed to emit result"),
+    }
+}
+
+#[cfg(not(feature = "emit-http"))]
+fn forward(url: &str, _out: &Value) {
+    tracing::warn!(url = %url, "--emit-http ignored: built without the emit-http feature");
+}
+
+fn status_out(conn: &Connection, args: &Args) -> Value {
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name != 'analysis_runs'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap_or(0);
+    json!({
+        "status": "ok",
+        "db": args.db.to_string_lossy(),
+        "tables": count,
+        "engine": "singine-persistence-rust",
+        "version": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+fn path_out(result: &shortest_path::PathResult, cache_hit: Option<bool>) -> Value {
+    let mut out = json!({
+        "ok":           true,
+        "src":          result.src_id,
+        "dst":          result.dst_id,
+        "path":         result.path,
+        "total_weight": result.total_weight,
+        "algorithm":    result.algorithm,
+    });
+    if !result.weight_by_type.is_empty() {
+        out["weight_by_type"] = json!(result.weight_by_type);
+    }
+    if let Some(hit) = cache_hit {
+        out["cache_hit"] = json!(hit);
+    }
+    out
+}
+
+/// Failure JSON for a missing path; `empty_graph` separates "no data" from "no route".
+fn no_path_out(conn: &Connection, args: &Args, src: &str, dst: &str) -> anyhow::Result<Value> {
+    let mut out = json!({"ok": false, "error": "No path found", "src": src, "dst": dst});
+    if !file_graph(args) && shortest_path::count_edges(conn, args.edge_type.as_deref())? == 0 {
+        out["empty_graph"] = json!(true);
+    }
+    Ok(out)
+}
+
+/// Latest applied migration; `none` when the table is empty and
+/// `uninitialized` when it is missing (unless `init` creates it, as V001 does).
+fn schema_version(conn: &Connection, init: bool) -> rusqlite::Result<String> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type='table' AND name='schema_migrations')",
+        [],
+        |r| r.get(0),
+    )?;
+    if !exists {
+        if !init {
+            return Ok("uninitialized".to_string());
+        }
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+               version     TEXT NOT NULL PRIMARY KEY,
+               description TEXT NOT NULL,
+               applied_at  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ','now')),
+               checksum    TEXT NOT NULL
+             );",
+        )?;
+        tracing::info!("Created empty schema_migrations table");
+    }
+    let ver: Option<String> = conn
+        .query_row("SELECT MAX(version) FROM schema_migrations", [], |r| r.get(0))?;
+    Ok(ver.unwrap_or_else(|| "none".to_string()))
+}
+
+fn parse_confidence(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(c) if c > 0.0 && c < 1.0 => Ok(c),
+        _ => Err(format!("'{}' is not a confidence level strictly between 0 and 1", s)),
+    }
+}
+
+/// Numeric part of a migration version: `V003`, `v3` and `3` are all 3.
+fn version_number(version: &str) -> Option<u32> {
+    version.trim().trim_start_matches(['V', 'v']).parse().ok()
+}
+
+/// Abort unless the latest applied migration is `required`.
+fn require_schema_version(conn: &Connection, required: &str) -> anyhow::Result<()> {
+    let want = version_number(required)
+        .ok_or_else(|| anyhow::anyhow!("invalid --require-schema-version '{}' (expected e.g. 3 or V003)", required))?;
+    let current = schema_version(conn, false)?;
+    if version_number(&current) != Some(want) {
+        anyhow::bail!(
+            "schema version mismatch: database is at {}, --require-schema-version wants V{:03}; run the migrations first",
+            current,
+            want
+        );
+    }
+    Ok(())
+}
+
+/// `--explain-sql` trace hook: every statement as run, bound parameters expanded.
+fn log_sql(sql: &str) {
+    tracing::info!(sql, "Executing SQL");
+}
+
+/// Pragmas `--pragma` may set; anything touching integrity or the schema is refused.
+const ALLOWED_PRAGMAS: &[&str] =
+    &["busy_timeout", "cache_size", "journal_mode", "mmap_size", "synchronous", "temp_store"];
+
+/// Apply each `--pragma key=value` to `conn`, in order.
+fn apply_pragmas(conn: &Connection, pragmas: &[String]) -> anyhow::Result<()> {
+    for pragma in pragmas {
+        let (key, value) = pragma
+            .split_once('=')
+            .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim()))
+            .ok_or_else(|| anyhow::anyhow!("invalid --pragma '{}' (expected key=value)", pragma))?;
+        if !ALLOWED_PRAGMAS.contains(&key.as_str()) {
+            anyhow::bail!("--pragma {} is not allowed (allowed: {})", key, ALLOWED_PRAGMAS.join(", "));
+        }
+        if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            anyhow::bail!("invalid --pragma {} value '{}'", key, value);
+        }
+        match value.parse::<i64>() {
+            Ok(n) => conn.pragma_update(None, &key, n)?,
+            Err(_) => conn.pragma_update(None, &key, value)?,
+        }
+        tracing::info!(pragma = %key, value, "Applied SQLite pragma");
+    }
+    Ok(())
+}
+
+fn write_output(out: &Value, args: &Args) -> anyhow::Result<()> {
+    let out = output::rename_fields(stamped(out, args), &args.field_map)?;
+    output::write_file(&args.output, &serde_json::to_string_pretty(&out)?, args.gzip)?;
+    Ok(())
+}
+
+#[cfg(feature = "export-parquet")]
+fn export_parquet(output: &Path, edges: &[shortest_path::Edge]) -> anyhow::Result<usize> {
+    parquet_export::write_edges(output, edges)
+}
+
+#[cfg(not(feature = "export-parquet"))]
+fn export_parquet(_output: &Path, _edges: &[shortest_path::Edge]) -> anyhow::Result<usize> {
+    anyhow::bail!("export-parquet mode requires building with --features export-parquet")
 }
 
 fn main() -> anyhow::Result<()> {
-    // Structured logging
+    // Structured logging — on stderr, so stdout carries only the result
     fmt()
         .with_env_filter(
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
         )
+        .with_writer(std::io::stderr)
         .json()
         .init();
 
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if args.normalize_output_paths {
+        args.output = output::normalize_path(&args.output)?;
+    }
 
     tracing::info!(mode = ?args.mode, db = %args.db.display(), "Singine persistence engine start");
 
-    let conn = Connection::open(&args.db)?;
+    if args.replace_run && args.run_id.is_none() {
+        anyhow::bail!("--replace-run requires --run-id");
+    }
+    if args.replace_run && args.resume {
+        anyhow::bail!("--replace-run cannot be combined with --resume: it would delete the rows being resumed");
+    }
+    if args.algorithm == algorithms::Algorithm::Widest
+        && (args.path_score != path_score::PathScore::Sum || args.min_hops.is_some() || args.integer_weights.is_some())
+    {
+        anyhow::bail!("--algorithm widest cannot be combined with --path-score, --min-hops or --integer-weights");
+    }
+    if args.path_score != path_score::PathScore::Sum && (args.min_hops.is_some() || args.integer_weights.is_some()) {
+        anyhow::bail!("--min-hops and --integer-weights only support --path-score sum");
+    }
+    if args.canonical_path && args.directed {
+        anyhow::bail!("--canonical-path only applies to undirected graphs; drop it or --directed");
+    }
+    if args.min_hops.is_some() && args.via.is_some() {
+        anyhow::bail!("--min-hops cannot be combined with --via");
+    }
+    if args.heap == shortest_path::HeapKind::Pairing && !cfg!(feature = "pairing-heap") {
+        anyhow::bail!("--heap pairing requires building with --features pairing-heap");
+    }
 
-    match args.mode {
-        Mode::Status => {
-            let count: i64 = conn
-                .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type='table'", [], |r| {
-                    r.get(0)
-                })
-                .unwrap_or(0);
-            let out = json!({
-                "status": "ok",
-                "db": args.db.to_string_lossy(),
-                "tables": count,
-                "engine": "singine-persistence-rust",
-                "version": env!("CARGO_PKG_VERSION"),
-            });
-            println!("{}", serde_json::to_string_pretty(&out)?);
+    let mut conn = Connection::open(&args.db)?;
+    if args.explain_sql {
+        conn.trace(Some(log_sql));
+    }
+    apply_pragmas(&conn, &args.pragma)?;
+    if let Some(required) = args.require_schema_version.as_deref() {
+        require_schema_version(&conn, required)?;
+    }
+    if !args.no_audit {
+        // the audit row is bookkeeping; a database that cannot take it still runs
+        match open_analysis_run(&conn, &args) {
+            Ok(run) => ANALYSIS_RUN.set(run).expect("analysis run opened once"),
+            Err(e) => tracing::warn!(error = %e, "Could not record analysis run; continuing without audit"),
         }
+    }
+
+    let outcome = run_mode(&conn, &args);
+    close_analysis_run(outcome.is_ok());
+    outcome?;
+    tracing::info!("Done");
+    Ok(())
+}
+
+/// The `analysis_runs` row of this invocation, closed by `exit` or at the
+/// end of `main`.
+static ANALYSIS_RUN: OnceLock<analysis_runs::OpenRun> = OnceLock::new();
+
+fn open_analysis_run(conn: &Connection, args: &Args) -> anyhow::Result<analysis_runs::OpenRun> {
+    // not a registry gen_id: auditing must not consume inodes or add rows
+    // that the registry checks then report
+    let run_id = args.run_id.clone().unwrap_or_else(|| format!("run-{}", uuid::Uuid::new_v4()));
+    let mode = args.mode.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default();
+    let params = serde_json::to_string(&std::env::args().skip(1).collect::<Vec<_>>())?;
+    Ok(analysis_runs::open(conn, &args.db, &run_id, &mode, &params)?)
+}
+
+fn close_analysis_run(success: bool) {
+    if let Some(run) = ANALYSIS_RUN.get() {
+        if let Err(e) = run.close(success) {
+            tracing::warn!(run_id = %run.run_id, error = %e, "Could not close analysis run");
+        }
+    }
+}
+
+/// `std::process::exit`, closing the analysis run first.
+fn exit(code: i32) -> ! {
+    close_analysis_run(code == 0);
+    std::process::exit(code)
+}
+
+fn run_mode(conn: &Connection, args: &Args) -> anyhow::Result<()> {
+    match args.mode {
+        Mode::Status => print_out(&status_out(conn, args), args)?,
 
         Mode::GenId => {
-            let rec = id_gen::generate(&conn, &args.namespace, args.hint.as_deref())?;
-            let out = json!({
-                "gen_id": rec.gen_id,
-                "urn":    rec.urn,
-                "inode":  rec.inode,
+            let namespace = if args.normalize_namespace {
+                id_gen::normalize_namespace(&args.namespace)
+            } else {
+                args.namespace.clone()
+            };
+            let urn_map = match args.urn_map_file.as_deref() {
+                Some(path) => id_gen::UrnMap::load(path, true)?,
+                None => id_gen::UrnMap::load(Path::new(DEFAULT_URN_MAP), false)?,
+            };
+            let rec = id_gen::generate_tracked(conn, &urn_map, &namespace, args.hint.as_deref(), args.max_inode)?;
+            let out = json!(rec);
+            print_out(&out, args)?;
+        }
+
+        Mode::FindHint => {
+            let hint = args.hint.as_deref().unwrap_or_else(|| {
+                tracing::error!("--hint required for find-hint mode");
+                exit(1);
             });
-            println!("{}", serde_json::to_string_pretty(&out)?);
+            let ids = id_gen::find_by_hint(conn, hint)?;
+            let out = json!({"hint": hint, "count": ids.len(), "ids": ids});
+            print_out(&out, args)?;
+        }
+
+        Mode::VerifyInodes => {
+            let reports = id_gen::verify_inodes(conn)?;
+            let ok = reports.iter().all(|r| r.ok);
+            let out = json!({"ok": ok, "namespaces": reports});
+            print_out(&out, args)?;
+            if !ok {
+                exit(2);
+            }
+        }
+
+        Mode::CheckRegistry => {
+            let check = id_gen::check_registry(conn, args.fix)?;
+            let out = json!({"ok": check.ok || args.fix, "registry": check});
+            print_out(&out, args)?;
+            if !check.ok && !args.fix {
+                exit(2);
+            }
+        }
+
+        Mode::CompactInodes => {
+            if !args.confirm {
+                tracing::error!(namespace = %args.namespace, "compact-inodes renumbers registry rows; pass --confirm");
+                exit(1);
+            }
+            let report = id_gen::compact_inodes(conn, &args.namespace)?;
+            let out = json!({"ok": true, "compact": report});
+            print_out(&out, args)?;
+        }
+
+        Mode::BackfillRegistry => {
+            let report = id_gen::backfill_registry(conn)?;
+            let out = json!({"ok": true, "backfill": report});
+            print_out(&out, args)?;
+        }
+
+        Mode::ShortestPath if args.src_set.is_some() || args.dst_set.is_some() => {
+            // the multi-source search is plain summed Dijkstra on a binary heap
+            let unsupported: Vec<&str> = [
+                ("--via", args.via.is_some()),
+                ("--min-hops", args.min_hops.is_some()),
+                ("--algorithm widest", args.algorithm != algorithms::Algorithm::Dijkstra),
+                ("--path-score", args.path_score != path_score::PathScore::Sum),
+                ("--integer-weights", args.integer_weights.is_some()),
+                ("--heap", args.heap != shortest_path::HeapKind::Binary),
+                ("--max-open-states", args.max_open_states.is_some()),
+                ("--canonical-path", args.canonical_path),
+            ]
+            .into_iter()
+            .filter_map(|(flag, set)| set.then_some(flag))
+            .collect();
+            if !unsupported.is_empty() {
+                tracing::error!(flags = ?unsupported, "cannot be combined with --src-set/--dst-set");
+                exit(1);
+            }
+            let candidates = |set: &Option<PathBuf>, single: &Option<String>, flag: &str| match (set, single) {
+                (Some(path), _) => pairwise::read_nodes(path),
+                (None, Some(id)) => Ok(vec![id.clone()]),
+                (None, None) => {
+                    tracing::error!("--{}-set or --{} required for shortest-path mode", flag, flag);
+                    exit(1);
+                }
+            };
+            let sources = candidates(&args.src_set, &args.src, "src")?;
+            let targets = candidates(&args.dst_set, &args.dst, "dst")?;
+            let adj = load_checked(conn, args)?;
+            let found = shortest_path::search_sets_and_persist(
+                conn, &adj, &sources, &targets, &graph_options(args)?, &search_options(args),
+                args.run_id.as_deref(),
+            )?;
+            let mut out = match found {
+                Some(mut result) => {
+                    result.path = id_gen::format_path(conn, &result.path, args.path_format)?;
+                    path_out(&result, None)
+                }
+                None => json!({"ok": false, "error": "No path found"}),
+            };
+            out["src_candidates"] = json!(sources.len());
+            out["dst_candidates"] = json!(targets.len());
+            if args.weight_as == output::WeightAs::Duration {
+                output::add_durations(&mut out);
+            }
+            if let Some(places) = args.weight_precision {
+                output::round_weights(&mut out, places);
+            }
+            if out["ok"] == json!(true) {
+                emit(&out, args)?;
+            } else {
+                write_output(&out, args)?;
+                exit(2);
+            }
         }
 
         Mode::ShortestPath => {
             let src = args.src.as_deref().unwrap_or_else(|| {
                 tracing::error!("--src required for shortest-path mode");
-                std::process::exit(1);
+                exit(1);
             });
             let dst = args.dst.as_deref().unwrap_or_else(|| {
                 tracing::error!("--dst required for shortest-path mode");
-                std::process::exit(1);
+                exit(1);
             });
+            if !file_graph(args) {
+                shortest_path::enforce_edge_limit(
+                    conn,
+                    args.edge_type.as_deref(),
+                    args.edge_limit,
+                    args.force,
+                )?;
+            }
 
-            match shortest_path::compute_and_persist(
-                &conn,
-                src,
-                dst,
-                args.edge_type.as_deref(),
-                args.run_id.as_deref(),
-            )? {
-                Some(result) => {
+            let template = match args.template.as_deref() {
+                Some(path) => Some(output::parse_template(&std::fs::read_to_string(path)?)?),
+                None => None,
+            };
+            let (graph_opts, search_opts) = (graph_options(args)?, search_options(args));
+            if args.profile && (args.cache || file_graph(args)) {
+                tracing::warn!("--profile only times the database-backed search; no timings reported");
+            }
+            let mut timings = None;
+            // the graph the search built, reused by --with-alternatives and --diagnose
+            let mut graph = None;
+            let (mut found, cache_hit) = if file_graph(args) {
+                let g = load_checked_graph(conn, args)?;
+                let r = shortest_path::search_and_persist(
+                    conn, &g, src, dst, &graph_opts, &search_opts, args.run_id.as_deref(),
+                )?;
+                graph = Some(g);
+                (r, None)
+            } else if args.cache {
+                let (r, hit) = cache::cached_shortest_path(
+                    conn, src, dst, &graph_opts, &search_opts, args.run_id.as_deref(),
+                )?;
+                (r, Some(hit))
+            } else {
+                let mut t = shortest_path::PhaseTimings::default();
+                let (r, g) = shortest_path::compute_and_persist_timed(
+                    conn, src, dst, &graph_opts, &search_opts, args.run_id.as_deref(), &mut t,
+                )?;
+                timings = args.profile.then_some(t);
+                graph = Some(g);
+                (r, None)
+            };
+
+            if let Some(result) = found.as_mut() {
+                result.path = id_gen::format_path(conn, &result.path, args.path_format)?;
+            }
+            match found {
+                Some(result) => match &template {
+                    Some(t) => {
+                        let mut fields = json!({
+                            "src":          result.src_id,
+                            "dst":          result.dst_id,
+                            "total_weight": result.total_weight,
+                            "hops":         result.path.len().saturating_sub(1),
+                            "path":         result.path,
+                        });
+                        if let Some(places) = args.weight_precision {
+                            output::round_weights(&mut fields, places);
+                        }
+                        let fields = fields.as_object().expect("object literal");
+                        emit(&output::render_template(t, fields), args)?
+                    }
+                    None => {
+                        let mut out = path_out(&result, cache_hit);
+                        if let Some(t) = &timings {
+                            out["timings"] = json!(t);
+                        }
+                        if let Some(via) = &args.via {
+                            out["via"] = json!(via);
+                        }
+                        if args.limit_neighbors.is_some() {
+                            out["approximate"] = json!(true);
+                        }
+                        if !args.exclude_edge.is_empty() {
+                            out["excluded_edges"] = json!(args.exclude_edge);
+                        }
+                        if args.with_alternatives {
+                            let adj = match graph {
+                                Some(g) => g.adj,
+                                None => load_checked(conn, args)?,
+                            };
+                            out["alternatives"] = json!(neighbors::alternatives(&adj, src, dst));
+                        }
+                        if args.weight_as == output::WeightAs::Duration {
+                            output::add_durations(&mut out);
+                        }
+                        if let Some(places) = args.weight_precision {
+                            output::round_weights(&mut out, places);
+                        }
+                        emit(&out, args)?
+                    }
+                },
+                None => {
+                    let mut out = no_path_out(conn, args, src, dst)?;
+                    if let Some(t) = &timings {
+                        out["timings"] = json!(t);
+                    }
+                    if let Some(via) = &args.via {
+                        out["via"] = json!(via);
+                    }
+                    if args.limit_neighbors.is_some() {
+                        out["approximate"] = json!(true);
+                    }
+                    if !args.exclude_edge.is_empty() {
+                        out["excluded_edges"] = json!(args.exclude_edge);
+                    }
+                    if args.diagnose {
+                        let adj = match graph {
+                            Some(g) => g.adj,
+                            None => load_checked(conn, args)?,
+                        };
+                        out["diagnosis"] = json!(components::diagnose_no_path(&adj, src, dst));
+                    }
+                    write_output(&out, args)?;
+                    exit(2);
+                }
+            }
+        }
+
+        Mode::CountPaths => {
+            let (Some(src), Some(dst)) = (args.src.as_deref(), args.dst.as_deref()) else {
+                tracing::error!("--src and --dst required for count-paths mode");
+                exit(1);
+            };
+            let adj = load_checked(conn, args)?;
+            shortest_path::check_endpoints(&adj, &graph_options(args)?, &[src, dst])?;
+            match shortest_path::count_shortest_paths(&adj, src, dst) {
+                Some(c) => {
                     let out = json!({
                         "ok":           true,
-                        "src":          result.src_id,
-                        "dst":          result.dst_id,
-                        "path":         result.path,
-                        "total_weight": result.total_weight,
-                        "algorithm":    result.algorithm,
+                        "src":          c.src_id,
+                        "dst":          c.dst_id,
+                        "total_weight": c.total_weight,
+                        "count":        c.count,
+                        "saturated":    c.saturated,
+                    });
+                    emit(&out, args)?;
+                }
+                None => {
+                    let mut out = no_path_out(conn, args, src, dst)?;
+                    out["count"] = json!(0);
+                    write_output(&out, args)?;
+                    exit(2);
+                }
+            }
+        }
+
+        Mode::Compare => {
+            let (Some(src), Some(dst)) = (args.src.as_deref(), args.dst.as_deref()) else {
+                tracing::error!("--src and --dst required for compare mode");
+                exit(1);
+            };
+            let adj = load_checked(conn, args)?;
+            let cmp = algorithms::compare(&adj, src, dst);
+            let fields = serde_json::to_value(&cmp)?;
+            let out = json!({
+                "ok":          cmp.agree,
+                "src":         src,
+                "dst":         dst,
+                "agree":       cmp.agree,
+                "discrepancy": fields["discrepancy"],
+                "runs":        fields["runs"],
+            });
+            emit(&out, args)?;
+            if !cmp.agree {
+                exit(2);
+            }
+        }
+
+        Mode::Batch => {
+            let pairs_file = args.pairs.as_deref().unwrap_or_else(|| {
+                tracing::error!("--pairs required for batch mode");
+                exit(1);
+            });
+            let pairs = batch::read_pairs(pairs_file)?;
+            let graph = load_checked_graph(conn, args)?;
+            let checkpoint = if args.checkpoint_every.is_some() || args.resume {
+                let path = batch::partial_path(&args.output);
+                let resume = if args.resume { batch::read_partial(&path)? } else { Vec::new() };
+                Some(batch::Checkpoint { path, every: args.checkpoint_every.unwrap_or(0), resume })
+            } else {
+                None
+            };
+            let results = batch::run_batch(
+                conn,
+                &graph,
+                &pairs,
+                &graph_options(args)?,
+                &search_options(args),
+                args.run_id.as_deref(),
+                checkpoint.as_ref(),
+                args.jobs,
+            )?;
+            let found = results.iter().filter(|r| r.result.is_some()).count();
+            let missing: Vec<_> = results
+                .iter()
+                .filter(|r| r.result.is_none())
+                .map(|r| json!({"src": r.src_id, "dst": r.dst_id}))
+                .collect();
+
+            let out = if args.dedup_paths {
+                let deduped = batch::dedup_paths(&results);
+                json!({
+                    "ok":           true,
+                    "pairs":        results.len(),
+                    "found":        found,
+                    "unique_paths": deduped.len(),
+                    "paths":        deduped,
+                    "missing":      missing,
+                })
+            } else {
+                let rows: Vec<_> = results
+                    .iter()
+                    .filter_map(|r| r.result.as_ref())
+                    .map(|r| {
+                        let mut row = json!({
+                            "src":          r.src_id,
+                            "dst":          r.dst_id,
+                            "path":         r.path,
+                            "total_weight": r.total_weight,
+                            "algorithm":    r.algorithm,
+                        });
+                        if !r.weight_by_type.is_empty() {
+                            row["weight_by_type"] = json!(r.weight_by_type);
+                        }
+                        row
+                    })
+                    .collect();
+                json!({
+                    "ok":      true,
+                    "pairs":   results.len(),
+                    "found":   found,
+                    "results": rows,
+                    "missing": missing,
+                })
+            };
+            emit(&out, args)?;
+        }
+
+        Mode::Neighbors => {
+            let src = args.src.as_deref().unwrap_or_else(|| {
+                tracing::error!("--src required for neighbors mode");
+                exit(1);
+            });
+            let adj = load_checked(conn, args)?;
+            let hood = neighbors::neighborhood(&adj, src, args.depth, args.max_nodes);
+            let out = json!({
+                "ok":        true,
+                "src":       src,
+                "depth":     hood.depth,
+                "nodes":     hood.nodes,
+                "truncated": hood.truncated,
+                "tree":      hood.root,
+            });
+            emit(&out, args)?;
+        }
+
+        Mode::Bands => {
+            let src = args.src.as_deref().unwrap_or_else(|| {
+                tracing::error!("--src required for bands mode");
+                exit(1);
+            });
+            if args.band_width <= 0.0 {
+                anyhow::bail!("--band-width must be positive");
+            }
+            let adj = load_checked(conn, args)?;
+            let bands = neighbors::distance_bands(&adj, src, args.band_width);
+            let out = json!({
+                "ok":         true,
+                "src":        src,
+                "band_width": args.band_width,
+                "reachable":  bands.iter().map(|b| b.count).sum::<usize>(),
+                "bands":      bands,
+            });
+            emit(&out, args)?;
+        }
+
+        Mode::Mst => {
+            if !file_graph(args) {
+                shortest_path::enforce_edge_limit(conn, args.edge_type.as_deref(), args.edge_limit, args.force)?;
+            }
+            let opts = graph_options(args)?;
+            let mut edges = load_rows_with(conn, args, &opts)?;
+            opts.apply(&mut edges);
+            shortest_path::quicksort_edges(&mut edges);
+            let forest = mst::minimum_spanning_forest(&edges);
+            let out = json!({"ok": true, "mst": forest});
+            emit(&out, args)?;
+        }
+
+        Mode::Bridges => {
+            if !file_graph(args) {
+                shortest_path::enforce_edge_limit(conn, args.edge_type.as_deref(), args.edge_limit, args.force)?;
+            }
+            let opts = graph_options(args)?;
+            let mut edges = load_rows_with(conn, args, &opts)?;
+            opts.apply(&mut edges);
+            let bridges = critical::bridges(&edges);
+            let out = json!({"ok": true, "count": bridges.len(), "bridges": bridges});
+            emit(&out, args)?;
+        }
+
+        Mode::Articulation => {
+            if !file_graph(args) {
+                shortest_path::enforce_edge_limit(conn, args.edge_type.as_deref(), args.edge_limit, args.force)?;
+            }
+            let opts = graph_options(args)?;
+            let mut edges = load_rows_with(conn, args, &opts)?;
+            opts.apply(&mut edges);
+            let points = critical::articulation_points(&edges);
+            let out = json!({"ok": true, "count": points.len(), "articulation_points": points});
+            emit(&out, args)?;
+        }
+
+        Mode::Components => {
+            let adj = load_checked(conn, args)?;
+            let listed = components::components(&adj);
+            let out = json!({"ok": true, "count": listed.len(), "components": listed});
+            emit(&out, args)?;
+        }
+
+        Mode::Ancestors => {
+            let src = args.src.as_deref().unwrap_or_else(|| {
+                tracing::error!("--src required for ancestors mode");
+                exit(1);
+            });
+            let mut opts = graph_options(args)?;
+            opts.edge_type.get_or_insert_with(|| hierarchy::DEFAULT_EDGE_TYPE.to_string());
+            let mut edges = load_rows_with(conn, args, &opts)?;
+            opts.apply(&mut edges);
+            let chain = hierarchy::ancestors(&edges, src)?;
+            let out = json!({
+                "ok":        true,
+                "src":       src,
+                "edge_type": opts.edge_type,
+                "root":      chain.last(),
+                "depth":     chain.len() - 1,
+                "ancestors": chain,
+            });
+            emit(&out, args)?;
+        }
+
+        Mode::Descendants => {
+            let src = args.src.as_deref().unwrap_or_else(|| {
+                tracing::error!("--src required for descendants mode");
+                exit(1);
+            });
+            let mut opts = graph_options(args)?;
+            opts.edge_type.get_or_insert_with(|| hierarchy::DEFAULT_EDGE_TYPE.to_string());
+            let mut edges = load_rows_with(conn, args, &opts)?;
+            opts.apply(&mut edges);
+            let sub = hierarchy::descendants(&edges, src);
+            let out = json!({
+                "ok":          sub.cycles.is_empty(),
+                "src":         src,
+                "edge_type":   opts.edge_type,
+                "count":       sub.descendants.len(),
+                "descendants": sub.descendants,
+                "cycles":      sub.cycles,
+            });
+            emit(&out, args)?;
+        }
+
+        Mode::Diameter => {
+            let adj = load_checked(conn, args)?;
+            let d = diameter::diameter(&adj, args.max_nodes)?;
+            let out = json!({"ok": true, "diameter": d});
+            emit(&out, args)?;
+        }
+
+        Mode::Pairwise => {
+            let nodes_file = args.nodes.as_deref().unwrap_or_else(|| {
+                tracing::error!("--nodes required for pairwise mode");
+                exit(1);
+            });
+            let nodes = pairwise::read_nodes(nodes_file)?;
+            let adj = load_checked(conn, args)?;
+            let matrix = pairwise::pairwise_matrix(&adj, &nodes);
+            let out = json!({"ok": true, "nodes": matrix.nodes, "matrix": matrix.matrix});
+            emit(&out, args)?;
+        }
+
+        Mode::Centroid => {
+            let nodes_file = args.nodes.as_deref().unwrap_or_else(|| {
+                tracing::error!("--nodes required for centroid mode");
+                exit(1);
+            });
+            let nodes = pairwise::read_nodes(nodes_file)?;
+            let adj = load_checked(conn, args)?;
+            match pairwise::centroid(&adj, &nodes) {
+                Some(c) => {
+                    let out = json!({
+                        "ok":             true,
+                        "nodes":          nodes.len(),
+                        "centroid":       c.node,
+                        "total_distance": c.total_distance,
                     });
-                    let json_str = serde_json::to_string_pretty(&out)?;
-                    std::fs::write(&args.output, &json_str)?;
-                    println!("{}", json_str);
+                    emit(&out, args)?;
                 }
                 None => {
-                    let out = json!({"ok": false, "error": "No path found", "src": src, "dst": dst});
-                    std::fs::write(&args.output, serde_json::to_string_pretty(&out)?)?;
-                    std::process::exit(2);
+                    let out = json!({
+                        "ok":    false,
+                        "error": "No listed node reaches all the others",
+                        "nodes": nodes.len(),
+                    });
+                    write_output(&out, args)?;
+                    exit(2);
                 }
             }
         }
 
+        Mode::ConnectivitySample => {
+            let adj = load_checked(conn, args)?;
+            let sample = sampling::connectivity_sample(&adj, args.samples, args.seed, args.confidence);
+            let out = json!({"ok": true, "seed": args.seed, "connectivity": sample});
+            emit(&out, args)?;
+        }
+
+        Mode::SampleEdges => {
+            let edges = load_rows(conn, args)?;
+            let total = edges.len();
+            let sample = sampling::sample_edges(edges, args.samples, args.seed);
+            let out = json!({
+                "ok":    true,
+                "seed":  args.seed,
+                "total": total,
+                "count": sample.len(),
+                "edges": sample,
+            });
+            print_out(&out, args)?;
+        }
+
+        Mode::RandomWalk => {
+            let src = args.src.as_deref().unwrap_or_else(|| {
+                tracing::error!("--src required for random-walk mode");
+                exit(1);
+            });
+            let adj = load_checked(conn, args)?;
+            let walks = sampling::random_walks(&adj, src, args.steps, args.walks, args.walk_bias, args.seed);
+            let out = json!({"ok": true, "src": src, "seed": args.seed, "steps": args.steps, "walks": walks});
+            emit(&out, args)?;
+        }
+
+        Mode::Repl => {
+            let adj = load_checked(conn, args)?;
+            let session = repl::Session { adj: &adj, depth: args.depth, max_nodes: args.max_nodes };
+            let commands = session.run(std::io::stdin().lock(), std::io::stdout().lock())?;
+            tracing::info!(commands, "REPL session ended");
+        }
+
+        Mode::ValidateGraph => {
+            if !file_graph(args) {
+                shortest_path::enforce_edge_limit(
+                    conn,
+                    args.edge_type.as_deref(),
+                    args.edge_limit,
+                    args.force,
+                )?;
+            }
+            let edges = load_rows(conn, args)?;
+            let report = validate::validate(&edges);
+            let out = json!({"ok": true, "report": report});
+            emit(&out, args)?;
+        }
+
+        Mode::EdgeTypes => {
+            let counts = shortest_path::edge_type_counts(conn)?;
+            let untyped = counts.iter().find(|c| c.edge_type.is_none()).map_or(0, |c| c.count);
+            let out = json!({
+                "ok":         true,
+                "edges":      counts.iter().map(|c| c.count).sum::<u64>(),
+                "untyped":    untyped,
+                "edge_types": counts,
+            });
+            print_out(&out, args)?;
+        }
+
+        Mode::Nodes => {
+            let (total, nodes) =
+                shortest_path::distinct_nodes(conn, args.edge_type.as_deref(), args.limit, args.offset)?;
+            let out = json!({
+                "ok":     true,
+                "total":  total,
+                "offset": args.offset,
+                "limit":  args.limit,
+                "count":  nodes.len(),
+                "nodes":  nodes,
+            });
+            print_out(&out, args)?;
+        }
+
+        Mode::ImportEdges => {
+            let input = args.input.as_deref().unwrap_or_else(|| {
+                tracing::error!("--input required for import-edges mode");
+                exit(1);
+            });
+            let text = std::fs::read_to_string(input)?;
+            let report = edge_io::import_edges_csv(conn, &text, args.fail_on_cycle)?;
+            let rolled_back = report.rolled_back;
+            let out = json!({"ok": !rolled_back, "import": report});
+            print_out(&out, args)?;
+            if rolled_back {
+                exit(2);
+            }
+        }
+
+        Mode::ExportEdges => {
+            let edges = shortest_path::load_edges(conn, args.edge_type.as_deref())?;
+            output::write_file(&args.output, &edge_io::export_edges_csv(&edges), args.gzip)?;
+            let out = json!({"ok": true, "output": args.output.to_string_lossy(), "rows": edges.len()});
+            print_out(&out, args)?;
+        }
+
+        Mode::ExportNetworkx => {
+            let adj = load_checked(conn, args)?;
+            let data = graph_export::node_link(&adj, args.directed);
+            output::write_file(&args.output, &serde_json::to_string_pretty(&data)?, args.gzip)?;
+            let out = json!({
+                "ok":     true,
+                "output": args.output.to_string_lossy(),
+                "nodes":  data.nodes.len(),
+                "links":  data.links.len(),
+            });
+            print_out(&out, args)?;
+        }
+
+        Mode::ExportCypher => {
+            let edges = load_rows(conn, args)?;
+            let text = graph_export::cypher(&edges, args.directed, args.cypher_verb);
+            output::write_file(&args.output, &text, args.gzip)?;
+            let out = json!({"ok": true, "output": args.output.to_string_lossy(), "rows": edges.len()});
+            print_out(&out, args)?;
+        }
+
+        Mode::ShardEdges => {
+            let out_dir = args.out_dir.as_deref().unwrap_or_else(|| {
+                tracing::error!("--out-dir required for shard-edges mode");
+                exit(1);
+            });
+            let shards = edge_io::shard_edges(conn, out_dir)?;
+            let rows: usize = shards.iter().map(|s| s.rows).sum();
+            let out = json!({"ok": true, "out_dir": out_dir.to_string_lossy(), "rows": rows, "shards": shards});
+            print_out(&out, args)?;
+        }
+
+        Mode::ExportParquet => {
+            if !file_graph(args) {
+                shortest_path::enforce_edge_limit(
+                    conn,
+                    args.edge_type.as_deref(),
+                    args.edge_limit,
+                    args.force,
+                )?;
+            }
+            let edges = load_rows(conn, args)?;
+            let rows = export_parquet(&args.output, &edges)?;
+            let out = json!({"ok": true, "output": args.output.to_string_lossy(), "rows": rows});
+            print_out(&out, args)?;
+        }
+
+        Mode::Snapshot => {
+            let snap = edge_io::take_snapshot(conn, schema_version(conn, false)?)?;
+            output::write_file(&args.output, &serde_json::to_string_pretty(&snap)?, args.gzip)?;
+            let out = json!({
+                "ok":             true,
+                "output":         args.output.to_string_lossy(),
+                "edges":          snap.edges.len(),
+                "schema_version": snap.schema_version,
+                "graph_hash":     snap.graph_hash,
+            });
+            print_out(&out, args)?;
+        }
+
+        Mode::Seed => {
+            let report = seed::seed_graph(conn, args.node_count, args.edge_count, args.seed)?;
+            let out = json!({"ok": true, "db": args.db.to_string_lossy(), "seed": report});
+            print_out(&out, args)?;
+        }
+
+        Mode::BuildAdjacency => {
+            let adj = load_checked(conn, args)?;
+            let rows = edge_io::write_adjacency(conn, &adj)?;
+            let out = json!({"ok": true, "table": "adjacency", "nodes": adj.len(), "rows": rows});
+            print_out(&out, args)?;
+        }
+
+        Mode::ListPaths => {
+            let since = args.since.as_deref().map(path_results::parse_since).transpose()?;
+            let rows = path_results::list_paths(conn, since.as_deref())?;
+            let out = json!({"ok": true, "since": since, "count": rows.len(), "paths": rows});
+            print_out(&out, args)?;
+        }
+
+        Mode::ReportPaths => {
+            let since = args.since.as_deref().map(path_results::parse_since).transpose()?;
+            let report = path_results::report_paths(conn, since.as_deref())?;
+            let out = json!({"ok": true, "since": since, "report": report});
+            print_out(&out, args)?;
+        }
+
         Mode::MigrateCheck => {
-            let ver: String = conn
-                .query_row(
-                    "SELECT version FROM schema_migrations ORDER BY version DESC LIMIT 1",
-                    [],
-                    |r| r.get(0),
-                )
-                .unwrap_or_else(|_| "none".to_string());
+            let ver = schema_version(conn, args.init)?;
             let out = json!({"schema_version": ver, "db": args.db.to_string_lossy()});
-            println!("{}", serde_json::to_string_pretty(&out)?);
+            print_out(&out, args)?;
         }
     }
-
-    tracing::info!("Done");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outputs_carry_format_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        let args = Args::try_parse_from(["persistence", "--db", "unused.db"]).unwrap();
+        let status = versioned(&status_out(&conn, &args));
+        assert_eq!(status["format_version"], json!(FORMAT_VERSION));
+        assert_eq!(status["format_version"], json!(1));
+
+        let result = shortest_path::PathResult {
+            src_id:         "A".into(),
+            dst_id:         "B".into(),
+            path:           vec!["A".into(), "B".into()],
+            total_weight:   1.0,
+            algorithm:      "dijkstra+quicksort".into(),
+            weight_by_type: Default::default(),
+        };
+        let path = versioned(&path_out(&result, None));
+        assert_eq!(path["format_version"], json!(1));
+        assert_eq!(path["total_weight"], json!(1.0));
+    }
+
+    #[test]
+    fn test_schema_version_statuses() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(schema_version(&conn, false).unwrap(), "uninitialized");
+        assert_eq!(schema_version(&conn, true).unwrap(), "none");
+        assert_eq!(schema_version(&conn, false).unwrap(), "none");
+        conn.execute(
+            "INSERT INTO schema_migrations (version, description, checksum) VALUES ('V001','init','x')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(schema_version(&conn, false).unwrap(), "V001");
+    }
+
+    #[test]
+    fn test_require_schema_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(require_schema_version(&conn, "3").is_err());
+        schema_version(&conn, true).unwrap();
+        conn.execute(
+            "INSERT INTO schema_migrations (version, description, checksum) VALUES ('V003','categories','x')",
+            [],
+        )
+        .unwrap();
+        let err = require_schema_version(&conn, "4").unwrap_err().to_string();
+        assert!(err.contains("V003") && err.contains("V004"), "{}", err);
+        assert!(require_schema_version(&conn, "3").is_ok());
+        assert!(require_schema_version(&conn, "V003").is_ok());
+        assert!(require_schema_version(&conn, "latest").is_err());
+    }
+
+    #[test]
+    fn test_pragmas_applied_and_allowlisted() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_pragmas(&conn, &["cache_size=-4000".into(), "temp_store=MEMORY".into()]).unwrap();
+        let cache_size: i64 = conn.pragma_query_value(None, "cache_size", |r| r.get(0)).unwrap();
+        assert_eq!(cache_size, -4000);
+
+        let err = apply_pragmas(&conn, &["writable_schema=1".into()]).unwrap_err().to_string();
+        assert!(err.contains("not allowed"), "{}", err);
+        assert!(apply_pragmas(&conn, &["cache_size=1; DROP TABLE x".into()]).is_err());
+        assert!(apply_pragmas(&conn, &["cache_size".into()]).is_err());
+    }
+
+    #[test]
+    fn test_explain_sql_logs_load_query_with_parameters() {
+        #[derive(Clone, Default)]
+        struct Capture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let logs = Capture::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt().with_writer(move || writer.clone()).finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let mut conn = Connection::open_in_memory().unwrap();
+            conn.execute_batch(
+                "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                                edge_type TEXT);",
+            )
+            .unwrap();
+            conn.trace(Some(log_sql));
+            let opts = shortest_path::GraphOptions { edge_type: Some("lineage".into()), ..Default::default() };
+            shortest_path::load_edges_with(&conn, &opts).unwrap();
+        });
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("FROM similarity_edges WHERE edge_type = 'lineage'"), "{}", logs);
+    }
+
+    #[test]
+    fn test_params_json_records_query_flags() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema_version(&conn, true).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT);
+             CREATE TABLE path_results (gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT,
+                                        path_json TEXT, total_weight REAL, algorithm TEXT,
+                                        computed_at TEXT, run_id TEXT);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','B',1.0,'lineage'), ('e2','B','C',1.0,'lineage'), ('e3','A','C',0.5,'lineage');",
+        )
+        .unwrap();
+        conn.execute_batch(include_str!("../../schema/V004__path_params.sql")).unwrap();
+        assert_eq!(schema_version(&conn, false).unwrap(), "V004");
+
+        let args = Args::try_parse_from([
+            "persistence", "--edge-type", "lineage", "--directed", "--exclude-edge", "e3",
+        ])
+        .unwrap();
+        let (g, s) = (graph_options(&args).unwrap(), search_options(&args));
+        let found = shortest_path::compute_and_persist(&conn, "A", "C", &g, &s, None).unwrap().unwrap();
+        assert_eq!(found.path, vec!["A", "B", "C"]);
+
+        let stored: String =
+            conn.query_row("SELECT params_json FROM path_results", [], |r| r.get(0)).unwrap();
+        let params: Value = serde_json::from_str(&stored).unwrap();
+        assert_eq!(params["edge_type"], json!("lineage"));
+        assert_eq!(params["directed"], json!(true));
+        assert_eq!(params["exclude_edges"], json!(["e3"]));
+        assert_eq!(params["parallel_policy"], json!("min"));
+        assert_eq!(params["heap"], json!("binary"));
+    }
+
+    #[test]
+    fn test_empty_table_marks_empty_graph() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT);",
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["persistence", "--db", "unused.db"]).unwrap();
+        let out = no_path_out(&conn, &args, "A", "B").unwrap();
+        assert_eq!(out["empty_graph"], json!(true));
+
+        conn.execute("INSERT INTO similarity_edges VALUES ('e1','A','C',1.0,'similarity')", [])
+            .unwrap();
+        let out = no_path_out(&conn, &args, "A", "B").unwrap();
+        assert!(out.get("empty_graph").is_none());
+    }
+
+    #[test]
+    fn test_analysis_run_recorded_by_default() {
+        let db = std::env::temp_dir().join(format!("analysis-run-{}.db", std::process::id()));
+        let conn = Connection::open(&db).unwrap();
+        seed::seed_graph(&conn, 4, 4, 7).unwrap();
+        let report = std::env::temp_dir().join(format!("analysis-run-{}.json", std::process::id()));
+        let (db_arg, report_arg) = (db.to_string_lossy().to_string(), report.to_string_lossy().to_string());
+        let args = Args::try_parse_from([
+            "persistence", "--db", &db_arg, "--output", &report_arg, "--mode", "components",
+        ])
+        .unwrap();
+        assert!(!args.no_audit);
+        let registered = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM id_registry", [], |r| r.get(0)).unwrap_or(0)
+        };
+        let (tables, ids) = (status_out(&conn, &args)["tables"].clone(), registered(&conn));
+        let run = open_analysis_run(&conn, &args).unwrap();
+        assert!(run.run_id.starts_with("run-"), "{}", run.run_id);
+        assert_eq!(status_out(&conn, &args)["tables"], tables, "the audit table is not counted");
+        assert_eq!(registered(&conn), ids, "no registry row for the run id");
+        run_mode(&conn, &args).unwrap();
+        run.close(true).unwrap();
+
+        let (mode, status, started, finished): (String, String, String, Option<String>) = conn
+            .query_row(
+                "SELECT mode, status, started_at, finished_at FROM analysis_runs WHERE run_id = ?1",
+                [&run.run_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .unwrap();
+        drop(conn);
+        std::fs::remove_file(&db).ok();
+        std::fs::remove_file(&report).ok();
+        assert_eq!((mode.as_str(), status.as_str()), ("components", "success"));
+        let finished = finished.expect("finished_at set");
+        assert!(finished >= started, "{} < {}", finished, started);
+    }
+}
@@ -0,0 +1,107 @@
+//! mst.rs — Singine minimum spanning forest
+//!
+//! `--mode mst` runs Kruskal over the loaded edge list: edges come out of
+//! `quicksort_edges` in ascending weight order, and a union-find with path
+//! halving and union by size keeps the cheapest edge joining each pair of
+//! trees. Disconnected graphs yield one tree per component.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::shortest_path::Edge;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpanningForest {
+    pub nodes:        usize,
+    pub trees:        usize,
+    pub total_weight: f64,
+    pub edges:        Vec<Edge>,
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+    size:   Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        UnionFind { parent: (0..n).collect(), size: vec![1; n] }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merge the trees of `a` and `b`; false when already joined.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        true
+    }
+}
+
+/// Kruskal over `edges`, which must already be sorted by ascending weight.
+pub fn minimum_spanning_forest(edges: &[Edge]) -> SpanningForest {
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    for e in edges {
+        for n in [e.src_id.as_str(), e.dst_id.as_str()] {
+            let next = ids.len();
+            ids.entry(n).or_insert(next);
+        }
+    }
+    let mut uf = UnionFind::new(ids.len());
+    let mut tree_edges = Vec::new();
+    for e in edges {
+        if uf.union(ids[e.src_id.as_str()], ids[e.dst_id.as_str()]) {
+            tree_edges.push(e.clone());
+        }
+    }
+    SpanningForest {
+        nodes:        ids.len(),
+        trees:        ids.len() - tree_edges.len(),
+        total_weight: tree_edges.iter().map(|e| e.weight).sum(),
+        edges:        tree_edges,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{dedup_edges, edge, quicksort_edges, EdgeDedup};
+
+    #[test]
+    fn test_dedup_rows_then_mst() {
+        let mut edges = vec![
+            edge("A", "B", 4.0).with_id("e1"),
+            edge("A", "B", 1.0).with_id("e2"),
+            edge("B", "C", 2.0).with_id("e3"),
+            edge("B", "C", 2.0).with_id("e4"),
+            edge("A", "C", 3.0).with_id("e5"),
+            edge("X", "Y", 1.0).with_id("e6"),
+        ];
+        let mut first = edges.clone();
+        assert_eq!(dedup_edges(&mut first, EdgeDedup::First), 2);
+        assert_eq!(first.iter().find(|e| e.src_id == "A" && e.dst_id == "B").unwrap().gen_id, "e1");
+
+        assert_eq!(dedup_edges(&mut edges, EdgeDedup::Min), 2);
+        assert_eq!(edges.len(), 4);
+        quicksort_edges(&mut edges);
+        let forest = minimum_spanning_forest(&edges);
+        assert_eq!(forest.trees, 2);
+        assert_eq!(forest.edges.len(), 3);
+        assert_eq!(forest.total_weight, 4.0);
+        let ids: Vec<&str> = forest.edges.iter().map(|e| e.gen_id.as_str()).collect();
+        assert!(ids.contains(&"e2") && ids.contains(&"e3") && ids.contains(&"e6"));
+    }
+}
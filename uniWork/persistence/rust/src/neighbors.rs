@@ -0,0 +1,216 @@
+//! neighbors.rs — Singine neighbourhood tree
+//!
+//! Breadth-first expansion from a root node up to `depth` hops, emitted as a
+//! nested tree (root → children → grandchildren …) for direct UI consumption.
+//! Each node appears once, at the shallowest depth it is reached; the total
+//! node count is capped so dense hubs cannot blow up the response.
+//!
+//! `distance_bands` complements the tree with a weighted reach profile:
+//! reachable nodes grouped into bands [0,W), [W,2W), … of Dijkstra distance.
+//!
+//! `alternatives` lists every first hop out of a path's source with the best
+//! total cost to the destination when routed through it (`--with-alternatives`).
+
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use crate::shortest_path::{distances_from, reversed, Adjacency};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NeighborTree {
+    pub id:       String,
+    /// weight of the edge from the parent (0 for the root)
+    pub weight:   f64,
+    pub children: Vec<NeighborTree>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Neighborhood {
+    pub root:      NeighborTree,
+    pub depth:     usize,
+    pub nodes:     usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DistanceBand {
+    pub band:  usize,
+    pub from:  f64,
+    pub to:    f64,
+    pub count: usize,
+    pub nodes: Vec<String>,
+}
+
+// Flat BFS record; children are indices into the arena.
+struct Slot {
+    id:       String,
+    weight:   f64,
+    children: Vec<usize>,
+}
+
+pub fn neighborhood(adj: &Adjacency, src: &str, depth: usize, max_nodes: usize) -> Neighborhood {
+    let mut arena = vec![Slot { id: src.to_string(), weight: 0.0, children: Vec::new() }];
+    let mut seen: HashSet<&str> = HashSet::from([src]);
+    let mut queue: VecDeque<(usize, usize)> = VecDeque::from([(0, 0)]);
+    let mut truncated = false;
+
+    'bfs: while let Some((slot, d)) = queue.pop_front() {
+        if d >= depth {
+            continue;
+        }
+        let Some(neighbours) = adj.get(&arena[slot].id) else { continue };
+        for (next, w) in neighbours {
+            if seen.contains(next.as_str()) {
+                continue;
+            }
+            if arena.len() >= max_nodes {
+                truncated = true;
+                break 'bfs;
+            }
+            seen.insert(next);
+            let child = arena.len();
+            arena.push(Slot { id: next.clone(), weight: *w, children: Vec::new() });
+            arena[slot].children.push(child);
+            queue.push_back((child, d + 1));
+        }
+    }
+
+    if truncated {
+        tracing::warn!(max_nodes, "Neighbourhood truncated at node cap");
+    }
+    let nodes = arena.len();
+    Neighborhood { root: assemble(&arena, 0), depth, nodes, truncated }
+}
+
+fn assemble(arena: &[Slot], i: usize) -> NeighborTree {
+    NeighborTree {
+        id:       arena[i].id.clone(),
+        weight:   arena[i].weight,
+        children: arena[i].children.iter().map(|&c| assemble(arena, c)).collect(),
+    }
+}
+
+/// Group every node reachable from `src` by distance band of width `width`.
+pub fn distance_bands(adj: &Adjacency, src: &str, width: f64) -> Vec<DistanceBand> {
+    let mut bands: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (node, d) in distances_from(adj, src) {
+        bands.entry((d / width).floor() as usize).or_default().push(node);
+    }
+    bands
+        .into_iter()
+        .map(|(band, mut nodes)| {
+            nodes.sort();
+            DistanceBand {
+                band,
+                from: band as f64 * width,
+                to: (band + 1) as f64 * width,
+                count: nodes.len(),
+                nodes,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alternative {
+    pub via:          String,
+    pub edge_weight:  f64,
+    /// edge_weight + best distance from `via` to dst; `None` if dst is unreachable from it
+    pub total_weight: Option<f64>,
+}
+
+/// Direct neighbours of `src`, cheapest route to `dst` first (unreachable last).
+pub fn alternatives(adj: &Adjacency, src: &str, dst: &str) -> Vec<Alternative> {
+    let to_dst = distances_from(&reversed(adj), dst);
+    let mut alts: Vec<Alternative> = adj
+        .get(src)
+        .into_iter()
+        .flatten()
+        .map(|(via, w)| Alternative {
+            via:          via.clone(),
+            edge_weight:  *w,
+            total_weight: to_dst.get(via).map(|d| w + d),
+        })
+        .collect();
+    alts.sort_by(|a, b| match (a.total_weight, b.total_weight) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal).then_with(|| a.via.cmp(&b.via)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.via.cmp(&b.via),
+    });
+    alts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, edge};
+
+    #[test]
+    fn test_depth_one_neighbors() {
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("A", "C", 2.0).with_id("e2"),
+            edge("C", "D", 1.0).with_id("e3"),
+        ]);
+        let n = neighborhood(&adj, "A", 1, 100);
+        assert_eq!(n.root.id, "A");
+        let ids: Vec<_> = n.root.children.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["B", "C"]);
+        assert_eq!(n.root.children[1].weight, 2.0);
+        assert!(n.root.children.iter().all(|c| c.children.is_empty()));
+        assert_eq!(n.nodes, 3);
+        assert!(!n.truncated);
+    }
+
+    #[test]
+    fn test_node_cap_truncates() {
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("A", "C", 2.0).with_id("e2"),
+            edge("C", "D", 1.0).with_id("e3"),
+        ]);
+        let n = neighborhood(&adj, "A", 5, 2);
+        assert_eq!(n.nodes, 2);
+        assert!(n.truncated);
+    }
+
+    #[test]
+    fn test_alternatives_include_non_optimal_first_hop() {
+        // A-B-D costs 2, A-C-D costs 5; E is a dead end off A
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("B", "D", 1.0).with_id("e2"),
+            edge("A", "C", 1.0).with_id("e3"),
+            edge("C", "D", 4.0).with_id("e4"),
+            edge("A", "E", 0.5).with_id("e5"),
+        ]);
+        let alts = alternatives(&adj, "A", "D");
+        let summary: Vec<_> = alts.iter().map(|a| (a.via.as_str(), a.total_weight)).collect();
+        // E and C route back through A: 0.5 + 0.5 + 2, and 1 + min(4, 1 + 2)
+        assert_eq!(summary, vec![("B", Some(2.0)), ("E", Some(3.0)), ("C", Some(4.0))]);
+        assert_eq!(alts[2].edge_weight, 1.0);
+    }
+
+    #[test]
+    fn test_distance_bands_on_line() {
+        // A -1- B -1- C -2- D -3- E : distances 0, 1, 2, 4, 7
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("B", "C", 1.0).with_id("e2"),
+            edge("C", "D", 2.0).with_id("e3"),
+            edge("D", "E", 3.0).with_id("e4"),
+        ]);
+        let bands = distance_bands(&adj, "A", 2.0);
+        let summary: Vec<_> = bands.iter().map(|b| (b.band, b.nodes.clone())).collect();
+        assert_eq!(summary, vec![
+            (0, vec!["A".to_string(), "B".to_string()]),
+            (1, vec!["C".to_string()]),
+            (2, vec!["D".to_string()]),
+            (3, vec!["E".to_string()]),
+        ]);
+        assert_eq!(bands[1].from, 2.0);
+        assert_eq!(bands[1].to, 4.0);
+    }
+}
@@ -0,0 +1,375 @@
+//! output.rs — Singine result rendering
+//!
+//! Every mode builds one `serde_json::Value`; this module turns it into the
+//! text printed on stdout. `--extract <pointer>` selects a single value by
+//! RFC 6901 JSON pointer so shell scripts can skip `jq`.
+//!
+//! `--template file.json` reshapes a path result: every `{{field}}` inside a
+//! string of the template is replaced from the result. A string that is just
+//! one placeholder takes the field's JSON value (numbers stay numbers, `path`
+//! stays an array); placeholders inside longer strings are spliced in as text.
+//!
+//! Output files are gzip-compressed with `--gzip` or when the path ends in
+//! `.gz`. With `--normalize-output-paths` the output path is made absolute
+//! up front, missing parent directories are created, and results report the
+//! file they were written to as `output_path`.
+//!
+//! `--weight-precision D` rounds reported weights to D decimal places; the
+//! search itself always runs at full precision.
+//!
+//! `--field-map src=source,dst=target` renames top-level result keys for
+//! integrations that expect their own names; every mapped-from key must be
+//! present in the result.
+//!
+//! `--weight-as duration` reads weights as seconds (e.g. lineage latency) and
+//! adds a human-readable `total_duration` such as `1h23m` beside every
+//! `total_weight`; the raw number is kept.
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde_json::{Map, Value};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Placeholders a template may use.
+pub const TEMPLATE_FIELDS: [&str; 5] = ["src", "dst", "total_weight", "hops", "path"];
+
+/// Render the value at `pointer`: strings bare, other scalars as JSON,
+/// objects/arrays pretty-printed.
+pub fn extract(out: &Value, pointer: &str) -> anyhow::Result<String> {
+    let v = out
+        .pointer(pointer)
+        .ok_or_else(|| anyhow::anyhow!("JSON pointer '{}' not found in output", pointer))?;
+    Ok(match v {
+        Value::String(s) => s.clone(),
+        Value::Array(_) | Value::Object(_) => serde_json::to_string_pretty(v)?,
+        scalar => scalar.to_string(),
+    })
+}
+
+/// Gzip wanted for `path`: forced by `--gzip`, or implied by a `.gz` suffix.
+pub fn wants_gzip(path: &Path, gzip: bool) -> bool {
+    gzip || path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// Write `text` to `path`, gzip-compressed when `wants_gzip`.
+pub fn write_file(path: &Path, text: &str, gzip: bool) -> std::io::Result<()> {
+    if !wants_gzip(path, gzip) {
+        return std::fs::write(path, text);
+    }
+    let mut encoder = GzEncoder::new(std::fs::File::create(path)?, Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Absolute, symlink-free form of `path`, creating its parent directories.
+/// Only the parent is canonicalized: the file itself need not exist yet.
+pub fn normalize_path(path: &Path) -> std::io::Result<PathBuf> {
+    let file = path.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("'{}' names no file", path.display()))
+    })?;
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)?;
+    Ok(parent.canonicalize()?.join(file))
+}
+
+/// Contents of `path` as text, transparently gunzipped when it starts with
+/// the gzip magic bytes.
+pub fn read_file(path: &Path) -> std::io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+    }
+    let mut text = String::new();
+    GzDecoder::new(bytes.as_slice()).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// `x` rounded half away from zero to `places` decimals.
+pub fn round_to(x: f64, places: u32) -> f64 {
+    let scale = 10f64.powi(places as i32);
+    (x * scale).round() / scale
+}
+
+/// Round every number under a key ending in `weight` (total_weight,
+/// edge_weight, per-hop weight …), at any depth.
+pub fn round_weights(v: &mut Value, places: u32) {
+    match v {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                match field.as_f64() {
+                    Some(x) if key.ends_with("weight") => *field = Value::from(round_to(x, places)),
+                    _ => round_weights(field, places),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| round_weights(item, places)),
+        _ => {}
+    }
+}
+
+/// One `from=to` entry of `--field-map`.
+pub fn parse_field_rename(entry: &str) -> Result<(String, String), String> {
+    match entry.split_once('=') {
+        Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => {
+            Ok((from.trim().to_string(), to.trim().to_string()))
+        }
+        _ => Err(format!("invalid field mapping '{}' (expected from=to)", entry)),
+    }
+}
+
+/// Rename the top-level keys of `out` per `map`, in order.
+pub fn rename_fields(mut out: Value, map: &[(String, String)]) -> anyhow::Result<Value> {
+    if map.is_empty() {
+        return Ok(out);
+    }
+    let Some(obj) = out.as_object_mut() else {
+        anyhow::bail!("--field-map needs a JSON object result");
+    };
+    for (from, to) in map {
+        let Some(value) = obj.remove(from) else {
+            let fields: Vec<&str> = obj.keys().map(String::as_str).collect();
+            anyhow::bail!("--field-map: result has no field '{}' (fields: {})", from, fields.join(", "));
+        };
+        obj.insert(to.clone(), value);
+    }
+    Ok(out)
+}
+
+/// How reported weights are interpreted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WeightAs {
+    /// plain numbers
+    #[default]
+    Number,
+    /// seconds, also shown as a duration
+    Duration,
+}
+
+/// `seconds` as its two largest non-zero units of d/h/m/s: `1h23m`, `2d4h`,
+/// `45s`. Under a second it is shown in milliseconds.
+pub fn format_duration(seconds: f64) -> String {
+    if seconds < 0.0 {
+        return format!("-{}", format_duration(-seconds));
+    }
+    if seconds < 1.0 {
+        return format!("{}ms", (seconds * 1000.0).round());
+    }
+    let total = seconds.round() as u64;
+    let units = [(total / 86_400, "d"), (total / 3_600 % 24, "h"), (total / 60 % 60, "m"), (total % 60, "s")];
+    let first = units.iter().position(|&(n, _)| n > 0).unwrap_or(3);
+    units[first..]
+        .iter()
+        .take(2)
+        .filter(|&&(n, _)| n > 0)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect()
+}
+
+/// Add `total_duration` beside every numeric `total_weight`, at any depth.
+pub fn add_durations(v: &mut Value) {
+    match v {
+        Value::Object(map) => {
+            map.values_mut().for_each(add_durations);
+            if let Some(seconds) = map.get("total_weight").and_then(Value::as_f64) {
+                map.insert("total_duration".to_string(), Value::from(format_duration(seconds)));
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(add_durations),
+        _ => {}
+    }
+}
+
+/// `{{name}}` placeholders in `text`, in order, as (raw token, trimmed name).
+fn placeholders(text: &str) -> Vec<(&str, &str)> {
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        let end = start + 2 + len + 2;
+        found.push((&rest[start..end], rest[start + 2..start + 2 + len].trim()));
+        rest = &rest[end..];
+    }
+    found
+}
+
+fn walk_strings<'a>(v: &'a Value, f: &mut impl FnMut(&'a str)) {
+    match v {
+        Value::String(s) => f(s),
+        Value::Array(items) => items.iter().for_each(|i| walk_strings(i, f)),
+        Value::Object(obj) => obj.values().for_each(|i| walk_strings(i, f)),
+        _ => {}
+    }
+}
+
+/// Parse a template, rejecting placeholders outside `TEMPLATE_FIELDS`.
+pub fn parse_template(text: &str) -> anyhow::Result<Value> {
+    let template: Value = serde_json::from_str(text)?;
+    let mut unknown: Vec<String> = Vec::new();
+    walk_strings(&template, &mut |s| {
+        for (_, name) in placeholders(s) {
+            if !TEMPLATE_FIELDS.contains(&name) && !unknown.iter().any(|u| u == name) {
+                unknown.push(name.to_string());
+            }
+        }
+    });
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "unknown template placeholder(s): {} (known: {})",
+            unknown.join(", "),
+            TEMPLATE_FIELDS.join(", ")
+        );
+    }
+    Ok(template)
+}
+
+/// Substitute `fields` into a template checked by `parse_template`.
+pub fn render_template(template: &Value, fields: &Map<String, Value>) -> Value {
+    match template {
+        Value::String(s) => {
+            let found = placeholders(s);
+            if let [(token, name)] = found[..] {
+                if s.trim() == token {
+                    return fields.get(name).cloned().unwrap_or(Value::Null);
+                }
+            }
+            let mut text = s.clone();
+            for (token, name) in found {
+                let value = match fields.get(name) {
+                    Some(Value::String(v)) => v.clone(),
+                    Some(v) => v.to_string(),
+                    None => String::new(),
+                };
+                text = text.replacen(token, &value, 1);
+            }
+            Value::String(text)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|i| render_template(i, fields)).collect()),
+        Value::Object(obj) => {
+            Value::Object(obj.iter().map(|(k, v)| (k.clone(), render_template(v, fields))).collect())
+        }
+        scalar => scalar.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_total_weight_scalar() {
+        let out = json!({"ok": true, "src": "A", "path": ["A", "B"], "total_weight": 3.0});
+        assert_eq!(extract(&out, "/total_weight").unwrap(), "3.0");
+        assert_eq!(extract(&out, "/src").unwrap(), "A");
+        assert_eq!(extract(&out, "/path/1").unwrap(), "B");
+    }
+
+    #[test]
+    fn test_gzip_output_round_trips() {
+        let out = json!({"ok": true, "path": ["A", "B", "C"], "total_weight": 3.0});
+        let text = serde_json::to_string_pretty(&out).unwrap();
+        let path = std::env::temp_dir().join(format!("out-{}.json.gz", std::process::id()));
+        write_file(&path, &text, false).unwrap();
+        let raw = std::fs::read(&path).unwrap();
+        assert_eq!(&raw[..2], &[0x1f, 0x8b], ".gz suffix implies gzip");
+        let back: Value = serde_json::from_str(&read_file(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(back, out);
+
+        let plain = std::env::temp_dir().join(format!("out-{}.json", std::process::id()));
+        write_file(&plain, &text, false).unwrap();
+        assert_eq!(std::fs::read_to_string(&plain).unwrap(), text);
+        std::fs::remove_file(&plain).ok();
+    }
+
+    #[test]
+    fn test_normalize_path_creates_nested_dirs() {
+        let base = std::env::temp_dir().join(format!("normalize-{}", std::process::id()));
+        let requested = base.join("nested").join(".").join("deeper").join("out.json");
+        let path = normalize_path(&requested).unwrap();
+        assert!(path.is_absolute());
+        assert_eq!(path, base.canonicalize().unwrap().join("nested/deeper/out.json"));
+        assert!(path.parent().unwrap().is_dir());
+        write_file(&path, "{}", false).unwrap();
+        assert_eq!(std::fs::read_to_string(&requested).unwrap(), "{}");
+        std::fs::remove_dir_all(&base).ok();
+        assert!(normalize_path(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn test_field_map_renames_keys() {
+        let out = json!({"ok": true, "src": "A", "dst": "C", "total_weight": 3.0});
+        let map: Vec<_> = ["src=source", "dst=target"].iter().map(|e| parse_field_rename(e).unwrap()).collect();
+        let renamed = rename_fields(out.clone(), &map).unwrap();
+        assert_eq!(renamed["source"], "A");
+        assert_eq!(renamed["target"], "C");
+        assert!(renamed.get("src").is_none() && renamed.get("dst").is_none());
+        assert_eq!(renamed["total_weight"], 3.0);
+
+        let missing = vec![("hops".to_string(), "length".to_string())];
+        let err = rename_fields(out, &missing).unwrap_err().to_string();
+        assert!(err.contains("no field 'hops'"), "{}", err);
+        assert!(parse_field_rename("src").is_err());
+    }
+
+    #[test]
+    fn test_duration_formatting() {
+        assert_eq!(format_duration(4980.0), "1h23m");
+        assert_eq!(format_duration(5000.0), "1h23m", "seconds beyond two units are dropped");
+        assert_eq!(format_duration(3600.0), "1h");
+        assert_eq!(format_duration(45.4), "45s");
+        assert_eq!(format_duration(187_200.0), "2d4h");
+        assert_eq!(format_duration(0.25), "250ms");
+
+        let mut out = json!({"ok": true, "total_weight": 4980.0, "legs": [{"total_weight": 61.0}]});
+        add_durations(&mut out);
+        assert_eq!(out["total_duration"], "1h23m");
+        assert_eq!(out["total_weight"], 4980.0);
+        assert_eq!(out["legs"][0]["total_duration"], "1m1s");
+    }
+
+    #[test]
+    fn test_weight_precision_rounds_reported_weights() {
+        let mut out = json!({
+            "total_weight": 3.0000000004, "hops": 2, "src": "A",
+            "alternatives": [{"via": "B", "edge_weight": 1.23456, "total_weight": 2.4567}],
+        });
+        round_weights(&mut out, 2);
+        assert_eq!(out["total_weight"], json!(3.0));
+        assert_eq!(out["alternatives"][0]["edge_weight"], json!(1.23));
+        assert_eq!(out["alternatives"][0]["total_weight"], json!(2.46));
+        assert_eq!(out["hops"], json!(2));
+        assert_eq!(round_to(1.23456, 3), 1.235);
+    }
+
+    #[test]
+    fn test_template_substitutes_fields() {
+        let template = parse_template(
+            r#"{"route": {"from": "{{src}}", "to": "{{ dst }}", "nodes": "{{path}}"},
+                "cost": "{{total_weight}}", "summary": "{{src}} -> {{dst}} in {{hops}} hops"}"#,
+        )
+        .unwrap();
+        let fields = json!({"src": "A", "dst": "C", "total_weight": 3.0, "hops": 2,
+                            "path": ["A", "B", "C"]});
+        let out = render_template(&template, fields.as_object().unwrap());
+        assert_eq!(out, json!({
+            "route": {"from": "A", "to": "C", "nodes": ["A", "B", "C"]},
+            "cost": 3.0,
+            "summary": "A -> C in 2 hops",
+        }));
+    }
+
+    #[test]
+    fn test_template_rejects_unknown_placeholder() {
+        let err = parse_template(r#"{"w": "{{weight}}"}"#).unwrap_err();
+        assert!(err.to_string().contains("weight"));
+    }
+
+    #[test]
+    fn test_extract_missing_pointer_errors() {
+        let out = json!({"ok": true});
+        let err = extract(&out, "/total_weight").unwrap_err();
+        assert!(err.to_string().contains("/total_weight"));
+    }
+}
@@ -0,0 +1,190 @@
+//! pairing_heap.rs — Singine pairing-heap Dijkstra (feature `pairing-heap`)
+//!
+//! `--heap pairing` swaps the BinaryHeap of cloned states for an arena-backed
+//! pairing heap with decrease-key: each node is queued once and re-keyed in
+//! place when a shorter route is found, so there are no stale entries and no
+//! per-push path clones (the path is rebuilt from parent links at the end).
+//! Kept for benchmarking against the default; results are identical.
+
+use std::collections::HashMap;
+
+use crate::shortest_path::{Adjacency, PathResult, SearchTooLarge};
+
+struct HeapNode {
+    key:     f64,
+    item:    usize,
+    child:   Option<usize>,
+    sibling: Option<usize>,
+    /// left sibling, or the parent for a first child
+    prev:    Option<usize>,
+}
+
+/// Min-heap of `(key, item)`; `push` returns a handle for `decrease_key`.
+#[derive(Default)]
+pub struct PairingHeap {
+    nodes: Vec<HeapNode>,
+    root:  Option<usize>,
+}
+
+impl PairingHeap {
+    pub fn push(&mut self, key: f64, item: usize) -> usize {
+        let h = self.nodes.len();
+        self.nodes.push(HeapNode { key, item, child: None, sibling: None, prev: None });
+        self.root = Some(match self.root {
+            Some(r) => self.meld(r, h),
+            None => h,
+        });
+        h
+    }
+
+    pub fn pop(&mut self) -> Option<(f64, usize)> {
+        let r = self.root?;
+        let mut children = Vec::new();
+        let mut c = self.nodes[r].child.take();
+        while let Some(i) = c {
+            c = self.nodes[i].sibling.take();
+            self.nodes[i].prev = None;
+            children.push(i);
+        }
+        // two-pass pairing: meld neighbours left to right, then fold right to left
+        let paired: Vec<usize> = children
+            .chunks(2)
+            .map(|pair| if let [a, b] = *pair { self.meld(a, b) } else { pair[0] })
+            .collect();
+        self.root = paired.into_iter().rev().reduce(|acc, t| self.meld(t, acc));
+        Some((self.nodes[r].key, self.nodes[r].item))
+    }
+
+    /// Lower the key of a queued entry; larger keys are ignored.
+    pub fn decrease_key(&mut self, h: usize, key: f64) {
+        if key >= self.nodes[h].key {
+            return;
+        }
+        self.nodes[h].key = key;
+        if self.root == Some(h) {
+            return;
+        }
+        // cut h (with its subtree) out of its sibling list, then meld it back in
+        let (prev, sibling) = (self.nodes[h].prev.take(), self.nodes[h].sibling.take());
+        if let Some(p) = prev {
+            if self.nodes[p].child == Some(h) {
+                self.nodes[p].child = sibling;
+            } else {
+                self.nodes[p].sibling = sibling;
+            }
+        }
+        if let Some(s) = sibling {
+            self.nodes[s].prev = prev;
+        }
+        let r = self.root.expect("h is queued, so the heap is not empty");
+        self.root = Some(self.meld(r, h));
+    }
+
+    /// Link two roots; the larger key becomes the first child of the smaller.
+    fn meld(&mut self, a: usize, b: usize) -> usize {
+        let (parent, child) = if self.nodes[b].key < self.nodes[a].key { (b, a) } else { (a, b) };
+        let first = self.nodes[parent].child;
+        self.nodes[child].sibling = first;
+        if let Some(f) = first {
+            self.nodes[f].prev = Some(child);
+        }
+        self.nodes[child].prev = Some(parent);
+        self.nodes[parent].child = Some(child);
+        parent
+    }
+}
+
+/// Dijkstra over a pairing heap with decrease-key.
+pub fn dijkstra(
+    adj: &Adjacency,
+    src: &str,
+    dst: &str,
+    max_open: Option<usize>,
+) -> Result<Option<PathResult>, SearchTooLarge> {
+    let mut index: HashMap<&str, usize> = HashMap::from([(src, 0)]);
+    let mut names: Vec<&str> = vec![src];
+    let mut dist: Vec<f64> = vec![0.0];
+    let mut parent: Vec<Option<usize>> = vec![None];
+    let mut handle: Vec<Option<usize>> = vec![None];
+    let mut done: Vec<bool> = vec![false];
+    let mut heap = PairingHeap::default();
+    handle[0] = Some(heap.push(0.0, 0));
+    let mut open = 1;
+
+    while let Some((cost, u)) = heap.pop() {
+        open -= 1;
+        done[u] = true;
+        if names[u] == dst {
+            let mut path = vec![dst.to_string()];
+            let mut at = u;
+            while let Some(p) = parent[at] {
+                path.push(names[p].to_string());
+                at = p;
+            }
+            path.reverse();
+            return Ok(Some(PathResult {
+                src_id:         src.to_string(),
+                dst_id:         dst.to_string(),
+                path,
+                total_weight:   cost,
+                algorithm:      "dijkstra+quicksort".to_string(),
+                weight_by_type: HashMap::new(),
+            }));
+        }
+        for (next, w) in adj.get(names[u]).into_iter().flatten() {
+            let v = *index.entry(next.as_str()).or_insert_with(|| {
+                names.push(next.as_str());
+                dist.push(f64::INFINITY);
+                parent.push(None);
+                handle.push(None);
+                done.push(false);
+                names.len() - 1
+            });
+            let next_cost = cost + w;
+            if done[v] || next_cost >= dist[v] {
+                continue;
+            }
+            dist[v] = next_cost;
+            parent[v] = Some(u);
+            match handle[v] {
+                Some(h) => heap.decrease_key(h, next_cost),
+                None => {
+                    handle[v] = Some(heap.push(next_cost, v));
+                    open += 1;
+                    if let Some(limit) = max_open.filter(|&limit| open > limit) {
+                        return Err(SearchTooLarge { limit });
+                    }
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{self, build_adjacency, edge};
+
+    #[test]
+    fn test_pairing_matches_binary_heap_on_triangle() {
+        let adj = build_adjacency(&[edge("A", "B", 1.0), edge("B", "C", 2.0), edge("A", "C", 10.0)]);
+        for (s, d) in [("A", "C"), ("C", "A"), ("B", "A"), ("A", "A")] {
+            let binary = shortest_path::dijkstra(&adj, s, d).unwrap();
+            let pairing = dijkstra(&adj, s, d, None).unwrap().unwrap();
+            assert_eq!(pairing.path, binary.path, "{} -> {}", s, d);
+            assert_eq!(pairing.total_weight, binary.total_weight);
+        }
+        assert!(dijkstra(&adj, "A", "Z", None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_decrease_key_reorders() {
+        let mut heap = PairingHeap::default();
+        let handles: Vec<usize> = (0..6).map(|i| heap.push(10.0 + i as f64, i)).collect();
+        heap.decrease_key(handles[4], 1.0);
+        heap.decrease_key(handles[2], 5.0);
+        let order: Vec<usize> = std::iter::from_fn(|| heap.pop().map(|(_, item)| item)).collect();
+        assert_eq!(order, vec![4, 2, 0, 1, 3, 5]);
+    }
+}
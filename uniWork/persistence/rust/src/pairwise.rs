@@ -0,0 +1,103 @@
+//! pairwise.rs — Singine shortest-path matrix for a focus set
+//!
+//! `--mode pairwise --nodes nodes.txt` runs one single-source Dijkstra per
+//! listed node and keeps only the distances to the other listed nodes —
+//! k searches instead of all-pairs over the whole graph. Unreachable pairs
+//! are `null`.
+//!
+//! `--mode centroid` reuses the same k searches to pick the focus set's
+//! medoid: the listed node with the smallest sum of distances to all the
+//! others. Nodes that cannot reach every other listed node are not eligible.
+
+use serde::Serialize;
+use std::path::Path;
+
+use crate::shortest_path::{distances_from, Adjacency};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairwiseMatrix {
+    pub nodes:  Vec<String>,
+    /// `matrix[i][j]` = shortest distance from nodes[i] to nodes[j]
+    pub matrix: Vec<Vec<Option<f64>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Centroid {
+    pub node:           String,
+    pub total_distance: f64,
+}
+
+/// One node id per line; blank lines and `#` comments skipped, duplicates dropped.
+pub fn parse_nodes(text: &str) -> Vec<String> {
+    let mut nodes: Vec<String> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if !line.is_empty() && !line.starts_with('#') && !nodes.iter().any(|n| n == line) {
+            nodes.push(line.to_string());
+        }
+    }
+    nodes
+}
+
+pub fn read_nodes(path: &Path) -> std::io::Result<Vec<String>> {
+    Ok(parse_nodes(&std::fs::read_to_string(path)?))
+}
+
+pub fn pairwise_matrix(adj: &Adjacency, nodes: &[String]) -> PairwiseMatrix {
+    let matrix = nodes
+        .iter()
+        .map(|src| {
+            let dist = distances_from(adj, src);
+            nodes.iter().map(|dst| dist.get(dst).copied()).collect()
+        })
+        .collect();
+    PairwiseMatrix { nodes: nodes.to_vec(), matrix }
+}
+
+/// Medoid of `nodes`; ties go to the node listed first. `None` when no
+/// listed node reaches all the others.
+pub fn centroid(adj: &Adjacency, nodes: &[String]) -> Option<Centroid> {
+    let m = pairwise_matrix(adj, nodes);
+    let mut best: Option<Centroid> = None;
+    for (node, row) in m.nodes.into_iter().zip(&m.matrix) {
+        let Some(total) = row.iter().copied().sum::<Option<f64>>() else { continue };
+        if best.as_ref().is_none_or(|b| total < b.total_distance) {
+            best = Some(Centroid { node, total_distance: total });
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, edge};
+
+    #[test]
+    fn test_three_node_matrix_is_symmetric() {
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0), edge("B", "C", 2.0), edge("C", "D", 1.0), edge("A", "D", 5.0),
+        ]);
+        let nodes = parse_nodes("# focus\nA\nC\nD\nA\n");
+        let m = pairwise_matrix(&adj, &nodes);
+        assert_eq!(m.nodes, vec!["A", "C", "D"]);
+        assert_eq!(m.matrix, vec![
+            vec![Some(0.0), Some(3.0), Some(4.0)],
+            vec![Some(3.0), Some(0.0), Some(1.0)],
+            vec![Some(4.0), Some(1.0), Some(0.0)],
+        ]);
+    }
+
+    #[test]
+    fn test_centroid_is_the_medoid() {
+        // line A -1- B -1- C -3- D, plus a separate E-F component
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0), edge("B", "C", 1.0), edge("C", "D", 3.0), edge("E", "F", 1.0),
+        ]);
+        let nodes = parse_nodes("A\nB\nC\nD\n");
+        let c = centroid(&adj, &nodes).unwrap();
+        // B: 1 + 1 + 4 = 6, C: 2 + 1 + 3 = 6, A: 1 + 2 + 5 = 8 — tie goes to B
+        assert_eq!(c.node, "B");
+        assert_eq!(c.total_distance, 6.0);
+        assert!(centroid(&adj, &parse_nodes("A\nE\n")).is_none());
+    }
+}
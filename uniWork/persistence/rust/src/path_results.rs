@@ -0,0 +1,180 @@
+//! path_results.rs — Singine stored-path queries
+//!
+//! Read side of the `path_results` table written by `persist_path`:
+//!   list-paths   — stored rows, newest first
+//!   report-paths — aggregate summary (counts, weight range, per algorithm)
+//!
+//! Both accept `--since <rfc3339>`, compared against `computed_at`.
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Result as SqlResult};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredPath {
+    pub gen_id:       String,
+    pub src_id:       String,
+    pub dst_id:       String,
+    /// nodes as stored; only head and tail when persisted with --max-path-store
+    pub path:         Vec<String>,
+    /// nodes left out of `path` by truncation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub omitted:      Option<usize>,
+    pub total_weight: f64,
+    pub algorithm:    String,
+    pub computed_at:  String,
+    pub run_id:       Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathReport {
+    pub paths:          u64,
+    pub distinct_pairs: u64,
+    pub min_weight:     Option<f64>,
+    pub max_weight:     Option<f64>,
+    pub mean_weight:    Option<f64>,
+    pub by_algorithm:   BTreeMap<String, u64>,
+}
+
+/// Parse an RFC 3339 timestamp into the `computed_at` storage format (UTC, seconds).
+pub fn parse_since(since: &str) -> anyhow::Result<String> {
+    parse_timestamp("--since", since)
+}
+
+/// Parse an RFC 3339 `value` given for `flag` into the storage format shared
+/// by every `*_at` / `valid_*` column: `%Y-%m-%dT%H:%M:%SZ` in UTC.
+pub fn parse_timestamp(flag: &str, value: &str) -> anyhow::Result<String> {
+    let ts = DateTime::parse_from_rfc3339(value)
+        .map_err(|e| anyhow::anyhow!("invalid {} '{}': {} (expected RFC 3339)", flag, value, e))?;
+    Ok(ts.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%SZ").to_string())
+}
+
+/// Node list of a stored path, either a plain array or a truncated
+/// `{head, tail, omitted}` record (head and tail are concatenated).
+fn parse_path_json(json: &str) -> (Vec<String>, Option<usize>) {
+    #[derive(serde::Deserialize)]
+    struct Truncated {
+        head:    Vec<String>,
+        tail:    Vec<String>,
+        omitted: usize,
+    }
+    if let Ok(path) = serde_json::from_str::<Vec<String>>(json) {
+        return (path, None);
+    }
+    match serde_json::from_str::<Truncated>(json) {
+        Ok(t) => ([t.head, t.tail].concat(), Some(t.omitted)),
+        Err(_) => (Vec::new(), None),
+    }
+}
+
+fn since_clause(since: Option<&str>) -> &'static str {
+    if since.is_some() { " WHERE computed_at >= ?1" } else { "" }
+}
+
+pub fn list_paths(conn: &Connection, since: Option<&str>) -> SqlResult<Vec<StoredPath>> {
+    let sql = format!(
+        "SELECT gen_id,src_id,dst_id,path_json,total_weight,algorithm,computed_at,run_id \
+         FROM path_results{} ORDER BY computed_at DESC, gen_id",
+        since_clause(since)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let map_row = |r: &rusqlite::Row<'_>| {
+        let path_json: String = r.get(3)?;
+        let (path, omitted) = parse_path_json(&path_json);
+        Ok(StoredPath {
+            gen_id:       r.get(0)?,
+            src_id:       r.get(1)?,
+            dst_id:       r.get(2)?,
+            path,
+            omitted,
+            total_weight: r.get(4)?,
+            algorithm:    r.get(5)?,
+            computed_at:  r.get(6)?,
+            run_id:       r.get(7)?,
+        })
+    };
+    let rows = match since {
+        Some(ts) => stmt.query_map([ts], map_row)?.collect::<SqlResult<Vec<_>>>()?,
+        None => stmt.query_map([], map_row)?.collect::<SqlResult<Vec<_>>>()?,
+    };
+    Ok(rows)
+}
+
+pub fn report_paths(conn: &Connection, since: Option<&str>) -> SqlResult<PathReport> {
+    let sql = format!(
+        "SELECT COUNT(*), COUNT(DISTINCT src_id || char(31) || dst_id),
+                MIN(total_weight), MAX(total_weight), AVG(total_weight)
+         FROM path_results{}",
+        since_clause(since)
+    );
+    let map_row = |r: &rusqlite::Row<'_>| {
+        Ok(PathReport {
+            paths:          r.get::<_, i64>(0)? as u64,
+            distinct_pairs: r.get::<_, i64>(1)? as u64,
+            min_weight:     r.get(2)?,
+            max_weight:     r.get(3)?,
+            mean_weight:    r.get(4)?,
+            by_algorithm:   BTreeMap::new(),
+        })
+    };
+    let mut report = match since {
+        Some(ts) => conn.query_row(&sql, [ts], map_row)?,
+        None => conn.query_row(&sql, [], map_row)?,
+    };
+
+    let sql = format!(
+        "SELECT algorithm, COUNT(*) FROM path_results{} GROUP BY algorithm",
+        since_clause(since)
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let map_row = |r: &rusqlite::Row<'_>| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)? as u64));
+    let rows = match since {
+        Some(ts) => stmt.query_map([ts], map_row)?.collect::<SqlResult<Vec<_>>>()?,
+        None => stmt.query_map([], map_row)?.collect::<SqlResult<Vec<_>>>()?,
+    };
+    report.by_algorithm = rows.into_iter().collect();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE path_results (
+               gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT, path_json TEXT,
+               total_weight REAL, algorithm TEXT, computed_at TEXT, run_id TEXT);
+             INSERT INTO path_results VALUES
+               ('p1','A','B','[\"A\",\"B\"]',1.0,'dijkstra+quicksort','2026-01-01T00:00:00Z',NULL),
+               ('p2','A','C','[\"A\",\"B\",\"C\"]',3.0,'dijkstra+quicksort','2026-03-01T12:00:00Z',NULL),
+               ('p3','B','C','[\"B\",\"C\"]',2.0,'dijkstra+quicksort','2026-06-01T00:00:00Z','r1');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_since_filters_older_rows() {
+        let conn = seeded();
+        let since = parse_since("2026-03-01T13:00:00+01:00").unwrap();
+        assert_eq!(since, "2026-03-01T12:00:00Z");
+        let rows = list_paths(&conn, Some(&since)).unwrap();
+        let ids: Vec<_> = rows.iter().map(|r| r.gen_id.as_str()).collect();
+        assert_eq!(ids, vec!["p3", "p2"]);
+        assert_eq!(rows[1].path, vec!["A", "B", "C"]);
+
+        let report = report_paths(&conn, Some(&since)).unwrap();
+        assert_eq!(report.paths, 2);
+        assert_eq!(report.mean_weight, Some(2.5));
+        assert_eq!(report_paths(&conn, None).unwrap().paths, 3);
+    }
+
+    #[test]
+    fn test_invalid_since_errors() {
+        let err = parse_since("last tuesday").unwrap_err();
+        assert!(err.to_string().contains("--since"));
+    }
+}
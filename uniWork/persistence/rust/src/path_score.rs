@@ -0,0 +1,190 @@
+//! path_score.rs — Singine path aggregation
+//!
+//! `--path-score` chooses how hop weights combine into a path's score, and
+//! the search minimises that score rather than the sum:
+//!
+//!   sum     — total weight (the default; plain Dijkstra)
+//!   max     — heaviest hop, i.e. the minimax / bottleneck path
+//!   product — product of hop weights
+//!
+//! Both alternatives keep Dijkstra exact because a path's score never drops
+//! as it grows — for `product` only while every weight is at least 1, so
+//! lighter edges are refused. To find the most probable chain of
+//! probabilities p, store 1/p: the smallest product of 1/p is the largest
+//! product of p.
+
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::shortest_path::{self, Adjacency, PathResult, SearchTooLarge};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathScore {
+    #[default]
+    Sum,
+    Max,
+    Product,
+}
+
+impl PathScore {
+    /// Score of the empty path.
+    pub fn identity(self) -> f64 {
+        match self {
+            PathScore::Sum | PathScore::Max => 0.0,
+            PathScore::Product => 1.0,
+        }
+    }
+
+    /// Score of a path extended by a hop (or joined to another path) of `w`.
+    pub fn combine(self, score: f64, w: f64) -> f64 {
+        match self {
+            PathScore::Sum => score + w,
+            PathScore::Max => score.max(w),
+            PathScore::Product => score * w,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PathScore::Sum => "sum",
+            PathScore::Max => "max",
+            PathScore::Product => "product",
+        }
+    }
+}
+
+/// Refuse a graph the score cannot be minimised over exactly.
+pub fn check(adj: &Adjacency, score: PathScore) -> anyhow::Result<()> {
+    if score != PathScore::Product {
+        return Ok(());
+    }
+    let lightest = shortest_path::arcs_once(adj).into_iter().min_by(|a, b| a.2.total_cmp(&b.2));
+    match lightest {
+        Some((src, dst, w)) if w < 1.0 => anyhow::bail!(
+            "--path-score product needs every weight >= 1 (lightest {}-{} = {}); store probabilities as 1/p",
+            src,
+            dst,
+            w
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Heap entry, min-first by score; ties go to the earlier-discovered node.
+#[derive(PartialEq)]
+struct Open {
+    score: f64,
+    at:    usize,
+}
+
+impl Eq for Open {}
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.score.total_cmp(&self.score).then_with(|| other.at.cmp(&self.at))
+    }
+}
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Dijkstra minimising `score` instead of the sum of weights.
+pub fn dijkstra(
+    adj: &Adjacency,
+    src: &str,
+    dst: &str,
+    score: PathScore,
+    max_open: Option<usize>,
+) -> Result<Option<PathResult>, SearchTooLarge> {
+    let mut index: HashMap<&str, usize> = HashMap::from([(src, 0)]);
+    let mut names: Vec<&str> = vec![src];
+    let mut best: Vec<f64> = vec![score.identity()];
+    let mut parent: Vec<Option<usize>> = vec![None];
+    let mut settled: Vec<bool> = vec![false];
+    let mut heap = BinaryHeap::from([Open { score: score.identity(), at: 0 }]);
+
+    while let Some(Open { score: cost, at: u }) = heap.pop() {
+        if settled[u] {
+            continue;
+        }
+        settled[u] = true;
+        if names[u] == dst {
+            let mut path = vec![dst.to_string()];
+            let mut at = u;
+            while let Some(p) = parent[at] {
+                path.push(names[p].to_string());
+                at = p;
+            }
+            path.reverse();
+            return Ok(Some(PathResult {
+                src_id:         src.to_string(),
+                dst_id:         dst.to_string(),
+                path,
+                total_weight:   cost,
+                algorithm:      format!("dijkstra+{}", score.name()),
+                weight_by_type: HashMap::new(),
+            }));
+        }
+        for (next, w) in adj.get(names[u]).into_iter().flatten() {
+            let v = *index.entry(next.as_str()).or_insert_with(|| {
+                names.push(next.as_str());
+                best.push(f64::INFINITY);
+                parent.push(None);
+                settled.push(false);
+                names.len() - 1
+            });
+            let next_cost = score.combine(cost, *w);
+            if settled[v] || next_cost >= best[v] {
+                continue;
+            }
+            best[v] = next_cost;
+            parent[v] = Some(u);
+            heap.push(Open { score: next_cost, at: v });
+            if let Some(limit) = max_open.filter(|&limit| heap.len() > limit) {
+                return Err(SearchTooLarge { limit });
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, edge, Edge};
+
+    #[test]
+    fn test_bottleneck_path_differs_from_sum_optimal() {
+        // A-X-D sums to 2.6 but crosses a 2.5 hop; A-B-C-D sums to 3 with no hop above 1
+        let adj = build_adjacency(&[
+            edge("A", "X", 2.5),
+            edge("X", "D", 0.1),
+            edge("A", "B", 1.0),
+            edge("B", "C", 1.0),
+            edge("C", "D", 1.0),
+        ]);
+        let sum = shortest_path::dijkstra(&adj, "A", "D").unwrap();
+        assert_eq!(sum.path, vec!["A", "X", "D"]);
+        let max = dijkstra(&adj, "A", "D", PathScore::Max, None).unwrap().unwrap();
+        assert_eq!(max.path, vec!["A", "B", "C", "D"]);
+        assert_eq!(max.total_weight, 1.0);
+        assert_eq!(max.algorithm, "dijkstra+max");
+
+        assert!(check(&adj, PathScore::Max).is_ok());
+        assert!(check(&adj, PathScore::Product).unwrap_err().to_string().contains("D-X = 0.1"));
+        // a one-way X→D is still checked though X sorts after D
+        let one_way = build_adjacency(&[Edge { directed: Some(true), ..edge("X", "D", 0.1) }, edge("A", "X", 1.0)]);
+        assert!(check(&one_way, PathScore::Product).unwrap_err().to_string().contains("X-D = 0.1"));
+
+        // A-C sums to 5.5 against 6, but 1 × 5 beats 5.5 as a product
+        let adj = build_adjacency(&[edge("A", "B", 1.0), edge("B", "C", 5.0), edge("A", "C", 5.5)]);
+        assert!(check(&adj, PathScore::Product).is_ok());
+        assert_eq!(shortest_path::dijkstra(&adj, "A", "C").unwrap().path, vec!["A", "C"]);
+        let product = dijkstra(&adj, "A", "C", PathScore::Product, None).unwrap().unwrap();
+        assert_eq!(product.path, vec!["A", "B", "C"]);
+        assert_eq!(product.total_weight, 5.0);
+    }
+}
@@ -0,0 +1,108 @@
+//! repl.rs — Singine interactive graph session
+//!
+//! `--mode repl` loads the graph once and answers one command per stdin line
+//! with one compact JSON line on stdout, until EOF:
+//!   path <src> <dst>   — shortest path (not persisted)
+//!   neighbors <node>   — neighbour tree, honouring --depth / --max-nodes
+//!   stats              — node and arc counts
+//! Blank lines are skipped; an unknown or malformed command answers
+//! `{"ok": false, "error": ...}` and the session continues.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+use crate::neighbors;
+use crate::shortest_path::{all_nodes, dijkstra, Adjacency};
+
+pub struct Session<'a> {
+    pub adj:       &'a Adjacency,
+    pub depth:     usize,
+    pub max_nodes: usize,
+}
+
+impl Session<'_> {
+    /// Answer a single command line.
+    pub fn execute(&self, line: &str) -> Value {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["path", src, dst] => match dijkstra(self.adj, src, dst) {
+                Some(r) => json!({
+                    "ok":           true,
+                    "src":          r.src_id,
+                    "dst":          r.dst_id,
+                    "path":         r.path,
+                    "total_weight": r.total_weight,
+                }),
+                None => json!({"ok": false, "src": src, "dst": dst, "error": "no path found"}),
+            },
+            ["neighbors", node] => {
+                let hood = neighbors::neighborhood(self.adj, node, self.depth, self.max_nodes);
+                json!({
+                    "ok":        true,
+                    "src":       node,
+                    "depth":     hood.depth,
+                    "nodes":     hood.nodes,
+                    "truncated": hood.truncated,
+                    "tree":      hood.root,
+                })
+            }
+            ["stats"] => json!({
+                "ok":    true,
+                "nodes": all_nodes(self.adj).len(),
+                "arcs":  self.adj.values().map(Vec::len).sum::<usize>(),
+            }),
+            _ => json!({
+                "ok": false,
+                "error": format!("unknown command '{}' (try: path A B | neighbors X | stats)", line.trim()),
+            }),
+        }
+    }
+
+    /// Read commands from `input` until EOF, writing one JSON line per command.
+    pub fn run(&self, input: impl BufRead, mut output: impl Write) -> std::io::Result<usize> {
+        let mut commands = 0;
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(output, "{}", self.execute(&line))?;
+            output.flush()?;
+            commands += 1;
+        }
+        Ok(commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, build_adjacency_with, edge, GraphOptions};
+
+    #[test]
+    fn test_piped_commands_answer_one_line_each() {
+        let adj = build_adjacency(&[edge("A", "B", 1.0), edge("B", "C", 2.0), edge("A", "C", 5.0)]);
+        let session = Session { adj: &adj, depth: 1, max_nodes: 100 };
+        let input = "path A C\n\nneighbors B\nstats\nfrobnicate\n";
+        let mut output = Vec::new();
+        assert_eq!(session.run(input.as_bytes(), &mut output).unwrap(), 4);
+
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["path"], json!(["A", "B", "C"]));
+        assert_eq!(lines[0]["total_weight"], json!(3.0));
+        assert_eq!(lines[1]["nodes"], json!(3));
+        assert_eq!(lines[2], json!({"ok": true, "nodes": 3, "arcs": 6}));
+        assert_eq!(lines[3]["ok"], json!(false));
+
+        // a sink with no outgoing arcs is still a node
+        let one_way = GraphOptions { directed: true, ..Default::default() };
+        let adj = build_adjacency_with(&[edge("A", "B", 1.0), edge("B", "C", 2.0)], &one_way);
+        let session = Session { adj: &adj, depth: 1, max_nodes: 100 };
+        assert_eq!(session.execute("stats"), json!({"ok": true, "nodes": 3, "arcs": 2}));
+    }
+}
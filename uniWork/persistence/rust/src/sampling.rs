@@ -0,0 +1,294 @@
+//! sampling.rs — Singine sampled graph statistics
+//!
+//! Cheap graph-health metrics that avoid all-pairs computation: pick random
+//! node pairs with the seeded RNG, run Dijkstra for each, and summarise.
+//! The connected fraction carries a Wilson score interval and the mean weight
+//! a normal-approximation one, both at `--confidence` (default 95%). Unlike
+//! the plain normal interval, Wilson's stays inside [0, 1] and keeps a
+//! non-zero width when every (or no) sampled pair is connected.
+//!
+//! `random_walks` produces weight-biased walks for embedding-style analysis;
+//! by default lighter (closer) edges are proportionally more likely.
+//!
+//! `sample_edges` backs `--mode sample-edges`: a seeded reservoir sample of
+//! the edge table (Algorithm R), so the same `--seed` shows the same rows.
+
+use serde::Serialize;
+
+use crate::rng::Rng;
+use crate::shortest_path::{dijkstra, Adjacency, Edge, EPSILON};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivitySample {
+    pub nodes:          usize,
+    pub samples:        usize,
+    pub connected:      usize,
+    /// connected / samples; None when the graph has fewer than two nodes
+    pub fraction:       Option<f64>,
+    pub fraction_ci:    Option<Interval>,
+    pub mean_weight:    Option<f64>,
+    pub mean_weight_ci: Option<Interval>,
+    pub median_weight:  Option<f64>,
+    pub mean_hops:      Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Interval {
+    pub confidence: f64,
+    pub lower:      f64,
+    pub upper:      f64,
+}
+
+/// Standard normal quantile (Acklam's rational approximation, relative
+/// error below 1.2e-9) for 0 < p < 1. Coefficients as published.
+#[allow(clippy::excessive_precision)]
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2,
+                         1.383577518672690e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2,
+                         6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838,
+                         -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996,
+                         3.754408661907416];
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// `estimate ± z·stderr` for a two-sided `confidence` level.
+fn interval(estimate: f64, stderr: f64, confidence: f64) -> Interval {
+    let half = normal_quantile(0.5 + confidence / 2.0) * stderr;
+    Interval { confidence, lower: estimate - half, upper: estimate + half }
+}
+
+/// Wilson score interval for a proportion `p` observed over `n` trials.
+fn wilson(p: f64, n: usize, confidence: f64) -> Interval {
+    let (z, n) = (normal_quantile(0.5 + confidence / 2.0), n as f64);
+    let z2 = z * z;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    Interval { confidence, lower: (centre - half).max(0.0), upper: (centre + half).min(1.0) }
+}
+
+/// Sorted node list, sinks of one-way arcs included, so sampling is
+/// reproducible regardless of HashMap order.
+pub fn sorted_nodes(adj: &Adjacency) -> Vec<&str> {
+    crate::shortest_path::all_nodes(adj)
+}
+
+pub fn connectivity_sample(adj: &Adjacency, samples: usize, seed: u64, confidence: f64) -> ConnectivitySample {
+    let nodes = sorted_nodes(adj);
+    let mut rng = Rng::new(seed);
+    let mut weights: Vec<f64> = Vec::new();
+    let mut hops: usize = 0;
+    let mut taken = 0;
+
+    if nodes.len() >= 2 {
+        for _ in 0..samples {
+            let a = rng.below(nodes.len());
+            // distinct second endpoint: offset by 1..n-1
+            let b = (a + 1 + rng.below(nodes.len() - 1)) % nodes.len();
+            taken += 1;
+            if let Some(r) = dijkstra(adj, nodes[a], nodes[b]) {
+                weights.push(r.total_weight);
+                hops += r.path.len() - 1;
+            }
+        }
+    }
+
+    let connected = weights.len();
+    let mean = |total: f64| (connected > 0).then(|| total / connected as f64);
+    let mean_weight = mean(weights.iter().sum());
+    let mean_hops = mean(hops as f64);
+    weights.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    let median_weight = match connected {
+        0 => None,
+        n if n % 2 == 1 => Some(weights[n / 2]),
+        n => Some((weights[n / 2 - 1] + weights[n / 2]) / 2.0),
+    };
+
+    let fraction = (taken > 0).then(|| connected as f64 / taken as f64);
+    let fraction_ci = fraction.map(|p| wilson(p, taken, confidence));
+    let mean_weight_ci = mean_weight.filter(|_| connected > 1).map(|m| {
+        let var = weights.iter().map(|w| (w - m).powi(2)).sum::<f64>() / (connected - 1) as f64;
+        interval(m, (var / connected as f64).sqrt(), confidence)
+    });
+
+    ConnectivitySample {
+        nodes: nodes.len(),
+        samples: taken,
+        connected,
+        fraction,
+        fraction_ci,
+        mean_weight,
+        mean_weight_ci,
+        median_weight,
+        mean_hops,
+    }
+}
+
+/// Transition bias for random walks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WalkBias {
+    /// probability ∝ 1 / weight — closer neighbours are preferred
+    #[default]
+    Inverse,
+    /// probability ∝ weight
+    Proportional,
+    Uniform,
+}
+
+/// `walks` walks of up to `steps` hops from `src`. A walk ends early at a node
+/// without neighbours; each sequence starts with `src`.
+pub fn random_walks(
+    adj: &Adjacency,
+    src: &str,
+    steps: usize,
+    walks: usize,
+    bias: WalkBias,
+    seed: u64,
+) -> Vec<Vec<String>> {
+    let mut rng = Rng::new(seed);
+    let mut out = Vec::with_capacity(walks);
+    for _ in 0..walks {
+        let mut walk = vec![src.to_string()];
+        let mut node = src;
+        for _ in 0..steps {
+            let Some(neighbours) = adj.get(node).filter(|n| !n.is_empty()) else { break };
+            let score = |w: f64| match bias {
+                WalkBias::Inverse => 1.0 / w.max(EPSILON),
+                WalkBias::Proportional => w.max(0.0),
+                WalkBias::Uniform => 1.0,
+            };
+            let total: f64 = neighbours.iter().map(|(_, w)| score(*w)).sum();
+            let next = if total > 0.0 {
+                let mut target = rng.next_f64() * total;
+                let mut pick = neighbours.len() - 1;
+                for (i, (_, w)) in neighbours.iter().enumerate() {
+                    target -= score(*w);
+                    if target < 0.0 {
+                        pick = i;
+                        break;
+                    }
+                }
+                pick
+            } else {
+                rng.below(neighbours.len())
+            };
+            node = &neighbours[next].0;
+            walk.push(node.to_string());
+        }
+        out.push(walk);
+    }
+    out
+}
+
+/// `n` edges chosen uniformly at random (all of them when there are fewer),
+/// in gen_id order.
+pub fn sample_edges(edges: impl IntoIterator<Item = Edge>, n: usize, seed: u64) -> Vec<Edge> {
+    let mut rng = Rng::new(seed);
+    let mut reservoir: Vec<Edge> = Vec::with_capacity(n);
+    for (seen, edge) in edges.into_iter().enumerate() {
+        if seen < n {
+            reservoir.push(edge);
+        } else {
+            let slot = rng.below(seen + 1);
+            if slot < n {
+                reservoir[slot] = edge;
+            }
+        }
+    }
+    reservoir.sort_by(|a, b| a.gen_id.cmp(&b.gen_id));
+    reservoir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{build_adjacency, edge, Edge};
+
+    #[test]
+    fn test_fully_connected_fraction_one() {
+        let adj = build_adjacency(&[
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("B", "C", 1.0).with_id("e2"),
+            edge("A", "C", 1.0).with_id("e3"),
+        ]);
+        let s = connectivity_sample(&adj, 20, 42, 0.95);
+        assert_eq!(s.samples, 20);
+        assert_eq!(s.fraction, Some(1.0));
+        assert_eq!(s.median_weight, Some(1.0));
+        assert_eq!(s.mean_hops, Some(1.0));
+
+        let one_way = build_adjacency(&[Edge { directed: Some(true), ..edge("A", "B", 1.0) }]);
+        assert_eq!(sorted_nodes(&one_way), vec!["A", "B"], "sinks are sampled too");
+    }
+
+    #[test]
+    fn test_random_walks_deterministic_per_seed() {
+        let adj = build_adjacency(&[
+            edge("A", "B", 0.1).with_id("e1"),
+            edge("A", "C", 10.0).with_id("e2"),
+            edge("B", "C", 1.0).with_id("e3"),
+        ]);
+        let a = random_walks(&adj, "A", 5, 20, WalkBias::Inverse, 3);
+        assert_eq!(a, random_walks(&adj, "A", 5, 20, WalkBias::Inverse, 3));
+        assert_eq!(a.len(), 20);
+        assert!(a.iter().all(|w| w.len() == 6 && w[0] == "A"));
+        // first hop: B is 100x more likely than C under inverse weighting
+        let to_b = a.iter().filter(|w| w[1] == "B").count();
+        assert!(to_b >= 18, "expected mostly B, got {}", to_b);
+        assert!(random_walks(&adj, "Z", 5, 1, WalkBias::Uniform, 3)[0] == vec!["Z"]);
+    }
+
+    #[test]
+    fn test_two_clusters_partial_and_seeded() {
+        let adj = build_adjacency(&[edge("A", "B", 1.0).with_id("e1"), edge("C", "D", 1.0).with_id("e2")]);
+        let a = connectivity_sample(&adj, 50, 9, 0.95);
+        let b = connectivity_sample(&adj, 50, 9, 0.95);
+        assert_eq!(a.connected, b.connected);
+        let f = a.fraction.unwrap();
+        assert!(f > 0.0 && f < 1.0);
+
+        let ci = a.fraction_ci.unwrap();
+        assert!(ci.lower < f && f < ci.upper, "{:?} should bracket {}", ci, f);
+        // Wilson at z(0.975) ≈ 1.96
+        let z2 = 1.959964_f64.powi(2);
+        let half = 1.959964 / (1.0 + z2 / 50.0) * (f * (1.0 - f) / 50.0 + z2 / 1e4).sqrt();
+        assert!((ci.upper - ci.lower - 2.0 * half).abs() < 1e-5);
+        let wide = connectivity_sample(&adj, 50, 9, 0.99).fraction_ci.unwrap();
+        assert!(wide.lower < ci.lower && wide.upper > ci.upper);
+
+        // every pair connected: the interval still has width below 1
+        let all = connectivity_sample(&build_adjacency(&[edge("A", "B", 1.0)]), 20, 9, 0.95);
+        let ci = all.fraction_ci.unwrap();
+        assert_eq!((all.fraction, ci.upper), (Some(1.0), 1.0));
+        assert!(ci.lower > 0.8 && ci.lower < 1.0, "{:?}", ci);
+    }
+
+    #[test]
+    fn test_sample_edges_returns_n_or_all() {
+        let edges: Vec<Edge> = (0..50)
+            .map(|i| edge(&format!("n{}", i), &format!("n{}", i + 1), 1.0).with_id(&format!("e{:02}", i)))
+            .collect();
+        let picked = sample_edges(edges.clone(), 10, 7);
+        assert_eq!(picked.len(), 10);
+        let ids: std::collections::HashSet<_> = picked.iter().map(|e| e.gen_id.as_str()).collect();
+        assert_eq!(ids.len(), 10, "no edge drawn twice");
+        let again: Vec<_> = sample_edges(edges.clone(), 10, 7).into_iter().map(|e| e.gen_id).collect();
+        assert_eq!(again, picked.iter().map(|e| e.gen_id.clone()).collect::<Vec<_>>());
+        assert_eq!(sample_edges(edges[..3].to_vec(), 10, 7).len(), 3);
+    }
+}
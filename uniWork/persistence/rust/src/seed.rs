@@ -0,0 +1,140 @@
+//! seed.rs — Singine fixture graphs
+//!
+//! `--mode seed --node-count N --edge-count M --db fresh.db` applies the
+//! V001–V004 migrations and fills `similarity_edges` with a random connected
+//! graph: a random spanning tree over N nodes, then extra distinct pairs up
+//! to M edges. Node and edge ids are real gen_ids from the `node` and `edge`
+//! namespaces, weights are similarity distances in [0.01, 1.00], and the same
+//! `--seed` always yields the same shape and weights (gen_ids are allocated
+//! afresh, so they differ between databases).
+
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult};
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::id_gen;
+use crate::rng::Rng;
+
+/// Schema migrations in order, as shipped in `../schema`.
+const MIGRATIONS: [(&str, &str); 4] = [
+    ("V001", include_str!("../../schema/V001__init.sql")),
+    ("V002", include_str!("../../schema/V002__lineage.sql")),
+    ("V003", include_str!("../../schema/V003__categories.sql")),
+    ("V004", include_str!("../../schema/V004__path_params.sql")),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeedReport {
+    /// migrations applied by this run; already-recorded ones are skipped
+    pub migrations: Vec<String>,
+    pub nodes:      usize,
+    pub edges:      usize,
+    pub seed:       u64,
+}
+
+/// Apply every migration not yet recorded in `schema_migrations`.
+pub fn apply_migrations(conn: &Connection) -> SqlResult<Vec<String>> {
+    let mut applied = Vec::new();
+    for (version, sql) in MIGRATIONS {
+        let has_registry: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type='table' AND name='schema_migrations')",
+            [],
+            |r| r.get(0),
+        )?;
+        if has_registry {
+            let recorded = conn
+                .query_row("SELECT 1 FROM schema_migrations WHERE version = ?1", [version], |_| Ok(()))
+                .optional()?;
+            if recorded.is_some() {
+                continue;
+            }
+        }
+        conn.execute_batch(sql)?;
+        applied.push(version.to_string());
+    }
+    Ok(applied)
+}
+
+/// Migrate `conn` and insert a random connected graph of `nodes` nodes and
+/// `edges` undirected edges, in one transaction.
+pub fn seed_graph(conn: &Connection, nodes: usize, edges: usize, seed: u64) -> anyhow::Result<SeedReport> {
+    let max_edges = nodes * nodes.saturating_sub(1) / 2;
+    if nodes < 2 || edges + 1 < nodes || edges > max_edges {
+        anyhow::bail!(
+            "a connected simple graph on {} nodes needs {}..={} edges (got {})",
+            nodes,
+            nodes.saturating_sub(1),
+            max_edges,
+            edges
+        );
+    }
+    let tx = conn.unchecked_transaction()?;
+    let migrations = apply_migrations(&tx)?;
+    let existing: i64 = tx.query_row("SELECT COUNT(*) FROM similarity_edges", [], |r| r.get(0))?;
+    if existing > 0 {
+        anyhow::bail!("similarity_edges already holds {} edges; seed expects a fresh database", existing);
+    }
+
+    let ids = (0..nodes)
+        .map(|_| id_gen::generate(&tx, "node", None).map(|g| g.gen_id))
+        .collect::<SqlResult<Vec<_>>>()?;
+    let mut rng = Rng::new(seed);
+    // node i joins a random earlier node, which keeps the graph connected
+    let mut pairs: Vec<(usize, usize)> = (1..nodes).map(|i| (rng.below(i), i)).collect();
+    let mut seen: HashSet<(usize, usize)> = pairs.iter().copied().collect();
+    while pairs.len() < edges {
+        let (a, b) = (rng.below(nodes), rng.below(nodes));
+        let pair = (a.min(b), a.max(b));
+        if a != b && seen.insert(pair) {
+            pairs.push(pair);
+        }
+    }
+    for (a, b) in pairs {
+        let weight = (1.0 + (rng.next_f64() * 100.0).floor()) / 100.0;
+        tx.execute(
+            "INSERT INTO similarity_edges (gen_id, src_id, dst_id, weight, edge_type)
+             VALUES (?1,?2,?3,?4,'similarity')",
+            rusqlite::params![id_gen::generate(&tx, "edge", None)?.gen_id, ids[a], ids[b], weight],
+        )?;
+    }
+    tx.commit()?;
+    tracing::info!(nodes, edges, seed, "Seeded fixture graph");
+    Ok(SeedReport { migrations, nodes, edges, seed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::{self, GraphOptions};
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_seed_tiny_graph_row_counts() {
+        let conn = Connection::open_in_memory().unwrap();
+        let report = seed_graph(&conn, 5, 7, 42).unwrap();
+        assert_eq!(report.migrations, vec!["V001", "V002", "V003", "V004"]);
+        assert_eq!(count(&conn, "schema_migrations"), 4);
+        assert_eq!(count(&conn, "similarity_edges"), 7);
+        assert_eq!(count(&conn, "id_registry"), 5 + 7);
+
+        let adj = shortest_path::load_graph(&conn, &GraphOptions::default()).unwrap();
+        assert_eq!(adj.len(), 5);
+        let start = adj.keys().next().unwrap();
+        assert_eq!(shortest_path::distances_from(&adj, start).len(), 5, "graph is connected");
+
+        let again = Connection::open_in_memory().unwrap();
+        seed_graph(&again, 5, 7, 42).unwrap();
+        let weights = |c: &Connection| {
+            let mut stmt = c.prepare("SELECT weight FROM similarity_edges ORDER BY rowid").unwrap();
+            stmt.query_map([], |r| r.get::<_, f64>(0)).unwrap().collect::<SqlResult<Vec<_>>>().unwrap()
+        };
+        assert_eq!(weights(&conn), weights(&again));
+
+        let err = seed_graph(&conn, 5, 7, 1).unwrap_err().to_string();
+        assert!(err.contains("fresh database"), "{}", err);
+        assert!(seed_graph(&Connection::open_in_memory().unwrap(), 4, 7, 1).is_err());
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! Algorithm:
 //!   1. Load similarity_edges from SQLite → Vec<Edge>           (raw data)
+//!      and apply GraphOptions filters (e.g. weight ceiling)
 //!   2. Quicksort the edge Vec by weight (ascending)            (list→vector transform)
 //!   3. Build an adjacency map (HashMap<NodeId, Vec<(NodeId, f64)>>)
 //!   4. Run Dijkstra over the sorted adjacency structure         (shortest path)
@@ -12,10 +13,14 @@
 
 use rusqlite::{Connection, Result as SqlResult};
 use serde::{Deserialize, Serialize};
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::OnceLock;
 use std::cmp::Ordering;
+use std::time::Instant;
 
+use crate::algorithms::{self, Algorithm};
 use crate::id_gen;
+use crate::path_score::{self, PathScore};
 
 // ── Data types ─────────────────────────────────────────────────────────────────
 
@@ -26,15 +31,338 @@ pub struct Edge {
     pub dst_id:    String,
     pub weight:    f64,
     pub edge_type: String,
+    /// per-row direction from the optional `directed` column; `None` defers
+    /// to `GraphOptions::directed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directed:  Option<bool>,
+    /// cost of the dst → src direction from the optional `reverse_weight`
+    /// column; `None` mirrors `weight`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverse_weight: Option<f64>,
+}
+
+/// Test fixture: an undirected `sim` edge `s`–`d` with gen_id `s-d`.
+#[cfg(test)]
+pub fn edge(s: &str, d: &str, w: f64) -> Edge {
+    Edge {
+        gen_id:         format!("{}-{}", s, d),
+        src_id:         s.into(),
+        dst_id:         d.into(),
+        weight:         w,
+        edge_type:      "sim".into(),
+        directed:       None,
+        reverse_weight: None,
+    }
+}
+
+#[cfg(test)]
+impl Edge {
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.gen_id = id.into();
+        self
+    }
+
+    pub fn with_type(mut self, edge_type: &str) -> Self {
+        self.edge_type = edge_type.into();
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PathResult {
+pub struct PathCount {
     pub src_id:       String,
     pub dst_id:       String,
-    pub path:         Vec<String>,
     pub total_weight: f64,
-    pub algorithm:    String,
+    /// number of distinct minimum-cost paths (saturates at u64::MAX)
+    pub count:        u64,
+    pub saturated:    bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathResult {
+    pub src_id:         String,
+    pub dst_id:         String,
+    pub path:           Vec<String>,
+    pub total_weight:   f64,
+    pub algorithm:      String,
+    /// share of total_weight contributed by each edge type (summed Dijkstra scores only)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub weight_by_type: HashMap<String, f64>,
+}
+
+/// Costs within this tolerance are treated as equal.
+pub const EPSILON: f64 = 1e-9;
+
+/// Edge-set shaping shared by every mode that builds an adjacency map.
+#[derive(Debug, Clone, Default)]
+pub struct GraphOptions {
+    /// only load edges of this type
+    pub edge_type:      Option<String>,
+    /// drop individual edges heavier than this before the search
+    pub weight_ceiling: Option<f64>,
+    /// drop hops costing more than this once weights are resolved
+    pub max_hop_weight: Option<f64>,
+    /// expected distinct node count; pre-sizes the adjacency map
+    pub node_hint:      Option<usize>,
+    /// how parallel edges between the same pair combine
+    pub parallel_policy: ParallelEdgePolicy,
+    /// only edges valid at this instant (storage format, UTC); needs valid_from/valid_to
+    pub as_of:          Option<String>,
+    /// column read as the edge weight (default `weight`); must exist on the table
+    pub weight_column:  Option<String>,
+    /// prune nodes with more distinct neighbours than this from traversal
+    pub max_degree:     Option<usize>,
+    /// with `edge_type`, also load rows whose edge_type is NULL
+    pub include_untyped: bool,
+    /// add `node_weights.weight` of the node entered to each hop's cost
+    pub use_node_weights: bool,
+    /// drop repeated (src, dst, edge_type) rows before building the graph
+    pub dedup_edges:    Option<EdgeDedup>,
+    /// treat edges without a per-row `directed` value as src → dst only
+    pub directed:       bool,
+    /// gen_ids of edges left out of the graph, to simulate their removal
+    pub exclude_edges:  Vec<String>,
+    /// parse JSON edge files leniently (comments, trailing commas)
+    pub lenient_json:   bool,
+    /// keep only each node's K lightest neighbours; searches become approximate
+    pub limit_neighbors: Option<usize>,
+    /// log a warning when the built graph has more than one component
+    pub warn_components: bool,
+    /// keep only nodes whose id starts with this prefix (e.g. `entity-`)
+    pub node_prefix:    Option<String>,
+}
+
+/// Which row survives when `--dedup-edges-on-load` drops repeated
+/// (src, dst, edge_type) rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeDedup {
+    /// first row in load order
+    First,
+    /// lightest row
+    Min,
+}
+
+/// Combination rule for parallel edges between the same node pair.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParallelEdgePolicy {
+    /// cheapest hop
+    #[default]
+    Min,
+    Max,
+    /// aggregate strength
+    Sum,
+    Mean,
+}
+
+impl GraphOptions {
+    /// Apply the in-memory filters to a freshly loaded edge list.
+    pub fn apply(&self, edges: &mut Vec<Edge>) {
+        if !self.exclude_edges.is_empty() {
+            let excluded: HashSet<&str> = self.exclude_edges.iter().map(String::as_str).collect();
+            let before = edges.len();
+            edges.retain(|e| !excluded.contains(e.gen_id.as_str()));
+            let removed = before - edges.len();
+            if removed < excluded.len() {
+                tracing::warn!(requested = excluded.len(), removed, "some --exclude-edge gen_ids matched no loaded edge");
+            }
+            tracing::info!(removed, "Excluded edges");
+        }
+        if let Some(keep) = self.dedup_edges {
+            let removed = dedup_edges(edges, keep);
+            tracing::info!(removed, keep = ?keep, "Removed duplicate edges");
+        }
+        if let Some(ceiling) = self.weight_ceiling {
+            let before = edges.len();
+            edges.retain(|e| e.weight <= ceiling);
+            tracing::info!(ceiling, dropped = before - edges.len(), "Applied weight ceiling");
+        }
+    }
+}
+
+/// Keep one row per (src, dst, edge_type), in first-seen position; returns
+/// how many rows were removed.
+pub fn dedup_edges(edges: &mut Vec<Edge>, keep: EdgeDedup) -> usize {
+    let before = edges.len();
+    let mut index: HashMap<(String, String, String), usize> = HashMap::new();
+    let mut kept: Vec<Edge> = Vec::with_capacity(edges.len());
+    for e in edges.drain(..) {
+        let key = (e.src_id.clone(), e.dst_id.clone(), e.edge_type.clone());
+        match index.get(&key) {
+            Some(&i) => {
+                if keep == EdgeDedup::Min && e.weight < kept[i].weight {
+                    kept[i] = e;
+                }
+            }
+            None => {
+                index.insert(key, kept.len());
+                kept.push(e);
+            }
+        }
+    }
+    *edges = kept;
+    before - edges.len()
+}
+
+/// Query-time options consulted by `search`.
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// undirected queries: run and report with the lexicographically smaller
+    /// endpoint first; ignored on a graph with one-way arcs
+    pub canonical: bool,
+    /// persist paths longer than this many nodes as a truncated head/tail record
+    pub max_path_store: Option<usize>,
+    /// refuse to search a graph with negative weights instead of warning
+    pub strict: bool,
+    /// priority queue behind Dijkstra
+    pub heap: HeapKind,
+    /// waypoint every reported path must pass through
+    pub via: Option<String>,
+    /// reject paths of fewer hops, e.g. to skip the direct edge
+    pub min_hops: Option<usize>,
+    /// how hop weights combine into the score the search minimises
+    pub path_score: PathScore,
+    /// `widest` maximises the path's lightest edge instead
+    pub algorithm: Algorithm,
+    /// abort with `SearchTooLarge` once the priority queue holds more entries
+    pub max_open_states: Option<usize>,
+    /// search in integer units of 1/S for bit-reproducible costs
+    pub integer_weights: Option<u64>,
+    /// batch: persist at most this many paths per run (later ones are still reported)
+    pub max_persist: Option<usize>,
+    /// batch: abort on the first pair without a path
+    pub fail_fast: bool,
+    /// delete the run_id's earlier path_results rows before persisting, in the same transaction
+    pub replace_run: bool,
+}
+
+/// The Dijkstra priority queue grew past `--max-open-states`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchTooLarge {
+    pub limit: usize,
+}
+
+impl std::fmt::Display for SearchTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SearchTooLarge: more than {} open states in the Dijkstra heap (--max-open-states)", self.limit)
+    }
+}
+
+impl std::error::Error for SearchTooLarge {}
+
+/// The options that shape a stored path, written to `path_results.params_json`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryParams {
+    pub edge_type:        Option<String>,
+    pub include_untyped:  bool,
+    pub weight_column:    Option<String>,
+    pub use_node_weights: bool,
+    pub directed:         bool,
+    pub parallel_policy:  ParallelEdgePolicy,
+    pub dedup_edges:      Option<EdgeDedup>,
+    pub as_of:            Option<String>,
+    pub weight_ceiling:   Option<f64>,
+    pub max_hop_weight:   Option<f64>,
+    pub max_degree:       Option<usize>,
+    pub node_prefix:      Option<String>,
+    pub limit_neighbors:  Option<usize>,
+    pub exclude_edges:    Vec<String>,
+    pub canonical:        bool,
+    pub heap:             HeapKind,
+    pub via:              Option<String>,
+    pub min_hops:         Option<usize>,
+    pub path_score:       PathScore,
+    pub algorithm:        Algorithm,
+    pub integer_weights:  Option<u64>,
+    /// how the path was stored (head/tail truncation), not how it was found
+    pub max_path_store:   Option<usize>,
+}
+
+impl QueryParams {
+    pub fn new(opts: &GraphOptions, search_opts: &SearchOptions) -> Self {
+        QueryParams {
+            edge_type:        opts.edge_type.clone(),
+            include_untyped:  opts.include_untyped,
+            weight_column:    opts.weight_column.clone(),
+            use_node_weights: opts.use_node_weights,
+            directed:         opts.directed,
+            parallel_policy:  opts.parallel_policy,
+            dedup_edges:      opts.dedup_edges,
+            as_of:            opts.as_of.clone(),
+            weight_ceiling:   opts.weight_ceiling,
+            max_hop_weight:   opts.max_hop_weight,
+            max_degree:       opts.max_degree,
+            node_prefix:      opts.node_prefix.clone(),
+            limit_neighbors:  opts.limit_neighbors,
+            exclude_edges:    opts.exclude_edges.clone(),
+            canonical:        search_opts.canonical,
+            heap:             search_opts.heap,
+            via:              search_opts.via.clone(),
+            min_hops:         search_opts.min_hops,
+            path_score:       search_opts.path_score,
+            algorithm:        search_opts.algorithm,
+            integer_weights:  search_opts.integer_weights,
+            max_path_store:   search_opts.max_path_store,
+        }
+    }
+}
+
+/// Dijkstra's priority queue. `Pairing` exists only with feature `pairing-heap`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeapKind {
+    /// std BinaryHeap of cloned states, stale entries skipped on pop
+    #[default]
+    Binary,
+    /// pairing heap with decrease-key
+    Pairing,
+}
+
+/// Adjacency: node → [(neighbour, weight)], neighbours in ascending weight
+/// order. An undirected edge is an arc each way; a directed row only src → dst.
+pub type Adjacency = HashMap<String, Vec<(String, f64)>>;
+
+/// Every arc of `adj` once: an arc whose mirror (the other direction, same
+/// weight) is present too is an undirected edge and is reported only from its
+/// smaller endpoint. One-way arcs are always reported.
+pub fn arcs_once(adj: &Adjacency) -> Vec<(&str, &str, f64)> {
+    arcs(adj).filter(|&(node, next, w)| node <= next || !mirrored(adj, node, next, w)).collect()
+}
+
+/// Every node of `adj`, sorted. A node reached only by directed arcs has no
+/// key of its own, so the neighbour lists are included.
+pub fn all_nodes(adj: &Adjacency) -> Vec<&str> {
+    let mut nodes: Vec<&str> =
+        adj.keys().map(String::as_str).chain(adj.values().flatten().map(|(next, _)| next.as_str())).collect();
+    nodes.sort_unstable();
+    nodes.dedup();
+    nodes
+}
+
+/// Every arc reversed, so a search from dst over it yields distances *to* dst
+/// even when some edges are directed.
+pub fn reversed(adj: &Adjacency) -> Adjacency {
+    let mut rev: Adjacency = HashMap::with_capacity(adj.len());
+    for (node, neighbours) in adj {
+        for (next, w) in neighbours {
+            rev.entry(next.clone()).or_default().push((node.clone(), *w));
+        }
+    }
+    rev
+}
+
+/// True when every arc has a mirror, i.e. the graph is undirected.
+pub fn is_undirected(adj: &Adjacency) -> bool {
+    arcs(adj).all(|(node, next, w)| mirrored(adj, node, next, w))
+}
+
+fn arcs(adj: &Adjacency) -> impl Iterator<Item = (&str, &str, f64)> {
+    adj.iter().flat_map(|(node, nbrs)| nbrs.iter().map(move |(next, w)| (node.as_str(), next.as_str(), *w)))
+}
+
+fn mirrored(adj: &Adjacency, node: &str, next: &str, w: f64) -> bool {
+    adj.get(next).is_some_and(|back| back.iter().any(|(n, bw)| n == node && *bw == w))
 }
 
 // Dijkstra node state — min-heap by cost
@@ -90,27 +418,262 @@ fn qs_partition(edges: &mut Vec<Edge>, lo: usize, hi: usize) {
 
 // ── Graph builder ─────────────────────────────────────────────────────────────
 
-fn build_adjacency(edges: &[Edge]) -> HashMap<String, Vec<(String, f64)>> {
-    let mut adj: HashMap<String, Vec<(String, f64)>> = HashMap::new();
+/// Default-options adjacency, used throughout the unit tests.
+#[cfg(test)]
+pub fn build_adjacency(edges: &[Edge]) -> Adjacency {
+    build_adjacency_with(edges, &GraphOptions::default())
+}
+
+/// Maps an edge to the weight the search sees. The extension point for edge
+/// kinds whose stored weight is not directly a distance.
+pub trait WeightFn {
+    fn weight(&self, edge: &Edge) -> f64;
+
+    /// True when effective weights keep the stored order, so neighbour lists
+    /// built from weight-sorted edges need no re-sort.
+    fn preserves_order(&self) -> bool {
+        false
+    }
+}
+
+/// The stored weight, unchanged.
+pub struct IdentityWeight;
+
+impl WeightFn for IdentityWeight {
+    fn weight(&self, edge: &Edge) -> f64 {
+        edge.weight
+    }
+
+    fn preserves_order(&self) -> bool {
+        true
+    }
+}
+
+impl<F: Fn(&Edge) -> f64> WeightFn for F {
+    fn weight(&self, edge: &Edge) -> f64 {
+        self(edge)
+    }
+}
+
+/// Build the adjacency map per `opts` with stored weights.
+pub fn build_adjacency_with(edges: &[Edge], opts: &GraphOptions) -> Adjacency {
+    build_adjacency_weighted(edges, opts, &IdentityWeight)
+}
+
+/// Build the adjacency map per `opts`, weighing each edge with `weight_fn`:
+/// parallel edges between the same node pair collapse into one neighbour
+/// entry combined by `opts.parallel_policy`, and `opts.node_hint` reserves
+/// room up front to avoid rehashing. A directed edge (per row, else per
+/// `opts.directed`) is traversable src → dst only and merges only with
+/// directed edges running the same way.
+pub fn build_adjacency_weighted<'a>(edges: &'a [Edge], opts: &GraphOptions, weight_fn: &impl WeightFn) -> Adjacency {
+    // (min, max, sum, count) per unordered pair or directed (src, dst), in first-seen order
+    type PairKey<'a> = (&'a str, &'a str, bool);
+    let mut index: HashMap<PairKey, usize> = HashMap::new();
+    let mut merged: Vec<(PairKey, [f64; 4])> = Vec::new();
+    let mut merge = |key: PairKey<'a>, w: f64| match index.get(&key) {
+        Some(&i) => {
+            let agg = &mut merged[i].1;
+            agg[0] = agg[0].min(w);
+            agg[1] = agg[1].max(w);
+            agg[2] += w;
+            agg[3] += 1.0;
+        }
+        None => {
+            index.insert(key, merged.len());
+            merged.push((key, [w, w, w, 1.0]));
+        }
+    };
+    let mut asymmetric = false;
     for e in edges {
-        adj.entry(e.src_id.clone())
-           .or_default()
-           .push((e.dst_id.clone(), e.weight));
+        let directed = e.directed.unwrap_or(opts.directed);
+        match e.reverse_weight {
+            // an asymmetric two-way edge is a pair of one-way arcs
+            Some(reverse) if !directed && e.src_id != e.dst_id => {
+                asymmetric = true;
+                merge((e.src_id.as_str(), e.dst_id.as_str(), true), weight_fn.weight(e));
+                let back = Edge {
+                    src_id: e.dst_id.clone(),
+                    dst_id: e.src_id.clone(),
+                    weight: reverse,
+                    reverse_weight: None,
+                    ..e.clone()
+                };
+                merge((e.dst_id.as_str(), e.src_id.as_str(), true), weight_fn.weight(&back));
+            }
+            _ => {
+                let key = if directed || e.src_id <= e.dst_id {
+                    (e.src_id.as_str(), e.dst_id.as_str(), directed)
+                } else {
+                    (e.dst_id.as_str(), e.src_id.as_str(), directed)
+                };
+                merge(key, weight_fn.weight(e));
+            }
+        }
+    }
+
+    let mut adj: Adjacency = HashMap::with_capacity(opts.node_hint.unwrap_or(0));
+    for ((a, b, directed), [min, max, sum, count]) in merged {
+        let w = match opts.parallel_policy {
+            ParallelEdgePolicy::Min => min,
+            ParallelEdgePolicy::Max => max,
+            ParallelEdgePolicy::Sum => sum,
+            ParallelEdgePolicy::Mean => sum / count,
+        };
+        adj.entry(a.to_string()).or_default().push((b.to_string(), w));
         // undirected — add reverse
-        adj.entry(e.dst_id.clone())
-           .or_default()
-           .push((e.src_id.clone(), e.weight));
+        if !directed && a != b {
+            adj.entry(b.to_string()).or_default().push((a.to_string(), w));
+        }
+    }
+    if let Some(max) = opts.max_hop_weight {
+        drop_heavy_hops(&mut adj, max);
+    }
+    if let Some(prefix) = opts.node_prefix.as_deref() {
+        keep_prefix(&mut adj, prefix);
+    }
+    if let Some(max_degree) = opts.max_degree {
+        prune_hubs(&mut adj, max_degree);
+    }
+    if asymmetric || opts.parallel_policy != ParallelEdgePolicy::Min || !weight_fn.preserves_order() {
+        // combined, remapped or reverse weights can reorder neighbours; keep them ascending
+        for neighbours in adj.values_mut() {
+            neighbours.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap_or(Ordering::Equal));
+        }
+    }
+    if let Some(k) = opts.limit_neighbors {
+        limit_neighbors(&mut adj, k);
     }
     adj
 }
 
+/// Cut every neighbour list down to its `k` lightest entries.
+fn limit_neighbors(adj: &mut Adjacency, k: usize) {
+    let mut dropped = 0;
+    for neighbours in adj.values_mut() {
+        dropped += neighbours.len().saturating_sub(k);
+        // edges not pre-sorted by weight leave the lists in input order
+        neighbours.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap_or(Ordering::Equal));
+        neighbours.truncate(k);
+    }
+    tracing::info!(limit = k, dropped, "Limited neighbours per node; results are approximate");
+}
+
+/// Cut every node with more than `max_degree` distinct neighbours out of the
+/// graph. A pruned hub keeps its key with an empty neighbour list — loaded
+/// nodes always have a neighbour otherwise — so `is_pruned` can tell it apart
+/// from an unknown node.
+fn prune_hubs(adj: &mut Adjacency, max_degree: usize) {
+    let mut hubs: Vec<String> =
+        adj.iter().filter(|(_, n)| n.len() > max_degree).map(|(k, _)| k.clone()).collect();
+    if hubs.is_empty() {
+        return;
+    }
+    hubs.sort_unstable();
+    let hub_set: HashSet<&str> = hubs.iter().map(String::as_str).collect();
+    for (node, neighbours) in adj.iter_mut() {
+        if hub_set.contains(node.as_str()) {
+            neighbours.clear();
+        } else {
+            neighbours.retain(|(n, _)| !hub_set.contains(n.as_str()));
+        }
+    }
+    tracing::info!(max_degree, pruned = hubs.len(), hubs = ?hubs, "Pruned hub nodes");
+}
+
+/// Drop every hop costing more than `max`. Unlike `weight_ceiling`, which
+/// filters stored rows by their `weight`, this sees the cost the search
+/// relaxes: after `--weight-column`, reverse weights and the parallel-edge
+/// policy (a `sum` of light rows can exceed `max`), though before node weights.
+fn drop_heavy_hops(adj: &mut Adjacency, max: f64) {
+    let mut dropped = 0;
+    for neighbours in adj.values_mut() {
+        let before = neighbours.len();
+        neighbours.retain(|(_, w)| *w <= max);
+        dropped += before - neighbours.len();
+    }
+    tracing::info!(max_hop_weight = max, dropped, "Dropped heavy hops");
+}
+
+/// Drop every node whose id does not start with `prefix`, with its edges.
+fn keep_prefix(adj: &mut Adjacency, prefix: &str) {
+    let before = adj.len();
+    adj.retain(|node, _| node.starts_with(prefix));
+    for neighbours in adj.values_mut() {
+        neighbours.retain(|(n, _)| n.starts_with(prefix));
+    }
+    tracing::info!(prefix, dropped = before - adj.len(), "Kept nodes with prefix");
+}
+
+pub fn is_pruned(adj: &Adjacency, node: &str) -> bool {
+    adj.get(node).is_some_and(|n| n.is_empty())
+}
+
+/// Pruning an endpoint would silently turn a query into "no path"; refuse instead.
+pub fn check_endpoints(adj: &Adjacency, opts: &GraphOptions, endpoints: &[&str]) -> anyhow::Result<()> {
+    if let Some(prefix) = opts.node_prefix.as_deref() {
+        if let Some(node) = endpoints.iter().find(|n| !n.starts_with(prefix)) {
+            anyhow::bail!("{} does not match --node-prefix {}", node, prefix);
+        }
+    }
+    if let Some(max_degree) = opts.max_degree {
+        for node in endpoints {
+            if is_pruned(adj, node) {
+                anyhow::bail!("{} has more than --max-degree {} neighbours and was pruned", node, max_degree);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Dijkstra assumes non-negative weights; with a negative edge it can settle a
+/// node too early and report a wrong path without any sign of failure. Warn
+/// (or under `strict`, refuse) and return the number of negative edges.
+pub fn check_weights(adj: &Adjacency, strict: bool) -> anyhow::Result<usize> {
+    let negative: Vec<(&str, &str, f64)> = arcs_once(adj).into_iter().filter(|&(_, _, w)| w < 0.0).collect();
+    let Some(&(src, dst, weight)) = negative.iter().min_by(|a, b| a.2.total_cmp(&b.2)) else {
+        return Ok(0);
+    };
+    if strict {
+        anyhow::bail!(
+            "{} negative edge(s) (lightest {}-{} = {}); Dijkstra results would be wrong, use Bellman-Ford",
+            negative.len(), src, dst, weight
+        );
+    }
+    tracing::warn!(
+        negative_edges = negative.len(),
+        src = %src,
+        dst = %dst,
+        weight,
+        "NEGATIVE EDGE WEIGHTS: Dijkstra results may be silently wrong; use Bellman-Ford (or --strict to refuse)"
+    );
+    Ok(negative.len())
+}
+
 // ── Dijkstra ─────────────────────────────────────────────────────────────────
 
 pub fn dijkstra(
-    adj: &HashMap<String, Vec<(String, f64)>>,
+    adj: &Adjacency,
     src: &str,
     dst: &str,
 ) -> Option<PathResult> {
+    dijkstra_counted(adj, src, dst).0
+}
+
+/// Dijkstra that also reports how many nodes were expanded.
+pub fn dijkstra_counted(adj: &Adjacency, src: &str, dst: &str) -> (Option<PathResult>, usize) {
+    dijkstra_capped(adj, src, dst, None).expect("uncapped search cannot overflow")
+}
+
+/// `dijkstra_counted`, failing once the heap holds more than `max_open` states
+/// (stale entries included, which is what grows on adversarial graphs).
+pub fn dijkstra_capped(
+    adj: &Adjacency,
+    src: &str,
+    dst: &str,
+    max_open: Option<usize>,
+) -> Result<(Option<PathResult>, usize), SearchTooLarge> {
+    let mut expanded = 0;
     let mut dist: HashMap<String, f64> = HashMap::new();
     let mut heap = BinaryHeap::new();
 
@@ -123,19 +686,22 @@ pub fn dijkstra(
 
     while let Some(State { cost, node, history }) = heap.pop() {
         if node == dst {
-            return Some(PathResult {
-                src_id:       src.to_string(),
-                dst_id:       dst.to_string(),
-                path:         history,
-                total_weight: cost,
-                algorithm:    "dijkstra+quicksort".to_string(),
-            });
+            let result = PathResult {
+                src_id:         src.to_string(),
+                dst_id:         dst.to_string(),
+                path:           history,
+                total_weight:   cost,
+                algorithm:      "dijkstra+quicksort".to_string(),
+                weight_by_type: HashMap::new(),
+            };
+            return Ok((Some(result), expanded));
         }
         if let Some(&best) = dist.get(&node) {
-            if cost > best + 1e-9 {
+            if cost > best + EPSILON {
                 continue;
             }
         }
+        expanded += 1;
         if let Some(neighbours) = adj.get(&node) {
             for (next, w) in neighbours {
                 let next_cost = cost + w;
@@ -149,6 +715,183 @@ pub fn dijkstra(
                         node:    next.clone(),
                         history: new_hist,
                     });
+                    if let Some(limit) = max_open.filter(|&limit| heap.len() > limit) {
+                        return Err(SearchTooLarge { limit });
+                    }
+                }
+            }
+        }
+    }
+    Ok((None, expanded))
+}
+
+/// Cheapest path from `src` to `dst` of at least `min_hops` edges. Search
+/// states are (node, hops so far capped at `min_hops`), so past the floor it
+/// is plain Dijkstra again. Paths never revisit a node; because states with
+/// different histories are merged, a cheaper simple path can in rare cases be
+/// missed in favour of a slightly dearer one.
+pub fn dijkstra_min_hops(
+    adj: &Adjacency,
+    src: &str,
+    dst: &str,
+    min_hops: usize,
+    max_open: Option<usize>,
+) -> Result<Option<PathResult>, SearchTooLarge> {
+    let layer = |history: &[String]| (history.len() - 1).min(min_hops);
+    let mut dist: HashMap<(String, usize), f64> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    dist.insert((src.to_string(), 0), 0.0);
+    heap.push(State { cost: 0.0, node: src.to_string(), history: vec![src.to_string()] });
+
+    while let Some(State { cost, node, history }) = heap.pop() {
+        let hops = layer(&history);
+        if node == dst {
+            if hops < min_hops {
+                // a simple path cannot leave dst and come back to it
+                continue;
+            }
+            return Ok(Some(PathResult {
+                src_id:         src.to_string(),
+                dst_id:         dst.to_string(),
+                path:           history,
+                total_weight:   cost,
+                algorithm:      "dijkstra+min-hops".to_string(),
+                weight_by_type: HashMap::new(),
+            }));
+        }
+        if cost > dist.get(&(node.clone(), hops)).copied().unwrap_or(f64::INFINITY) + EPSILON {
+            continue;
+        }
+        for (next, w) in adj.get(&node).into_iter().flatten() {
+            if history.contains(next) {
+                continue;
+            }
+            let next_cost = cost + w;
+            let entry = dist.entry((next.clone(), (hops + 1).min(min_hops))).or_insert(f64::INFINITY);
+            if next_cost < *entry {
+                *entry = next_cost;
+                let mut new_hist = history.clone();
+                new_hist.push(next.clone());
+                heap.push(State { cost: next_cost, node: next.clone(), history: new_hist });
+                if let Some(limit) = max_open.filter(|&limit| heap.len() > limit) {
+                    return Err(SearchTooLarge { limit });
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Cheapest path from any node of `sources` to any node of `targets`: every
+/// source is seeded at cost 0 and the search stops at the first target
+/// settled. The result's src_id/dst_id are the endpoints that were chosen.
+pub fn dijkstra_sets(adj: &Adjacency, sources: &[String], targets: &[String]) -> Option<PathResult> {
+    let targets: HashSet<&str> = targets.iter().map(String::as_str).collect();
+    let mut dist: HashMap<String, f64> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    for src in sources {
+        if dist.insert(src.clone(), 0.0).is_none() {
+            heap.push(State { cost: 0.0, node: src.clone(), history: vec![src.clone()] });
+        }
+    }
+
+    while let Some(State { cost, node, history }) = heap.pop() {
+        if targets.contains(node.as_str()) {
+            return Some(PathResult {
+                src_id:         history[0].clone(),
+                dst_id:         node,
+                path:           history,
+                total_weight:   cost,
+                algorithm:      "dijkstra+multi-source".to_string(),
+                weight_by_type: HashMap::new(),
+            });
+        }
+        if cost > dist.get(&node).copied().unwrap_or(f64::INFINITY) + EPSILON {
+            continue;
+        }
+        for (next, w) in adj.get(&node).into_iter().flatten() {
+            let next_cost = cost + w;
+            let entry = dist.entry(next.clone()).or_insert(f64::INFINITY);
+            if next_cost < *entry {
+                *entry = next_cost;
+                let mut new_hist = history.clone();
+                new_hist.push(next.clone());
+                heap.push(State { cost: next_cost, node: next.clone(), history: new_hist });
+            }
+        }
+    }
+    None
+}
+
+/// Single-source Dijkstra: best distance from `src` to every reachable node.
+pub fn distances_from(adj: &Adjacency, src: &str) -> HashMap<String, f64> {
+    let mut dist: HashMap<String, f64> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    dist.insert(src.to_string(), 0.0);
+    heap.push(State { cost: 0.0, node: src.to_string(), history: Vec::new() });
+
+    while let Some(State { cost, node, .. }) = heap.pop() {
+        if cost > dist.get(&node).copied().unwrap_or(f64::INFINITY) + EPSILON {
+            continue;
+        }
+        if let Some(neighbours) = adj.get(&node) {
+            for (next, w) in neighbours {
+                let next_cost = cost + w;
+                let entry = dist.entry(next.clone()).or_insert(f64::INFINITY);
+                if next_cost < *entry {
+                    *entry = next_cost;
+                    heap.push(State { cost: next_cost, node: next.clone(), history: Vec::new() });
+                }
+            }
+        }
+    }
+    dist
+}
+
+/// Count the distinct minimum-cost paths from `src` to `dst` without
+/// enumerating them; routes whose costs differ by ≤ EPSILON count as equal.
+pub fn count_shortest_paths(adj: &Adjacency, src: &str, dst: &str) -> Option<PathCount> {
+    let mut dist: HashMap<String, f64> = HashMap::new();
+    let mut count: HashMap<String, u64> = HashMap::new();
+    let mut settled: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut heap = BinaryHeap::new();
+    let mut saturated = false;
+
+    dist.insert(src.to_string(), 0.0);
+    count.insert(src.to_string(), 1);
+    heap.push(State { cost: 0.0, node: src.to_string(), history: Vec::new() });
+
+    while let Some(State { cost, node, .. }) = heap.pop() {
+        if !settled.insert(node.clone()) {
+            continue;
+        }
+        if node == dst {
+            return Some(PathCount {
+                src_id:       src.to_string(),
+                dst_id:       dst.to_string(),
+                total_weight: cost,
+                count:        count[&node],
+                saturated,
+            });
+        }
+        let here = count[&node];
+        if let Some(neighbours) = adj.get(&node) {
+            for (next, w) in neighbours {
+                if settled.contains(next) {
+                    continue;
+                }
+                let next_cost = cost + w;
+                let best = dist.get(next).copied().unwrap_or(f64::INFINITY);
+                if next_cost < best - EPSILON {
+                    dist.insert(next.clone(), next_cost);
+                    count.insert(next.clone(), here);
+                    heap.push(State { cost: next_cost, node: next.clone(), history: Vec::new() });
+                } else if (next_cost - best).abs() <= EPSILON {
+                    let c = count.entry(next.clone()).or_insert(0);
+                    *c = c.checked_add(here).unwrap_or_else(|| {
+                        saturated = true;
+                        u64::MAX
+                    });
                 }
             }
         }
@@ -156,75 +899,618 @@ pub fn dijkstra(
     None
 }
 
+/// Search entry point: applies `SearchOptions` around `dijkstra`.
+/// `undirected` says whether every arc of `adj` can be walked back (see
+/// `is_undirected`); only `canonical` reads it.
+pub fn search(
+    adj: &Adjacency,
+    undirected: bool,
+    src: &str,
+    dst: &str,
+    opts: &SearchOptions,
+) -> Result<Option<PathResult>, SearchTooLarge> {
+    // reversing the query is only sound when every arc can be walked back
+    let (src, dst) = if opts.canonical && dst < src && undirected { (dst, src) } else { (src, dst) };
+    if let Some(min_hops) = opts.min_hops.filter(|&n| n > 1) {
+        return dijkstra_min_hops(adj, src, dst, min_hops, opts.max_open_states);
+    }
+    match opts.via.as_deref() {
+        Some(via) => search_via(adj, src, via, dst, opts),
+        None => search_leg(adj, src, dst, opts),
+    }
+}
+
+/// shortest(src → via) joined to shortest(via → dst); `None` if either leg is missing.
+fn search_via(
+    adj: &Adjacency,
+    src: &str,
+    via: &str,
+    dst: &str,
+    opts: &SearchOptions,
+) -> Result<Option<PathResult>, SearchTooLarge> {
+    let Some(first) = search_leg(adj, src, via, opts)? else { return Ok(None) };
+    let Some(second) = search_leg(adj, via, dst, opts)? else { return Ok(None) };
+    let mut path = first.path;
+    path.extend(second.path.into_iter().skip(1));
+    Ok(Some(PathResult {
+        src_id: src.to_string(),
+        dst_id: dst.to_string(),
+        path,
+        total_weight: match opts.algorithm {
+            Algorithm::Widest => first.total_weight.min(second.total_weight),
+            Algorithm::Dijkstra => opts.path_score.combine(first.total_weight, second.total_weight),
+        },
+        ..first
+    }))
+}
+
+fn search_leg(
+    adj: &Adjacency,
+    src: &str,
+    dst: &str,
+    opts: &SearchOptions,
+) -> Result<Option<PathResult>, SearchTooLarge> {
+    if opts.algorithm == Algorithm::Widest {
+        return Ok(algorithms::widest_path(adj, src, dst));
+    }
+    if opts.path_score != PathScore::Sum {
+        return path_score::dijkstra(adj, src, dst, opts.path_score, opts.max_open_states);
+    }
+    if let Some(scale) = opts.integer_weights {
+        return crate::integer_weights::dijkstra(adj, src, dst, scale, opts.max_open_states);
+    }
+    match opts.heap {
+        #[cfg(feature = "pairing-heap")]
+        HeapKind::Pairing => crate::pairing_heap::dijkstra(adj, src, dst, opts.max_open_states),
+        _ => Ok(dijkstra_capped(adj, src, dst, opts.max_open_states)?.0),
+    }
+}
+
 // ── DB interface ──────────────────────────────────────────────────────────────
 
 pub fn load_edges(conn: &Connection, edge_type: Option<&str>) -> SqlResult<Vec<Edge>> {
-    let sql = match edge_type {
-        Some(t) => format!(
-            "SELECT gen_id,src_id,dst_id,weight,edge_type FROM similarity_edges \
-             WHERE edge_type='{}' ORDER BY weight",
-            t
-        ),
-        None => "SELECT gen_id,src_id,dst_id,weight,edge_type \
-                 FROM similarity_edges ORDER BY weight"
-            .to_string(),
+    let opts = GraphOptions { edge_type: edge_type.map(String::from), ..Default::default() };
+    load_edges_with(conn, &opts)
+}
+
+/// Load edges applying the SQL-side filters of `opts` (edge_type, as_of)
+/// as bound parameters.
+pub fn load_edges_with(conn: &Connection, opts: &GraphOptions) -> SqlResult<Vec<Edge>> {
+    let mut clauses: Vec<String> = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    let cols = table_columns(conn, "similarity_edges")?;
+    let weight = match opts.weight_column.as_deref() {
+        None | Some("weight") => "weight",
+        Some(name) => {
+            // identifiers cannot be bound, so only accept a real column name
+            if !cols.contains(name) {
+                return Err(rusqlite::Error::InvalidColumnName(name.to_string()));
+            }
+            name
+        }
     };
+    if let Some(t) = &opts.edge_type {
+        params.push(t.clone());
+        if opts.include_untyped {
+            clauses.push(format!("(edge_type = ?{} OR edge_type IS NULL)", params.len()));
+        } else {
+            clauses.push(format!("edge_type = ?{}", params.len()));
+        }
+    }
+    if let Some(ts) = &opts.as_of {
+        if cols.contains("valid_from") && cols.contains("valid_to") {
+            params.push(ts.clone());
+            let i = params.len();
+            clauses.push(format!(
+                "(valid_from IS NULL OR valid_from <= ?{i}) AND (valid_to IS NULL OR valid_to > ?{i})"
+            ));
+        } else {
+            tracing::warn!("--as-of ignored: similarity_edges has no valid_from/valid_to columns");
+        }
+    }
+
+    // without the column every row falls back to the global --directed flag
+    let directed = if cols.contains("directed") { "directed" } else { "NULL" };
+    let reverse = if cols.contains("reverse_weight") { "reverse_weight" } else { "NULL" };
+    let mut sql = format!(
+        "SELECT gen_id,src_id,dst_id,\"{weight}\",edge_type,{directed},{reverse} FROM similarity_edges"
+    );
+    if !clauses.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&clauses.join(" AND "));
+    }
+    sql.push_str(&format!(" ORDER BY \"{weight}\""));
     let mut stmt = conn.prepare(&sql)?;
     let edges = stmt
-        .query_map([], |r| {
+        .query_map(rusqlite::params_from_iter(params.iter()), |r| {
             Ok(Edge {
                 gen_id:    r.get(0)?,
                 src_id:    r.get(1)?,
                 dst_id:    r.get(2)?,
                 weight:    r.get(3)?,
-                edge_type: r.get(4)?,
+                // legacy rows may carry a NULL type
+                edge_type: r.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                directed:  r.get(5)?,
+                reverse_weight: r.get(6)?,
             })
         })?
         .collect::<SqlResult<Vec<_>>>()?;
     Ok(edges)
 }
 
+/// Column names of `table` (empty when the table does not exist).
+pub fn table_columns(conn: &Connection, table: &str) -> SqlResult<HashSet<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let cols = stmt
+        .query_map([table], |r| r.get::<_, String>(0))?
+        .collect::<SqlResult<_>>()?;
+    Ok(cols)
+}
+
+pub fn count_edges(conn: &Connection, edge_type: Option<&str>) -> SqlResult<u64> {
+    let n: i64 = match edge_type {
+        Some(t) => conn.query_row(
+            "SELECT COUNT(*) FROM similarity_edges WHERE edge_type = ?1",
+            [t],
+            |r| r.get(0),
+        )?,
+        None => conn.query_row("SELECT COUNT(*) FROM similarity_edges", [], |r| r.get(0))?,
+    };
+    Ok(n as u64)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EdgeTypeCount {
+    /// `None` for legacy rows with a NULL edge_type
+    pub edge_type: Option<String>,
+    pub count:     u64,
+}
+
+/// A sorted page of the distinct node ids in similarity_edges (src and dst
+/// alike), plus the total number of distinct ids.
+pub fn distinct_nodes(
+    conn: &Connection,
+    edge_type: Option<&str>,
+    limit: Option<u64>,
+    offset: u64,
+) -> SqlResult<(u64, Vec<String>)> {
+    let filter = if edge_type.is_some() { " WHERE edge_type = ?1" } else { "" };
+    let universe = format!(
+        "SELECT src_id AS node FROM similarity_edges{filter} UNION SELECT dst_id FROM similarity_edges{filter}"
+    );
+    let params: Vec<&str> = edge_type.into_iter().collect();
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM ({universe})"),
+        rusqlite::params_from_iter(&params),
+        |r| r.get(0),
+    )?;
+    // SQLite reads a negative LIMIT as "no limit"; values past i64::MAX
+    // saturate rather than wrap negative
+    let limit = limit.map_or(-1, |n| i64::try_from(n).unwrap_or(i64::MAX));
+    let offset = i64::try_from(offset).unwrap_or(i64::MAX);
+    let mut stmt = conn.prepare(&format!("{universe} ORDER BY node LIMIT {limit} OFFSET {offset}"))?;
+    let nodes = stmt
+        .query_map(rusqlite::params_from_iter(&params), |r| r.get(0))?
+        .collect::<SqlResult<Vec<String>>>()?;
+    Ok((total as u64, nodes))
+}
+
+/// Rows per edge_type, most common first (ties by name, NULL last).
+pub fn edge_type_counts(conn: &Connection) -> SqlResult<Vec<EdgeTypeCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT edge_type, COUNT(*) FROM similarity_edges GROUP BY edge_type
+         ORDER BY COUNT(*) DESC, edge_type IS NULL, edge_type",
+    )?;
+    let rows = stmt
+        .query_map([], |r| Ok(EdgeTypeCount { edge_type: r.get(0)?, count: r.get::<_, i64>(1)? as u64 }))?
+        .collect::<SqlResult<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Abort before loading when the edge count exceeds `limit` (unless `force`).
+/// Returns the counted edges so callers can log it.
+pub fn enforce_edge_limit(
+    conn: &Connection,
+    edge_type: Option<&str>,
+    limit: Option<u64>,
+    force: bool,
+) -> anyhow::Result<u64> {
+    let count = count_edges(conn, edge_type)?;
+    if let Some(max) = limit {
+        if count > max {
+            if !force {
+                anyhow::bail!(
+                    "edge count {} exceeds --edge-limit {} (use --force to load anyway)",
+                    count,
+                    max
+                );
+            }
+            tracing::warn!(count, limit = max, "Edge limit exceeded, continuing under --force");
+        }
+    }
+    Ok(count)
+}
+
+/// `path_json` for a stored path: the node array, or — past `max_nodes` — an
+/// object keeping the first and last nodes, the omitted count and the true hops.
+pub fn stored_path_json(path: &[String], max_nodes: Option<usize>) -> String {
+    match max_nodes {
+        Some(max) if path.len() > max.max(2) => {
+            let max = max.max(2);
+            let (head, tail) = (max.div_ceil(2), max / 2);
+            serde_json::json!({
+                "head":    &path[..head],
+                "tail":    &path[path.len() - tail..],
+                "omitted": path.len() - head - tail,
+                "hops":    path.len() - 1,
+            })
+            .to_string()
+        }
+        _ => serde_json::to_string(path).unwrap_or_default(),
+    }
+}
+
+/// Store `result` in path_results; `params` go to `params_json` on databases
+/// migrated to V004 and are dropped on older ones.
 pub fn persist_path(
     conn: &Connection,
     result: &PathResult,
     run_id: Option<&str>,
+    params: &QueryParams,
 ) -> SqlResult<String> {
     let id_rec = id_gen::generate(conn, "path", None)?;
-    let path_json = serde_json::to_string(&result.path).unwrap_or_default();
+    let path_json = stored_path_json(&result.path, params.max_path_store);
+    let params_json = table_columns(conn, "path_results")?
+        .contains("params_json")
+        .then(|| serde_json::to_string(params).unwrap_or_default());
+    let (params_col, params_val) = if params_json.is_some() { (",params_json", ",?8") } else { ("", "") };
+    let mut values: Vec<&dyn rusqlite::ToSql> = vec![
+        &id_rec.gen_id,
+        &result.src_id,
+        &result.dst_id,
+        &path_json,
+        &result.total_weight,
+        &result.algorithm,
+        &run_id,
+    ];
+    if let Some(p) = &params_json {
+        values.push(p);
+    }
     conn.execute(
-        "INSERT INTO path_results
-           (gen_id, src_id, dst_id, path_json, total_weight, algorithm, run_id)
-         VALUES (?1,?2,?3,?4,?5,?6,?7)",
-        rusqlite::params![
-            id_rec.gen_id,
-            result.src_id,
-            result.dst_id,
-            path_json,
-            result.total_weight,
-            result.algorithm,
-            run_id,
-        ],
+        &format!(
+            "INSERT INTO path_results
+               (gen_id, src_id, dst_id, path_json, total_weight, algorithm, run_id{params_col})
+             VALUES (?1,?2,?3,?4,?5,?6,?7{params_val})"
+        ),
+        values.as_slice(),
     )?;
     Ok(id_rec.gen_id)
 }
 
 // ── Public entry point ────────────────────────────────────────────────────────
 
+/// Wall-clock milliseconds per query phase, reported under `--profile`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PhaseTimings {
+    pub load:    f64,
+    pub sort:    f64,
+    pub build:   f64,
+    pub search:  f64,
+    pub persist: f64,
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1e3
+}
+
+/// Load → filter → quicksort → adjacency, per `opts`.
+pub fn load_graph(conn: &Connection, opts: &GraphOptions) -> SqlResult<Adjacency> {
+    load_graph_timed(conn, opts, &mut PhaseTimings::default())
+}
+
+/// `load_graph`, recording the load, sort and build phases in `timings`.
+pub fn load_graph_timed(conn: &Connection, opts: &GraphOptions, timings: &mut PhaseTimings) -> SqlResult<Adjacency> {
+    let edges = load_sorted_edges(conn, opts, timings)?;
+    build_graph(conn, &edges, opts, timings)
+}
+
+/// `load_graph`, keeping the hop index `weight_by_type` reads.
+pub fn load_typed_graph(conn: &Connection, opts: &GraphOptions) -> SqlResult<Graph> {
+    load_typed_graph_timed(conn, opts, &mut PhaseTimings::default())
+}
+
+/// `load_typed_graph`, recording the load, sort and build phases in `timings`.
+pub fn load_typed_graph_timed(conn: &Connection, opts: &GraphOptions, timings: &mut PhaseTimings) -> SqlResult<Graph> {
+    let edges = load_sorted_edges(conn, opts, timings)?;
+    let adj = build_graph(conn, &edges, opts, timings)?;
+    Ok(Graph::new(&edges, adj, opts))
+}
+
+/// Load, filter and quicksort the edges, recording the load and sort phases.
+fn load_sorted_edges(conn: &Connection, opts: &GraphOptions, timings: &mut PhaseTimings) -> SqlResult<Vec<Edge>> {
+    let start = Instant::now();
+    let mut edges = load_edges_with(conn, opts)?;
+    opts.apply(&mut edges);
+    timings.load = elapsed_ms(start);
+    tracing::info!(edge_count = edges.len(), "Loaded edges, running quicksort");
+
+    let start = Instant::now();
+    quicksort_edges(&mut edges);
+    timings.sort = elapsed_ms(start);
+    Ok(edges)
+}
+
+/// Adjacency (plus node weights when enabled) from sorted edges, recording the build phase.
+fn build_graph(conn: &Connection, edges: &[Edge], opts: &GraphOptions, timings: &mut PhaseTimings) -> SqlResult<Adjacency> {
+    let start = Instant::now();
+    let mut adj = build_adjacency_with(edges, opts);
+    if opts.use_node_weights {
+        apply_node_weights(&mut adj, &load_node_weights(conn)?);
+    }
+    timings.build = elapsed_ms(start);
+    if opts.warn_components {
+        crate::components::warn_if_disconnected(&adj);
+    }
+    Ok(adj)
+}
+
+/// Edge rows behind each directed hop, `from → to → [(stored weight, edge type)]`.
+pub type HopTypes = HashMap<String, HashMap<String, Vec<(f64, String)>>>;
+
+/// An adjacency map plus the edge type behind each of its hops, so a found
+/// path can be split by type without rescanning the edge list.
+pub struct Graph {
+    pub adj:       Adjacency,
+    pub hop_types: HopTypes,
+    /// `is_undirected(adj)`, worked out on the first canonical search
+    undirected:    OnceLock<bool>,
+}
+
+impl Graph {
+    /// Index the hops of `edges`, the rows `adj` was built from.
+    pub fn new(edges: &[Edge], adj: Adjacency, opts: &GraphOptions) -> Self {
+        let mut hop_types = HopTypes::new();
+        let mut add = |from: &str, to: &str, w: f64, e: &Edge| {
+            hop_types
+                .entry(from.to_string())
+                .or_default()
+                .entry(to.to_string())
+                .or_default()
+                .push((w, e.edge_type.clone()));
+        };
+        for e in edges {
+            add(&e.src_id, &e.dst_id, e.weight, e);
+            if !e.directed.unwrap_or(opts.directed) {
+                add(&e.dst_id, &e.src_id, e.reverse_weight.unwrap_or(e.weight), e);
+            }
+        }
+        Graph { adj, hop_types, undirected: OnceLock::new() }
+    }
+
+    pub fn undirected(&self) -> bool {
+        *self.undirected.get_or_init(|| is_undirected(&self.adj))
+    }
+
+    /// `search` over this graph, checking for one-way arcs at most once.
+    pub fn search(&self, src: &str, dst: &str, opts: &SearchOptions) -> Result<Option<PathResult>, SearchTooLarge> {
+        search(&self.adj, opts.canonical && self.undirected(), src, dst, opts)
+    }
+}
+
+/// Shape raw edge rows per `opts` — filters, quicksort, adjacency — keeping
+/// the hop index. Node weights are not applied.
+pub fn graph_from_rows(mut edges: Vec<Edge>, opts: &GraphOptions) -> Graph {
+    opts.apply(&mut edges);
+    quicksort_edges(&mut edges);
+    let adj = build_adjacency_with(&edges, opts);
+    Graph::new(&edges, adj, opts)
+}
+
+/// Split a path's cost by edge type. Each hop's searched weight goes to the
+/// type of the edge that produced it — among the rows joining the two nodes
+/// in the hop's direction, the one whose stored weight is closest (the
+/// lightest wins under the default parallel-edge policy).
+pub fn weight_by_type(graph: &Graph, path: &[String]) -> HashMap<String, f64> {
+    let mut by_type: HashMap<String, f64> = HashMap::new();
+    for hop in path.windows(2) {
+        let (u, v) = (hop[0].as_str(), hop[1].as_str());
+        let Some(w) = graph
+            .adj
+            .get(u)
+            .into_iter()
+            .flatten()
+            .filter(|(n, _)| n == v)
+            .map(|(_, w)| *w)
+            .reduce(f64::min)
+        else {
+            continue;
+        };
+        let edge_type = graph
+            .hop_types
+            .get(u)
+            .and_then(|to| to.get(v))
+            .into_iter()
+            .flatten()
+            .min_by(|a, b| (a.0 - w).abs().total_cmp(&(b.0 - w).abs()))
+            .map(|(_, t)| t.clone())
+            .unwrap_or_default();
+        *by_type.entry(edge_type).or_insert(0.0) += w;
+    }
+    by_type
+}
+
+/// Fill `r.weight_by_type` when the search scores a path by summing its hops;
+/// under any other score a per-type split would not add up to the total.
+pub fn split_by_type(graph: &Graph, search_opts: &SearchOptions, r: &mut PathResult) {
+    if search_opts.path_score == PathScore::Sum && search_opts.algorithm == Algorithm::Dijkstra {
+        r.weight_by_type = weight_by_type(graph, &r.path);
+    }
+}
+
+/// Per-node pass-through costs from the optional `node_weights(node_id, weight)`
+/// table; empty when the table does not exist.
+pub fn load_node_weights(conn: &Connection) -> SqlResult<HashMap<String, f64>> {
+    if table_columns(conn, "node_weights")?.is_empty() {
+        tracing::warn!("--use-node-weights: no node_weights table, treating all node weights as 0");
+        return Ok(HashMap::new());
+    }
+    let mut stmt = conn.prepare("SELECT node_id, weight FROM node_weights")?;
+    let weights = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, f64>(1)?)))?
+        .collect::<SqlResult<_>>()?;
+    Ok(weights)
+}
+
+/// Fold node costs into the directed hop weights: entering `v` costs
+/// `edge + node_weights[v]` (missing nodes cost 0), which is the same as
+/// adding the destination node's weight during relaxation.
+pub fn apply_node_weights(adj: &mut Adjacency, node_weights: &HashMap<String, f64>) {
+    if node_weights.is_empty() {
+        return;
+    }
+    for neighbours in adj.values_mut() {
+        for (next, w) in neighbours.iter_mut() {
+            *w += node_weights.get(next).copied().unwrap_or(0.0);
+        }
+        neighbours.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap_or(Ordering::Equal));
+    }
+}
+
 pub fn compute_and_persist(
     conn: &Connection,
     src_id: &str,
     dst_id: &str,
-    edge_type: Option<&str>,
+    opts: &GraphOptions,
+    search_opts: &SearchOptions,
     run_id: Option<&str>,
-) -> SqlResult<Option<PathResult>> {
-    let mut edges = load_edges(conn, edge_type)?;
-    tracing::info!(edge_count = edges.len(), "Loaded edges, running quicksort");
-    quicksort_edges(&mut edges);
+) -> anyhow::Result<Option<PathResult>> {
+    compute_and_persist_timed(conn, src_id, dst_id, opts, search_opts, run_id, &mut PhaseTimings::default())
+        .map(|(found, _)| found)
+}
 
-    let adj = build_adjacency(&edges);
-    match dijkstra(&adj, src_id, dst_id) {
+/// `compute_and_persist`, recording every phase in `timings`; also hands back
+/// the graph it built so follow-up analyses need not load it again.
+pub fn compute_and_persist_timed(
+    conn: &Connection,
+    src_id: &str,
+    dst_id: &str,
+    opts: &GraphOptions,
+    search_opts: &SearchOptions,
+    run_id: Option<&str>,
+    timings: &mut PhaseTimings,
+) -> anyhow::Result<(Option<PathResult>, Graph)> {
+    let graph = load_typed_graph_timed(conn, opts, timings)?;
+    let start = Instant::now();
+    let found = checked_search(&graph, src_id, dst_id, opts, search_opts)?;
+    timings.search = elapsed_ms(start);
+    let start = Instant::now();
+    let params = QueryParams::new(opts, search_opts);
+    let tx = run_transaction(conn, run_id, search_opts)?;
+    let found = record_outcome(&tx, &graph.adj, found, src_id, dst_id, run_id, &params)?;
+    tx.commit()?;
+    timings.persist = elapsed_ms(start);
+    Ok((found, graph))
+}
+
+/// Search an already-built graph and persist the path when found.
+pub fn search_and_persist(
+    conn: &Connection,
+    graph: &Graph,
+    src_id: &str,
+    dst_id: &str,
+    opts: &GraphOptions,
+    search_opts: &SearchOptions,
+    run_id: Option<&str>,
+) -> anyhow::Result<Option<PathResult>> {
+    let found = checked_search(graph, src_id, dst_id, opts, search_opts)?;
+    let tx = run_transaction(conn, run_id, search_opts)?;
+    let found = record_outcome(&tx, &graph.adj, found, src_id, dst_id, run_id, &QueryParams::new(opts, search_opts))?;
+    tx.commit()?;
+    Ok(found)
+}
+
+/// Refuse a graph the query cannot be answered on, then search it and split
+/// the found path by edge type.
+fn checked_search(
+    graph: &Graph,
+    src_id: &str,
+    dst_id: &str,
+    opts: &GraphOptions,
+    search_opts: &SearchOptions,
+) -> anyhow::Result<Option<PathResult>> {
+    let adj = &graph.adj;
+    check_endpoints(adj, opts, &endpoints(src_id, dst_id, search_opts))?;
+    check_weights(adj, search_opts.strict)?;
+    path_score::check(adj, search_opts.path_score)?;
+    crate::integer_weights::check(adj, search_opts.integer_weights)?;
+    let mut found = graph.search(src_id, dst_id, search_opts)?;
+    if let Some(r) = found.as_mut() {
+        split_by_type(graph, search_opts, r);
+    }
+    Ok(found)
+}
+
+/// `search_and_persist` for `--src-set`/`--dst-set`: the cheapest path
+/// between the two node sets, persisted under its chosen endpoints.
+pub fn search_sets_and_persist(
+    conn: &Connection,
+    adj: &Adjacency,
+    sources: &[String],
+    targets: &[String],
+    opts: &GraphOptions,
+    search_opts: &SearchOptions,
+    run_id: Option<&str>,
+) -> anyhow::Result<Option<PathResult>> {
+    let named: Vec<&str> = sources.iter().chain(targets).map(String::as_str).collect();
+    check_endpoints(adj, opts, &named)?;
+    check_weights(adj, search_opts.strict)?;
+    path_score::check(adj, search_opts.path_score)?;
+    let found = dijkstra_sets(adj, sources, targets);
+    let (src_label, dst_label) = (sources.join(","), targets.join(","));
+    let tx = run_transaction(conn, run_id, search_opts)?;
+    let found = record_outcome(&tx, adj, found, &src_label, &dst_label, run_id, &QueryParams::new(opts, search_opts))?;
+    tx.commit()?;
+    Ok(found)
+}
+
+/// Nodes a query touches by name: src, dst and any `--via` waypoint.
+pub fn endpoints<'a>(src_id: &'a str, dst_id: &'a str, search_opts: &'a SearchOptions) -> Vec<&'a str> {
+    [src_id, dst_id].into_iter().chain(search_opts.via.as_deref()).collect()
+}
+
+/// Delete every path_results row of `run_id`; returns how many went.
+pub fn delete_run(conn: &Connection, run_id: &str) -> SqlResult<usize> {
+    conn.execute("DELETE FROM path_results WHERE run_id = ?1", [run_id])
+}
+
+/// Transaction to persist a query's paths in. Under `replace_run` it starts
+/// by deleting the earlier rows of `run_id`, so a rerun leaves one set.
+pub fn run_transaction<'c>(
+    conn: &'c Connection,
+    run_id: Option<&str>,
+    search_opts: &SearchOptions,
+) -> SqlResult<rusqlite::Transaction<'c>> {
+    let tx = conn.unchecked_transaction()?;
+    if let (true, Some(run_id)) = (search_opts.replace_run, run_id) {
+        let removed = delete_run(&tx, run_id)?;
+        tracing::info!(run_id = %run_id, removed, "Replacing earlier path_results of run");
+    }
+    Ok(tx)
+}
+
+/// Persist and log a found path, or log why there is none.
+fn record_outcome(
+    conn: &Connection,
+    adj: &Adjacency,
+    found: Option<PathResult>,
+    src_id: &str,
+    dst_id: &str,
+    run_id: Option<&str>,
+    params: &QueryParams,
+) -> anyhow::Result<Option<PathResult>> {
+    match found {
         Some(result) => {
-            let path_id = persist_path(conn, &result, run_id)?;
+            let path_id = persist_path(conn, &result, run_id, params)?;
             tracing::info!(
                 path_id = %path_id,
                 total_weight = result.total_weight,
@@ -233,6 +1519,10 @@ pub fn compute_and_persist(
             );
             Ok(Some(result))
         }
+        None if adj.is_empty() => {
+            tracing::warn!(src = %src_id, dst = %dst_id, "No edges loaded: graph is empty");
+            Ok(None)
+        }
         None => {
             tracing::warn!(src = %src_id, dst = %dst_id, "No path found");
             Ok(None)
@@ -244,15 +1534,45 @@ pub fn compute_and_persist(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_min_hops_skips_direct_edge() {
+        let e = |s: &str, d: &str, w: f64| edge(s, d, w).with_type("similarity");
+        let adj = build_adjacency(&[e("A", "C", 1.0), e("A", "B", 2.0), e("B", "C", 2.0)]);
+        let direct = search(&adj, false, "A", "C", &SearchOptions::default()).unwrap().unwrap();
+        assert_eq!(direct.path, vec!["A", "C"]);
+
+        let opts = SearchOptions { min_hops: Some(2), ..Default::default() };
+        let indirect = search(&adj, false, "A", "C", &opts).unwrap().unwrap();
+        assert_eq!(indirect.path, vec!["A", "B", "C"]);
+        assert_eq!(indirect.total_weight, 4.0);
+        // A-C-B-A-C would have three hops, but only simple paths count
+        let opts = SearchOptions { min_hops: Some(3), ..Default::default() };
+        assert!(search(&adj, false, "A", "C", &opts).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_dijkstra_sets_picks_cheapest_cross_pair() {
+        let e = |s: &str, d: &str, w: f64| edge(s, d, w).with_type("similarity");
+        // S = {a1, a2}, T = {z1, z2}: a1→z1 costs 5, a2→m→z2 costs 1 + 1.5
+        let adj = build_adjacency(&[e("a1", "z1", 5.0), e("a2", "m", 1.0), e("m", "z2", 1.5),
+                                    e("a1", "a2", 4.0)]);
+        let set = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let r = dijkstra_sets(&adj, &set(&["a1", "a2"]), &set(&["z1", "z2"])).unwrap();
+        assert_eq!((r.src_id.as_str(), r.dst_id.as_str()), ("a2", "z2"));
+        assert_eq!(r.path, vec!["a2", "m", "z2"]);
+        assert_eq!(r.total_weight, 2.5);
+        assert!(dijkstra_sets(&adj, &set(&["a1"]), &set(&["nowhere"])).is_none());
+    }
+
     #[test]
     fn test_quicksort_ascending() {
         let mut edges = vec![
             Edge { gen_id: "3".into(), src_id: "a".into(), dst_id: "b".into(),
-                   weight: 3.0, edge_type: "similarity".into() },
+                   weight: 3.0, edge_type: "similarity".into(), directed: None, reverse_weight: None },
             Edge { gen_id: "1".into(), src_id: "b".into(), dst_id: "c".into(),
-                   weight: 1.0, edge_type: "similarity".into() },
+                   weight: 1.0, edge_type: "similarity".into(), directed: None, reverse_weight: None },
             Edge { gen_id: "2".into(), src_id: "a".into(), dst_id: "c".into(),
-                   weight: 2.0, edge_type: "similarity".into() },
+                   weight: 2.0, edge_type: "similarity".into(), directed: None, reverse_weight: None },
         ];
         quicksort_edges(&mut edges);
         assert_eq!(edges[0].weight, 1.0);
@@ -264,15 +1584,555 @@ mod tests {
     fn test_dijkstra_finds_shortest() {
         let edges = vec![
             Edge { gen_id: "e1".into(), src_id: "A".into(), dst_id: "B".into(),
-                   weight: 1.0, edge_type: "sim".into() },
+                   weight: 1.0, edge_type: "sim".into(), directed: None, reverse_weight: None },
             Edge { gen_id: "e2".into(), src_id: "B".into(), dst_id: "C".into(),
-                   weight: 2.0, edge_type: "sim".into() },
+                   weight: 2.0, edge_type: "sim".into(), directed: None, reverse_weight: None },
             Edge { gen_id: "e3".into(), src_id: "A".into(), dst_id: "C".into(),
-                   weight: 10.0, edge_type: "sim".into() },
+                   weight: 10.0, edge_type: "sim".into(), directed: None, reverse_weight: None },
         ];
         let adj = build_adjacency(&edges);
         let result = dijkstra(&adj, "A", "C").unwrap();
         assert!(result.total_weight < 4.0); // A→B→C = 3, not A→C = 10
         assert_eq!(result.path, vec!["A", "B", "C"]);
     }
+
+    #[test]
+    fn test_edge_limit_aborts_above_and_proceeds_below() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT,
+                                            weight REAL, edge_type TEXT);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','B',1.0,'similarity'),
+               ('e2','B','C',2.0,'similarity'),
+               ('e3','A','C',5.0,'lineage');",
+        )
+        .unwrap();
+        assert!(enforce_edge_limit(&conn, None, Some(2), false).is_err());
+        assert_eq!(enforce_edge_limit(&conn, None, Some(2), true).unwrap(), 3);
+        assert_eq!(enforce_edge_limit(&conn, None, Some(3), false).unwrap(), 3);
+        assert_eq!(enforce_edge_limit(&conn, Some("similarity"), Some(2), false).unwrap(), 2);
+        assert_eq!(enforce_edge_limit(&conn, None, None, false).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_weight_ceiling_gates_path() {
+        let edges = vec![
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("B", "C", 5.0).with_id("e2"),
+        ];
+        let route = |ceiling: f64| {
+            let mut filtered = edges.clone();
+            GraphOptions { weight_ceiling: Some(ceiling), ..Default::default() }
+                .apply(&mut filtered);
+            dijkstra(&build_adjacency(&filtered), "A", "C")
+        };
+        assert!(route(3.0).is_none());
+        assert_eq!(route(5.0).unwrap().path, vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_max_hop_weight_skips_heavy_edge_on_optimal_path() {
+        // A-C (3) beats A-B-C (4) on total, but its single hop is the heaviest
+        let edges = vec![edge("A", "C", 3.0), edge("A", "B", 2.0), edge("B", "C", 2.0)];
+        assert_eq!(dijkstra(&build_adjacency(&edges), "A", "C").unwrap().path, vec!["A", "C"]);
+        let opts = GraphOptions { max_hop_weight: Some(2.5), ..Default::default() };
+        let r = dijkstra(&build_adjacency_with(&edges, &opts), "A", "C").unwrap();
+        assert_eq!(r.path, vec!["A", "B", "C"]);
+        assert_eq!(r.total_weight, 4.0);
+
+        // two light parallel rows summed into one 3.0 hop: the ceiling keeps them, the hop cap does not
+        let parallel = vec![edge("A", "C", 1.5), edge("A", "C", 1.5)];
+        let opts = GraphOptions {
+            weight_ceiling: Some(2.5),
+            parallel_policy: ParallelEdgePolicy::Sum,
+            ..Default::default()
+        };
+        assert!(dijkstra(&build_adjacency_with(&parallel, &opts), "A", "C").is_some());
+        let opts = GraphOptions { max_hop_weight: Some(2.5), ..opts };
+        assert!(dijkstra(&build_adjacency_with(&parallel, &opts), "A", "C").is_none());
+    }
+
+    #[test]
+    fn test_as_of_selects_time_valid_edges() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT, valid_from TEXT, valid_to TEXT);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','C',1.0,'lineage','2026-01-01T00:00:00Z','2026-06-01T00:00:00Z'),
+               ('e2','A','B',1.0,'lineage',NULL,NULL),
+               ('e3','B','C',1.0,'lineage','2026-03-01T00:00:00Z',NULL);",
+        )
+        .unwrap();
+        let path_at = |ts: &str| {
+            let opts = GraphOptions { as_of: Some(ts.into()), ..Default::default() };
+            let adj = load_graph(&conn, &opts).unwrap();
+            dijkstra(&adj, "A", "C").map(|r| r.path)
+        };
+        assert_eq!(path_at("2026-02-01T00:00:00Z").unwrap(), vec!["A", "C"]);
+        assert_eq!(path_at("2026-07-01T00:00:00Z").unwrap(), vec!["A", "B", "C"]);
+        assert!(path_at("2025-12-01T00:00:00Z").is_none());
+    }
+
+    #[test]
+    fn test_as_of_ignored_without_temporal_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT);
+             INSERT INTO similarity_edges VALUES ('e1','A','C',1.0,'lineage');",
+        )
+        .unwrap();
+        let opts = GraphOptions { as_of: Some("2026-01-01T00:00:00Z".into()), ..Default::default() };
+        assert_eq!(load_edges_with(&conn, &opts).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_weight_column_selects_confidence() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT, confidence REAL);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','C',1.0,'sim',9.0),
+               ('e2','A','B',1.0,'sim',1.0),
+               ('e3','B','C',1.0,'sim',1.0);",
+        )
+        .unwrap();
+        let path_with = |col: Option<&str>| {
+            let opts = GraphOptions { weight_column: col.map(String::from), ..Default::default() };
+            dijkstra(&load_graph(&conn, &opts).unwrap(), "A", "C").unwrap()
+        };
+        assert_eq!(path_with(None).path, vec!["A", "C"]);
+        let r = path_with(Some("confidence"));
+        assert_eq!(r.path, vec!["A", "B", "C"]);
+        assert_eq!(r.total_weight, 2.0);
+
+        let bad = GraphOptions { weight_column: Some("weight; DROP TABLE x".into()), ..Default::default() };
+        assert!(load_edges_with(&conn, &bad).is_err());
+    }
+
+    #[test]
+    fn test_custom_weight_fn_drives_path() {
+        let e = |id: &str, s: &str, d: &str, w: f64, t: &str| edge(s, d, w).with_id(id).with_type(t);
+        let edges = vec![
+            e("e1", "A", "C", 1.0, "category"),
+            e("e2", "A", "B", 2.0, "similarity"),
+            e("e3", "B", "C", 2.0, "similarity"),
+        ];
+        let stored = build_adjacency_with(&edges, &GraphOptions::default());
+        assert_eq!(dijkstra(&stored, "A", "C").unwrap().path, vec!["A", "C"]);
+
+        // penalise categorical edges so the similarity route wins
+        let penalise = |e: &Edge| if e.edge_type == "category" { 10.0 } else { e.weight };
+        let adj = build_adjacency_weighted(&edges, &GraphOptions::default(), &penalise);
+        let r = dijkstra(&adj, "A", "C").unwrap();
+        assert_eq!(r.path, vec!["A", "B", "C"]);
+        assert_eq!(r.total_weight, 4.0);
+        assert_eq!(adj["A"][0].0, "B");
+    }
+
+    #[test]
+    fn test_max_degree_prunes_hub() {
+        let e = |s: &str, d: &str, w: f64| edge(s, d, w).with_type("category");
+        // H is a generic hub touching everything; A-B-C is the specific route
+        let edges = vec![
+            e("A", "H", 0.1), e("C", "H", 0.1), e("X", "H", 0.1), e("Y", "H", 0.1),
+            e("A", "B", 1.0), e("B", "C", 1.0),
+        ];
+        let adj = build_adjacency_with(&edges, &GraphOptions::default());
+        assert_eq!(dijkstra(&adj, "A", "C").unwrap().path, vec!["A", "H", "C"]);
+
+        let opts = GraphOptions { max_degree: Some(3), ..Default::default() };
+        let adj = build_adjacency_with(&edges, &opts);
+        assert!(is_pruned(&adj, "H"));
+        assert!(check_endpoints(&adj, &opts, &["A", "C"]).is_ok());
+        assert_eq!(dijkstra(&adj, "A", "C").unwrap().path, vec!["A", "B", "C"]);
+        assert!(check_endpoints(&adj, &opts, &["H", "C"]).is_err());
+    }
+
+    #[test]
+    fn test_node_prefix_reroutes_around_other_nodes() {
+        let e = |s: &str, d: &str, w: f64| edge(s, d, w).with_type("lineage");
+        let edges = vec![
+            e("entity-a", "lineage-x", 0.5), e("lineage-x", "entity-c", 0.5),
+            e("entity-a", "entity-b", 1.0), e("entity-b", "entity-c", 1.0),
+        ];
+        let adj = build_adjacency_with(&edges, &GraphOptions::default());
+        assert_eq!(dijkstra(&adj, "entity-a", "entity-c").unwrap().path, vec!["entity-a", "lineage-x", "entity-c"]);
+
+        let opts = GraphOptions { node_prefix: Some("entity-".into()), ..Default::default() };
+        let adj = build_adjacency_with(&edges, &opts);
+        assert!(!adj.contains_key("lineage-x"));
+        let r = dijkstra(&adj, "entity-a", "entity-c").unwrap();
+        assert_eq!(r.path, vec!["entity-a", "entity-b", "entity-c"]);
+        assert!(check_endpoints(&adj, &opts, &["entity-a", "entity-c"]).is_ok());
+        let err = check_endpoints(&adj, &opts, &["entity-a", "lineage-x"]).unwrap_err().to_string();
+        assert!(err.contains("--node-prefix entity-"), "{}", err);
+    }
+
+    #[test]
+    fn test_include_untyped_adds_null_type_edges() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','B',1.0,'lineage'), ('e2','B','C',1.0,NULL), ('e3','C','D',1.0,'category');",
+        )
+        .unwrap();
+        let mut opts = GraphOptions { edge_type: Some("lineage".into()), ..Default::default() };
+        let ids = |opts: &GraphOptions| -> Vec<String> {
+            load_edges_with(&conn, opts).unwrap().into_iter().map(|e| e.gen_id).collect()
+        };
+        assert_eq!(ids(&opts), vec!["e1"]);
+        opts.include_untyped = true;
+        assert_eq!(ids(&opts), vec!["e1", "e2"]);
+    }
+
+    #[test]
+    fn test_max_path_store_truncates_long_path() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE path_results (gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT,
+                                        path_json TEXT, total_weight REAL, algorithm TEXT,
+                                        computed_at TEXT, run_id TEXT);",
+        )
+        .unwrap();
+        let nodes: Vec<String> = (0..10).map(|i| format!("n{}", i)).collect();
+        let edges: Vec<Edge> = nodes
+            .windows(2)
+            .map(|w| edge(&w[0], &w[1], 0.5).with_id(&w[0]).with_type("lineage"))
+            .collect();
+        let graph = graph_from_rows(edges, &GraphOptions::default());
+        let opts = SearchOptions { max_path_store: Some(4), ..Default::default() };
+        let r = search_and_persist(&conn, &graph, "n0", "n9", &GraphOptions::default(), &opts, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(r.path.len(), 10, "reported path stays complete");
+
+        let (json, weight): (String, f64) = conn
+            .query_row("SELECT path_json, total_weight FROM path_results", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        let stored: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored["head"], serde_json::json!(["n0", "n1"]));
+        assert_eq!(stored["tail"], serde_json::json!(["n8", "n9"]));
+        assert_eq!(stored["omitted"], 6);
+        assert_eq!(stored["hops"], 9);
+        assert_eq!(weight, 4.5);
+    }
+
+    #[test]
+    fn test_node_weights_reroute_around_heavy_node() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT);
+             CREATE TABLE node_weights (node_id TEXT PRIMARY KEY, weight REAL);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','H',1.0,'sim'), ('e2','H','D',1.0,'sim'),
+               ('e3','A','B',2.0,'sim'), ('e4','B','D',2.0,'sim');
+             INSERT INTO node_weights VALUES ('H', 5.0), ('D', 0.5);",
+        )
+        .unwrap();
+        let plain = load_graph(&conn, &GraphOptions::default()).unwrap();
+        assert_eq!(dijkstra(&plain, "A", "D").unwrap().path, vec!["A", "H", "D"]);
+
+        let opts = GraphOptions { use_node_weights: true, ..Default::default() };
+        let r = dijkstra(&load_graph(&conn, &opts).unwrap(), "A", "D").unwrap();
+        assert_eq!(r.path, vec!["A", "B", "D"]);
+        assert_eq!(r.total_weight, 4.5);
+    }
+
+    #[test]
+    fn test_directed_column_mixes_one_way_and_two_way_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT, directed INTEGER);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','B',1.0,'lineage',1), ('e2','B','C',1.0,'sim',0), ('e3','C','D',1.0,'sim',NULL);",
+        )
+        .unwrap();
+        let adj = load_graph(&conn, &GraphOptions::default()).unwrap();
+        assert!(dijkstra(&adj, "A", "D").is_some());
+        assert!(dijkstra(&adj, "B", "A").is_none(), "directed row must be one-way");
+        assert!(dijkstra(&adj, "C", "B").is_some());
+        assert!(dijkstra(&adj, "D", "C").is_some(), "NULL falls back to undirected");
+
+        let opts = GraphOptions { directed: true, ..Default::default() };
+        let adj = load_graph(&conn, &opts).unwrap();
+        assert!(dijkstra(&adj, "C", "B").is_some(), "explicit 0 stays two-way");
+        assert!(dijkstra(&adj, "D", "C").is_none(), "NULL falls back to --directed");
+    }
+
+    #[test]
+    fn test_weight_by_type_on_mixed_path() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT);
+             CREATE TABLE path_results (gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT,
+                                        path_json TEXT, total_weight REAL, algorithm TEXT,
+                                        computed_at TEXT, run_id TEXT);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','B',0.5,'similarity'), ('e2','B','C',2.0,'lineage'),
+               ('e3','C','D',0.25,'similarity'), ('e4','B','C',3.0,'category');",
+        )
+        .unwrap();
+        let (g, s) = (GraphOptions::default(), SearchOptions::default());
+        let r = compute_and_persist(&conn, "A", "D", &g, &s, None).unwrap().unwrap();
+        assert_eq!(r.total_weight, 2.75);
+        assert_eq!(r.weight_by_type.len(), 2);
+        assert_eq!(r.weight_by_type["similarity"], 0.75);
+        assert_eq!(r.weight_by_type["lineage"], 2.0);
+        assert_eq!(r.weight_by_type.values().sum::<f64>(), r.total_weight);
+    }
+
+    #[test]
+    fn test_excluding_optimal_edge_reroutes() {
+        let e = |id: &str, s: &str, d: &str, w: f64| edge(s, d, w).with_id(id);
+        let edges = vec![e("e1", "A", "B", 1.0), e("e2", "B", "C", 1.0), e("e3", "A", "C", 5.0)];
+        let route = |exclude: &[&str]| {
+            let exclude_edges = exclude.iter().map(|s| s.to_string()).collect();
+            let opts = GraphOptions { exclude_edges, ..Default::default() };
+            let mut kept = edges.clone();
+            opts.apply(&mut kept);
+            dijkstra(&build_adjacency_with(&kept, &opts), "A", "C").map(|r| (r.path, r.total_weight))
+        };
+        assert_eq!(route(&[]), Some((vec!["A".into(), "B".into(), "C".into()], 2.0)));
+        assert_eq!(route(&["e2"]), Some((vec!["A".into(), "C".into()], 5.0)));
+        assert_eq!(route(&["e2", "e3"]), None);
+    }
+
+    #[test]
+    fn test_negative_edge_warns_or_errors_under_strict() {
+        let e = |s: &str, d: &str, w: f64| edge(s, d, w).with_id(&format!("{}{}", s, d));
+        let adj = build_adjacency(&[e("A", "B", 2.0), e("B", "C", -1.5)]);
+        assert_eq!(check_weights(&adj, false).unwrap(), 1);
+        let err = check_weights(&adj, true).unwrap_err().to_string();
+        assert!(err.contains("Bellman-Ford") && err.contains("-1.5"), "{}", err);
+        assert_eq!(check_weights(&build_adjacency(&[e("A", "B", 2.0)]), true).unwrap(), 0);
+
+        // a one-way row from the larger id has no mirror and still counts
+        let one_way = Edge { directed: Some(true), ..e("B", "A", -1.0) };
+        let adj = build_adjacency(&[one_way, e("B", "C", 2.0)]);
+        assert_eq!(check_weights(&adj, false).unwrap(), 1);
+        assert!(check_weights(&adj, true).is_err());
+    }
+
+    #[test]
+    fn test_profile_records_every_phase() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT);
+             CREATE TABLE path_results (gen_id TEXT PRIMARY KEY, src_id TEXT, dst_id TEXT,
+                                        path_json TEXT, total_weight REAL, algorithm TEXT,
+                                        computed_at TEXT, run_id TEXT);
+             INSERT INTO similarity_edges VALUES ('e1','A','B',1.0,'sim'), ('e2','B','C',1.0,'sim');",
+        )
+        .unwrap();
+        let mut timings = PhaseTimings::default();
+        let (g, s) = (GraphOptions::default(), SearchOptions::default());
+        let (found, graph) = compute_and_persist_timed(&conn, "A", "C", &g, &s, None, &mut timings).unwrap();
+        assert!(found.is_some());
+        assert_eq!(graph.adj.len(), 3);
+        let json = serde_json::to_value(&timings).unwrap();
+        for phase in ["load", "sort", "build", "search", "persist"] {
+            let ms = json[phase].as_f64().unwrap_or_else(|| panic!("missing phase {}", phase));
+            assert!(ms >= 0.0, "{} = {}", phase, ms);
+        }
+    }
+
+    #[test]
+    fn test_capacity_hint_preserves_result() {
+        let edges = vec![
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("B", "C", 2.0).with_id("e2"),
+        ];
+        let hinted = build_adjacency_with(
+            &edges,
+            &GraphOptions { node_hint: Some(1024), ..Default::default() },
+        );
+        assert!(hinted.capacity() >= 1024);
+        let plain = build_adjacency(&edges);
+        assert_eq!(dijkstra(&hinted, "A", "C").unwrap().path,
+                   dijkstra(&plain, "A", "C").unwrap().path);
+    }
+
+    #[test]
+    fn test_parallel_edge_policies() {
+        let edges = vec![
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("B", "A", 3.0).with_id("e2").with_type("lineage"),
+        ];
+        let combined = |policy| {
+            let adj = build_adjacency_with(
+                &edges,
+                &GraphOptions { parallel_policy: policy, ..Default::default() },
+            );
+            assert_eq!(adj["A"].len(), 1);
+            assert_eq!(adj["B"], vec![("A".to_string(), adj["A"][0].1)]);
+            adj["A"][0].1
+        };
+        assert_eq!(combined(ParallelEdgePolicy::Min), 1.0);
+        assert_eq!(combined(ParallelEdgePolicy::Max), 3.0);
+        assert_eq!(combined(ParallelEdgePolicy::Sum), 4.0);
+        assert_eq!(combined(ParallelEdgePolicy::Mean), 2.0);
+    }
+
+    #[test]
+    fn test_count_paths_on_diamond() {
+        // A→B→D and A→C→D both cost 2; A→D direct costs 5
+        let edges: Vec<Edge> = [("A", "B", 1.0), ("B", "D", 1.0), ("A", "C", 0.5),
+                                ("C", "D", 1.5), ("A", "D", 5.0)]
+            .iter()
+            .enumerate()
+            .map(|(i, (s, d, w))| edge(s, d, *w).with_id(&format!("e{}", i)))
+            .collect();
+        let adj = build_adjacency(&edges);
+        let c = count_shortest_paths(&adj, "A", "D").unwrap();
+        assert_eq!(c.count, 2);
+        assert_eq!(c.total_weight, 2.0);
+        assert!(!c.saturated);
+        assert!(count_shortest_paths(&adj, "A", "Z").is_none());
+    }
+
+    #[test]
+    fn test_canonical_path_ignores_query_direction() {
+        let edges = vec![
+            edge("A", "X", 1.0).with_id("e1"),
+            edge("X", "B", 1.0).with_id("e2"),
+            edge("A", "Y", 1.0).with_id("e3"),
+            edge("Y", "B", 1.0).with_id("e4"),
+        ];
+        let graph = graph_from_rows(edges, &GraphOptions::default());
+        let opts = SearchOptions { canonical: true, ..Default::default() };
+        let fwd = graph.search("A", "B", &opts).unwrap().unwrap();
+        let rev = graph.search("B", "A", &opts).unwrap().unwrap();
+        assert_eq!(rev.src_id, "A");
+        assert_eq!(rev.path, fwd.path);
+        assert_eq!(rev.path.first().map(String::as_str), Some("A"));
+
+        let one_way = Edge { directed: Some(true), ..edge("B", "A", 1.0) };
+        let one_way = graph_from_rows(vec![one_way], &GraphOptions::default());
+        assert!(!one_way.undirected());
+        let found = one_way.search("B", "A", &opts).unwrap().unwrap();
+        assert_eq!(found.path, vec!["B", "A"]);
+    }
+
+    #[test]
+    fn test_via_routes_through_waypoint() {
+        // A-B direct costs 1; the mandated review node R costs 2 + 3 to reach B
+        let edges = vec![
+            edge("A", "B", 1.0).with_id("e1"),
+            edge("A", "R", 2.0).with_id("e2"),
+            edge("R", "B", 3.0).with_id("e3"),
+        ];
+        let adj = build_adjacency(&edges);
+        let opts = SearchOptions { via: Some("R".into()), ..Default::default() };
+        let r = search(&adj, false, "A", "B", &opts).unwrap().unwrap();
+        assert_eq!(r.path, vec!["A", "R", "B"]);
+        assert_eq!(r.total_weight, 5.0);
+        assert_eq!((r.src_id.as_str(), r.dst_id.as_str()), ("A", "B"));
+
+        let opts = SearchOptions { via: Some("Z".into()), ..Default::default() };
+        assert!(search(&adj, false, "A", "B", &opts).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_max_open_states_aborts_search() {
+        // a chain whose every node also fans out to five cheap dead ends:
+        // the first expansion alone queues six states against a cap of four
+        let mut edges = Vec::new();
+        for i in 0..20 {
+            edges.push(edge(&format!("n{i}"), &format!("n{}", i + 1), 10.0).with_id(&format!("c{i}")));
+            for j in 0..5 {
+                edges.push(edge(&format!("n{i}"), &format!("leaf{i}-{j}"), 1.0).with_id(&format!("f{i}-{j}")));
+            }
+        }
+        let adj = build_adjacency(&edges);
+        let capped = SearchOptions { max_open_states: Some(4), ..Default::default() };
+        let err = search(&adj, false, "n0", "n20", &capped).unwrap_err();
+        assert_eq!(err, SearchTooLarge { limit: 4 });
+        assert!(err.to_string().contains("SearchTooLarge"));
+
+        let found = search(&adj, false, "n0", "n20", &SearchOptions::default()).unwrap().unwrap();
+        assert_eq!(found.total_weight, 200.0);
+    }
+
+    #[test]
+    fn test_reverse_weight_column_drives_each_direction() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL,
+                                            edge_type TEXT, reverse_weight REAL);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','B',1.0,'lineage',4.0),
+               ('e2','B','C',2.0,'lineage',NULL);",
+        )
+        .unwrap();
+        let adj = load_graph(&conn, &GraphOptions::default()).unwrap();
+        assert_eq!(dijkstra(&adj, "A", "B").unwrap().total_weight, 1.0);
+        assert_eq!(dijkstra(&adj, "B", "A").unwrap().total_weight, 4.0);
+        // NULL mirrors the forward weight
+        assert_eq!(dijkstra(&adj, "C", "B").unwrap().total_weight, 2.0);
+        assert_eq!(dijkstra(&adj, "C", "A").unwrap().total_weight, 6.0);
+    }
+
+    #[test]
+    fn test_limit_neighbors_keeps_lightest_k() {
+        let edges = vec![edge("H", "A", 3.0), edge("H", "B", 1.0), edge("H", "C", 2.0), edge("A", "B", 1.5)];
+        let opts = GraphOptions { limit_neighbors: Some(2), ..Default::default() };
+        let adj = build_adjacency_with(&edges, &opts);
+        assert!(adj.values().all(|n| n.len() <= 2));
+        let hub: Vec<_> = adj["H"].iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(hub, vec!["B", "C"]);
+    }
+
+    #[test]
+    fn test_edge_type_counts_by_frequency() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL, edge_type TEXT);
+             INSERT INTO similarity_edges VALUES
+               ('e1','A','B',1,'lineage'), ('e2','B','C',1,'similarity'), ('e3','C','D',1,'lineage'),
+               ('e4','D','E',1,NULL), ('e5','E','F',1,'lineage'), ('e6','F','G',1,'category');",
+        )
+        .unwrap();
+        let counts: Vec<_> = edge_type_counts(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.edge_type, c.count))
+            .collect();
+        assert_eq!(counts, vec![
+            (Some("lineage".to_string()), 3),
+            (Some("category".to_string()), 1),
+            (Some("similarity".to_string()), 1),
+            (None, 1),
+        ]);
+    }
+
+    #[test]
+    fn test_distinct_nodes_paginates_sorted_union() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE similarity_edges (gen_id TEXT, src_id TEXT, dst_id TEXT, weight REAL, edge_type TEXT);
+             INSERT INTO similarity_edges VALUES
+               ('e1','C','A',1,'lineage'), ('e2','A','B',1,'lineage'), ('e3','D','E',1,'similarity'),
+               ('e4','B','C',1,'lineage');",
+        )
+        .unwrap();
+        let (total, all) = distinct_nodes(&conn, None, None, 0).unwrap();
+        assert_eq!(total, 5);
+        assert_eq!(all, vec!["A", "B", "C", "D", "E"]);
+        let (total, page) = distinct_nodes(&conn, None, Some(2), 2).unwrap();
+        assert_eq!((total, page), (5, vec!["C".to_string(), "D".to_string()]));
+        let (total, lineage) = distinct_nodes(&conn, Some("lineage"), None, 1).unwrap();
+        assert_eq!((total, lineage), (3, vec!["B".to_string(), "C".to_string()]));
+        assert_eq!(distinct_nodes(&conn, None, Some(u64::MAX), 3).unwrap().1, vec!["D", "E"]);
+        assert!(distinct_nodes(&conn, None, None, u64::MAX).unwrap().1.is_empty());
+    }
 }
@@ -0,0 +1,154 @@
+//! validate.rs — Singine graph validation
+//!
+//! Data-quality checks over the raw edge list, reported by
+//! `--mode validate-graph`. Checks flag suspicious modelling rather than
+//! rejecting the graph; stewards reconcile the findings upstream.
+//!
+//! Checks:
+//!   - mixed_type_pairs: node pairs joined by more than one edge_type
+//!   - unit_suspects: edge types whose weights cluster both within [0,1] and
+//!     an order of magnitude above 1 (fractions mixed with percentages)
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::shortest_path::Edge;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MixedTypePair {
+    pub src_id:     String,
+    pub dst_id:     String,
+    pub edge_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnitSuspect {
+    pub edge_type:   String,
+    pub edges:       usize,
+    /// weights ≤ 1
+    pub unit_range:  usize,
+    /// weights > 1
+    pub above_one:   usize,
+    pub unit_median: f64,
+    pub high_median: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphReport {
+    pub edges:            usize,
+    pub mixed_type_pairs: Vec<MixedTypePair>,
+    pub unit_suspects:    Vec<UnitSuspect>,
+    /// total number of findings across all checks
+    pub issues:           usize,
+}
+
+pub fn validate(edges: &[Edge]) -> GraphReport {
+    let mixed = mixed_type_pairs(edges);
+    let units = unit_suspects(edges);
+    let issues = mixed.len() + units.len();
+    if issues > 0 {
+        tracing::warn!(issues, "Graph validation findings");
+    }
+    GraphReport { edges: edges.len(), mixed_type_pairs: mixed, unit_suspects: units, issues }
+}
+
+/// Smallest share of a type's edges each cluster needs, so a lone outlier is not flagged.
+const UNIT_MIN_SHARE: f64 = 0.1;
+/// Median ratio between the two clusters that suggests different units.
+const UNIT_MEDIAN_RATIO: f64 = 10.0;
+
+fn median(sorted: &[f64]) -> f64 {
+    let n = sorted.len();
+    if n % 2 == 1 { sorted[n / 2] } else { (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0 }
+}
+
+/// Edge types whose weights split into a [0,1] cluster and a much larger one.
+pub fn unit_suspects(edges: &[Edge]) -> Vec<UnitSuspect> {
+    let mut by_type: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for e in edges {
+        by_type.entry(e.edge_type.as_str()).or_default().push(e.weight);
+    }
+    by_type
+        .into_iter()
+        .filter_map(|(edge_type, mut weights)| {
+            weights.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            let split = weights.partition_point(|&w| w <= 1.0);
+            let (low, high) = weights.split_at(split);
+            let min_count = ((weights.len() as f64 * UNIT_MIN_SHARE).ceil() as usize).max(1);
+            if low.len() < min_count || high.len() < min_count {
+                return None;
+            }
+            let (unit_median, high_median) = (median(low), median(high));
+            (high_median >= UNIT_MEDIAN_RATIO * unit_median.max(f64::MIN_POSITIVE)).then(|| UnitSuspect {
+                edge_type: edge_type.to_string(),
+                edges: weights.len(),
+                unit_range: low.len(),
+                above_one: high.len(),
+                unit_median,
+                high_median,
+            })
+        })
+        .collect()
+}
+
+/// Unordered node pairs connected by edges of more than one type.
+pub fn mixed_type_pairs(edges: &[Edge]) -> Vec<MixedTypePair> {
+    let mut types: BTreeMap<(&str, &str), BTreeSet<&str>> = BTreeMap::new();
+    for e in edges {
+        let key = if e.src_id <= e.dst_id {
+            (e.src_id.as_str(), e.dst_id.as_str())
+        } else {
+            (e.dst_id.as_str(), e.src_id.as_str())
+        };
+        types.entry(key).or_default().insert(e.edge_type.as_str());
+    }
+    types
+        .into_iter()
+        .filter(|(_, t)| t.len() > 1)
+        .map(|((s, d), t)| MixedTypePair {
+            src_id:     s.to_string(),
+            dst_id:     d.to_string(),
+            edge_types: t.into_iter().map(String::from).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortest_path::edge;
+
+    #[test]
+    fn test_flags_mixed_unit_edge_type() {
+        let weighted = |id: &str, t: &str, w: f64| Edge { weight: w, ..edge(id, "Z", 1.0).with_id(id).with_type(t) };
+        let edges = vec![
+            // similarity: fractions and percentages mixed
+            weighted("s1", "similarity", 0.2), weighted("s2", "similarity", 0.35),
+            weighted("s3", "similarity", 0.8), weighted("s4", "similarity", 40.0),
+            weighted("s5", "similarity", 75.0),
+            // lineage: hop counts, consistently ≥ 1
+            weighted("l1", "lineage", 1.0), weighted("l2", "lineage", 2.0),
+            weighted("l3", "lineage", 3.0),
+        ];
+        let suspects = unit_suspects(&edges);
+        assert_eq!(suspects.len(), 1);
+        assert_eq!(suspects[0].edge_type, "similarity");
+        assert_eq!((suspects[0].unit_range, suspects[0].above_one), (3, 2));
+        assert_eq!(validate(&edges).issues, 1);
+    }
+
+    #[test]
+    fn test_flags_pair_with_two_types() {
+        let edges = vec![
+            edge("A", "B", 1.0).with_id("e1").with_type("similarity"),
+            edge("B", "A", 1.0).with_id("e2").with_type("lineage"),
+            edge("B", "C", 1.0).with_id("e3").with_type("similarity"),
+            edge("B", "C", 1.0).with_id("e4").with_type("similarity"),
+        ];
+        let report = validate(&edges);
+        assert_eq!(report.issues, 1);
+        let pair = &report.mixed_type_pairs[0];
+        assert_eq!((pair.src_id.as_str(), pair.dst_id.as_str()), ("A", "B"));
+        assert_eq!(pair.edge_types, vec!["lineage", "similarity"]);
+    }
+}
@@ -0,0 +1,92 @@
+//! webhook.rs — Singine result emission (feature `emit-http`)
+//!
+//! POSTs the result JSON to `--emit-http <url>` once it has been persisted,
+//! so event-driven pipelines can react without polling the DB. Delivery is
+//! best-effort: the caller logs a failure and carries on. Connecting is
+//! given 5 s and each read or write 10 s, so an unresponsive endpoint
+//! cannot hold the process open. Both `http://` and `https://` URLs work;
+//! TLS is rustls with the bundled webpki root certificates.
+
+use serde_json::Value;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn post_json(url: &str, body: &Value) -> anyhow::Result<u16> {
+    post_json_within(url, body, CONNECT_TIMEOUT, IO_TIMEOUT)
+}
+
+fn post_json_within(url: &str, body: &Value, connect: Duration, io: Duration) -> anyhow::Result<u16> {
+    let agent = ureq::AgentBuilder::new().timeout_connect(connect).timeout_read(io).timeout_write(io).build();
+    let resp = agent
+        .post(url)
+        .set("Content-Type", "application/json")
+        .send_string(&serde_json::to_string(body)?)?;
+    Ok(resp.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_posts_payload_to_mock_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut len = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    len = v.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(body).unwrap()
+        });
+
+        let payload = json!({"ok": true, "src": "A", "dst": "C", "total_weight": 3.0});
+        assert_eq!(post_json(&url, &payload).unwrap(), 200);
+        let received: Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!(received, payload);
+    }
+
+    #[test]
+    fn test_silent_server_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        // accepts the connection but never answers
+        let server = std::thread::spawn(move || listener.accept().map(|(stream, _)| stream));
+        let start = std::time::Instant::now();
+        let short = Duration::from_millis(200);
+        assert!(post_json_within(&url, &json!({"ok": true}), short, short).is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        drop(server.join().unwrap());
+    }
+
+    #[test]
+    fn test_https_is_a_known_scheme() {
+        // nothing listens on the port, so the attempt fails at connect, not on the scheme
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let err = post_json(&format!("https://127.0.0.1:{}/hook", port), &json!({})).unwrap_err();
+        match err.downcast_ref::<ureq::Error>() {
+            Some(ureq::Error::Transport(t)) => assert_eq!(t.kind(), ureq::ErrorKind::ConnectionFailed, "{}", t),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
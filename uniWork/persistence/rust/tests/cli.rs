@@ -0,0 +1,29 @@
+//! End-to-end checks that run the built `persistence` binary.
+
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::Command;
+
+/// The `db` a `--mode status` run reports, under `flags` and an optional
+/// `$SINGINE_DB`, run from `dir`.
+fn status_db(dir: &Path, flags: &[&str], env: Option<&Path>) -> Value {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_persistence"));
+    cmd.current_dir(dir).args(["--mode", "status"]).args(flags).env_remove("SINGINE_DB");
+    if let Some(db) = env {
+        cmd.env("SINGINE_DB", db);
+    }
+    let out = cmd.output().unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    serde_json::from_slice::<Value>(&out.stdout).unwrap()["db"].clone()
+}
+
+#[test]
+fn test_db_path_from_env_unless_flag_given() {
+    let dir = std::env::temp_dir().join(format!("singine-env-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let from_env = dir.join("from-env.db");
+    assert_eq!(status_db(&dir, &[], Some(&from_env)), json!(from_env.to_string_lossy()));
+    assert_eq!(status_db(&dir, &["--db", "flag.db"], Some(&from_env)), json!("flag.db"));
+    assert_eq!(status_db(&dir, &[], None), json!("singine.db"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
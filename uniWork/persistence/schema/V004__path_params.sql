@@ -0,0 +1,10 @@
+-- V004__path_params.sql — Query parameters behind each stored path
+
+-- ── path_results.params_json ──────────────────────────────────────────────────
+-- The effective query options (edge_type filter, weight column, direction,
+-- parallel-edge policy, search settings) as a JSON object, so a stored path
+-- can be reproduced. NULL for rows persisted before V004.
+ALTER TABLE path_results ADD COLUMN params_json TEXT;
+
+INSERT OR IGNORE INTO schema_migrations (version, description, checksum)
+VALUES ('V004', 'Path params: path_results.params_json', 'sha256:placeholder_V004');